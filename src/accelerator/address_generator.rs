@@ -0,0 +1,238 @@
+//! # Description
+//! - generate the memory requests of a window in its dataflow, the addresses come from `MemoryLayout`
+//! - the sparse dataflow reads and writes the sparse features(`nnz * (index + value)` of each node),
+//!   the dense dataflow reads and writes the dense features(`dim * element_bytes` of each node)
+//! - the input buffer loads both slots by the generator of the window, and the writeback writes the output
+//!   of layer `l` into the features of layer `l + 1` by the generator of the next layer's dataflow
+//! - with the `Window` granularity of `Mixed` mode, the windows of the next layer read in different dataflows,
+//!   so each node is written back by the generators of the windows that read it, see `NodeReadModes`
+//!
+
+use std::fmt::Debug;
+
+use super::{memory_layout::MemoryLayout, sliding_window::InputWindow};
+use crate::{error::GcnAggError, settings::RunningMode};
+
+/// # Description
+/// - the address generation of one dataflow
+pub trait AddressGenerator: Debug {
+    /// the requests to read the input nodes `start..end` of `layer`
+    fn read_requests(
+        &self,
+        layout: &MemoryLayout,
+        layer: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<u64>, GcnAggError>;
+    /// the requests to write the output nodes `start..end` of `layer`, they are the input nodes of `layer + 1`
+    fn write_requests(
+        &self,
+        layout: &MemoryLayout,
+        layer: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<u64>, GcnAggError> {
+        self.read_requests(layout, layer + 1, start, end)
+    }
+}
+
+/// # Description
+/// - the addresses of the sparse features
+#[derive(Debug)]
+pub struct SparseAddressGenerator;
+
+impl AddressGenerator for SparseAddressGenerator {
+    fn read_requests(
+        &self,
+        layout: &MemoryLayout,
+        layer: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<u64>, GcnAggError> {
+        Ok(layout.sparse_requests(layer, start, end))
+    }
+}
+
+/// # Description
+/// - the addresses of the dense features, the layout should place them(the running mode is not `Sparse`)
+#[derive(Debug)]
+pub struct DenseAddressGenerator;
+
+impl AddressGenerator for DenseAddressGenerator {
+    fn read_requests(
+        &self,
+        layout: &MemoryLayout,
+        layer: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<u64>, GcnAggError> {
+        layout.dense_requests(layer, start, end)
+    }
+}
+
+/// # Description
+/// - the generator of the resolved dataflow of a window
+/// # Errors
+/// - `InvalidState` for `Mixed`, each window should be either sparse or dense
+pub fn get_address_generator(
+    running_mode: &RunningMode,
+) -> Result<&'static dyn AddressGenerator, GcnAggError> {
+    match running_mode {
+        RunningMode::Sparse => Ok(&SparseAddressGenerator),
+        RunningMode::Dense => Ok(&DenseAddressGenerator),
+        RunningMode::Mixed => Err(GcnAggError::InvalidState(
+            "the window should be either sparse or dense".into(),
+        )),
+    }
+}
+
+/// # Description
+/// - the dataflows that the windows of a layer read each input node in
+/// - a node read by windows of both dataflows is written back in both, a node read by no window is written back
+///   in the dataflow of `fallback`
+#[derive(Debug, Clone)]
+pub struct NodeReadModes {
+    sparse: Vec<bool>,
+    dense: Vec<bool>,
+    fallback: RunningMode,
+}
+
+impl NodeReadModes {
+    pub fn new<'a>(
+        num_nodes: usize,
+        windows: impl IntoIterator<Item = InputWindow<'a>>,
+        fallback: RunningMode,
+    ) -> Self {
+        let mut sparse = vec![false; num_nodes];
+        let mut dense = vec![false; num_nodes];
+        for window in windows {
+            let read = match window.get_output_window().get_running_mode() {
+                RunningMode::Dense => &mut dense,
+                _ => &mut sparse,
+            };
+            read[window.start_input_index..window.end_input_index].fill(true);
+        }
+        NodeReadModes {
+            sparse,
+            dense,
+            fallback,
+        }
+    }
+
+    /// is the node `i` read in `mode`
+    fn is_read(&self, i: usize, mode: &RunningMode) -> bool {
+        let (read, other) = match mode {
+            RunningMode::Dense => (&self.dense, &self.sparse),
+            _ => (&self.sparse, &self.dense),
+        };
+        read[i] || (!other[i] && *mode == self.fallback)
+    }
+
+    /// # Description
+    /// - the requests to write the output nodes `start..end` of `layer` in every dataflow they are read in,
+    ///   each run of the nodes read in one dataflow is written by its generator
+    pub fn write_requests(
+        &self,
+        layout: &MemoryLayout,
+        layer: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<u64>, GcnAggError> {
+        let mut requests = vec![];
+        for mode in [RunningMode::Sparse, RunningMode::Dense] {
+            let generator = get_address_generator(&mode)?;
+            let mut i = start;
+            while i < end {
+                let run_start = i;
+                while i < end && self.is_read(i, &mode) {
+                    i += 1;
+                }
+                if run_start < i {
+                    requests.extend(generator.write_requests(layout, layer, run_start, i)?);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        // the runs of the sparse features can share a request
+        requests.sort_unstable();
+        requests.dedup();
+        Ok(requests)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        gcn_result::RegionKind, graph::generators, node_features::NodeFeatures,
+        settings::LayoutSettings,
+    };
+
+    #[test]
+    fn test_address_generator() -> Result<(), GcnAggError> {
+        let graph = generators::grid(2, 4, 16)?;
+        // 8 nodes of 16 dims, node i has i + 1 nonzeros(8 bytes each)
+        let rows = |dim: usize| -> Vec<Vec<f32>> {
+            (0..8)
+                .map(|i| (0..dim).map(|j| (j <= i) as u8 as f32).collect())
+                .collect()
+        };
+        let node_features = vec![
+            NodeFeatures::from_dense(&rows(16)),
+            NodeFeatures::from_dense(&rows(8)),
+        ];
+        let settings = LayoutSettings {
+            alignment: 1024,
+            row_padding: false,
+            channels: 1,
+            interleave_bytes: 256,
+        };
+        let layout =
+            MemoryLayout::new(&graph, &node_features, &[8], &RunningMode::Mixed, &settings)?;
+        let sparse = get_address_generator(&RunningMode::Sparse)?;
+        let dense = get_address_generator(&RunningMode::Dense)?;
+
+        // the sparse nodes 2..5 are bytes 24..120 of the features region of layer 0
+        assert_eq!(sparse.read_requests(&layout, 0, 2, 5)?, vec![0, 64]);
+        // the dense nodes are 64 bytes each, after the 288 bytes of the sparse features
+        assert_eq!(
+            dense.read_requests(&layout, 0, 2, 5)?,
+            vec![1024 + 128, 1024 + 192, 1024 + 256]
+        );
+
+        // the writeback of layer 0 goes to the regions of layer 1
+        let regions = layout.report().regions;
+        let region = |kind| {
+            regions
+                .iter()
+                .find(|x| x.layer == 1 && x.kind == kind)
+                .map(|x| x.start)
+                .unwrap()
+        };
+        let features = region(RegionKind::Features);
+        let dense_features = region(RegionKind::DenseFeatures);
+        assert_eq!(
+            sparse.write_requests(&layout, 0, 0, 8)?,
+            (0..5).map(|x| features + x * 64).collect::<Vec<_>>()
+        );
+        // 8 dims of 4 bytes, 2 nodes each request
+        assert_eq!(
+            dense.write_requests(&layout, 0, 4, 8)?,
+            vec![dense_features + 128, dense_features + 192]
+        );
+
+        assert!(get_address_generator(&RunningMode::Mixed).is_err());
+        // no dense features in a sparse-only layout
+        let layout = MemoryLayout::new(
+            &graph,
+            &node_features,
+            &[8],
+            &RunningMode::Sparse,
+            &settings,
+        )?;
+        assert!(dense.read_requests(&layout, 0, 0, 1).is_err());
+        assert_eq!(sparse.read_requests(&layout, 0, 7, 8)?, vec![192, 256]);
+        Ok(())
+    }
+}
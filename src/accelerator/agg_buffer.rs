@@ -1,6 +1,6 @@
 use std::{mem::swap, rc::Rc};
 
-use crate::settings::RunningMode;
+use crate::{error::GcnAggError, settings::RunningMode};
 
 use super::{component::Component, sliding_window::OutputWindow, temp_agg_result::TempAggResult};
 #[derive(Debug, PartialEq)]
@@ -51,7 +51,11 @@ impl AggBuffer {
                 Some(TempAggResult::new(num_nodes)),
             ),
             RunningMode::Dense => (None, None),
-            RunningMode::Mixed => (todo!()),
+            // the sparse windows in mixed mode still need the temp result
+            RunningMode::Mixed => (
+                Some(TempAggResult::new(num_nodes)),
+                Some(TempAggResult::new(num_nodes)),
+            ),
         };
 
         AggBuffer {
@@ -87,7 +91,7 @@ impl Component for AggBuffer {
     ///
     /// ```
     ///
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
         if let (BufferStatus::WaitingToMlp, BufferStatus::Empty) =
             (&self.current_state, &self.next_state)
         {
@@ -113,15 +117,22 @@ impl AggBuffer {
         self.current_state = BufferStatus::Writing;
     }
 
-    pub(super) fn start_mlp(&mut self) {
+    pub(super) fn start_mlp(&mut self) -> Result<(), GcnAggError> {
         if !matches!(self.next_state, BufferStatus::WaitingToMlp) {
-            panic!("start_mlp: current state is not waiting to mlp");
+            return Err(GcnAggError::InvalidState(format!(
+                "agg_buffer start_mlp: next state is {:?}, not waiting to mlp",
+                self.next_state
+            )));
         }
         self.next_state = BufferStatus::Mlp;
+        Ok(())
     }
-    pub(super) fn finished_mlp(&mut self) {
+    pub(super) fn finished_mlp(&mut self) -> Result<(), GcnAggError> {
         if !matches!(self.next_state, BufferStatus::Mlp) {
-            panic!("finished_mlp: current state is not mlp");
+            return Err(GcnAggError::InvalidState(format!(
+                "agg_buffer finished_mlp: next state is {:?}, not mlp",
+                self.next_state
+            )));
         }
         self.next_state = BufferStatus::Empty;
         // fix bug here, clear the temp result after mlp
@@ -130,6 +141,7 @@ impl AggBuffer {
                 x.clear();
             });
         }
+        Ok(())
     }
 
     #[allow(dead_code)]
@@ -180,6 +192,7 @@ mod test {
             10,
             false,
             false,
+            RunningMode::Sparse,
         ));
         agg_buffer.add_task(output_window);
 
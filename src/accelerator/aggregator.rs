@@ -1,9 +1,10 @@
-use std::{
-    collections::{btree_set::Range, HashSet},
-    vec,
-};
+use std::{collections::HashSet, vec};
 
-use crate::node_features::NodeFeatures;
+use crate::{
+    error::GcnAggError,
+    node_features::NodeFeatures,
+    settings::{Normalization, RunningMode},
+};
 
 use super::{
     component::Component, sliding_window::InputWindow, temp_agg_result::TempAggResult,
@@ -24,6 +25,8 @@ pub struct Aggregator {
 
     dense_cores: usize,
     dense_width: usize,
+    // the normalization of the graph, which costs the extra multiplies to scale the features
+    normalization: Normalization,
 
     pub state: AggregatorState,
     // last_output_id: usize,
@@ -39,7 +42,7 @@ impl Component for Aggregator {
     /// aggregator.cycle();
     /// ```
     ///
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
         if self.state == AggregatorState::Working {
             if self.current_task_remaining_cycles == 0 {
                 self.state = AggregatorState::Finished;
@@ -57,12 +60,14 @@ impl Aggregator {
         sparse_width: usize,
         dense_cores: usize,
         dense_width: usize,
+        normalization: Normalization,
     ) -> Aggregator {
         Aggregator {
             sparse_cores,
             sparse_width,
             dense_cores,
             dense_width,
+            normalization,
             state: AggregatorState::Idle,
             // last_output_id: 0,
             current_task_id: None,
@@ -76,8 +81,9 @@ impl Aggregator {
         node_features: &NodeFeatures,
         temp_agg_result: &mut Option<TempAggResult>,
     ) {
-        match temp_agg_result {
-            Some(temp_agg_result) => {
+        // in mixed mode, the temp result is always there, so the window decide the dataflow
+        match (task.get_output_window().get_running_mode(), temp_agg_result) {
+            (RunningMode::Sparse, Some(temp_agg_result)) => {
                 let tasks = task.get_tasks().clone();
                 // collect tasks to Vec<Vec<usize>>
                 let output_start = task.start_output_index;
@@ -87,20 +93,26 @@ impl Aggregator {
                     tasks,
                     &mut temp_agg_result[output_start..output_end],
                     node_features,
+                    task.is_last_row,
                 );
 
                 self.state = AggregatorState::Working;
                 self.current_task_id = Some(task.get_task_id().clone());
                 self.current_task_remaining_cycles = cycles;
             }
-            None => {
+            _ => {
                 // dense aggregation
-                let num_add = task
-                    .get_tasks()
-                    .iter()
-                    .fold(0, |acc, x| acc + x.clone().count());
+                let num_add = task.get_tasks().iter().fold(0, |acc, x| acc + x.len());
+                // the normalization scales the input nodes and the finished output nodes
+                let mut num_scale = 0;
+                if self.scales_input() {
+                    num_scale += num_add;
+                }
+                if self.scales_output(task.is_last_row) {
+                    num_scale += task.get_tasks().len();
+                }
                 let mut cycles: u64 = 0;
-                cycles += (num_add * task.get_output_window().get_input_dim()
+                cycles += ((num_add + num_scale) * task.get_output_window().get_input_dim()
                     / (self.dense_width * self.dense_cores)) as u64;
                 // extra cycle for load data
                 cycles *= 2;
@@ -127,6 +139,7 @@ impl Aggregator {
     /// node 5,6,7,8 will be aggregated to the second node,
     /// and node 9,10,11,12 will be aggregated to the third node
     /// * node_features - the node features is sparse format, each line is a node, each column is a feature index
+    /// * is_last_row - the output nodes are finished by this window, so they are scaled by the normalization
     ///
     /// # Return
     /// (the cycles to calculate each node, the node features of result nodes)
@@ -151,14 +164,20 @@ impl Aggregator {
     ///
     pub fn get_add_sparse_cycle(
         &mut self,
-        tasks: Vec<Range<usize>>,
+        tasks: Vec<&[u32]>,
         output_features: &mut [Vec<usize>],
         node_features: &NodeFeatures,
+        is_last_row: bool,
     ) -> u64 {
         // each task's cycles
         let mut cycle_vec = Vec::new();
         for (task, output_vec) in tasks.into_iter().zip(output_features.iter_mut()) {
-            cycle_vec.push(self.get_add_cycle_and_result_sparse(output_vec, task, node_features));
+            let mut cycles = self.get_add_cycle_and_result_sparse(output_vec, task, node_features);
+            // one multiply for each nonzero of the finished output node
+            if self.scales_output(is_last_row) {
+                cycles += output_vec.len() as u64;
+            }
+            cycle_vec.push(cycles);
         }
 
         // each cores current cycles, always push task to the core with the least cycles
@@ -206,7 +225,7 @@ impl Aggregator {
     fn get_add_cycle_and_result_sparse(
         &mut self,
         output_feature: &mut Vec<usize>,
-        input_nodes: Range<usize>,
+        input_nodes: &[u32],
         node_features: &NodeFeatures,
     ) -> u64 {
         let mut cycles = 0;
@@ -214,7 +233,12 @@ impl Aggregator {
         let mut temp_set: HashSet<usize> = output_feature.iter().cloned().collect();
 
         for &i in input_nodes {
+            let i = i as usize;
             cycles += temp_set.len() + node_features.get_features(i).len();
+            // one multiply for each nonzero of the input node
+            if self.scales_input() {
+                cycles += node_features.get_features(i).len();
+            }
             for &j in node_features.get_features(i) {
                 temp_set.insert(j);
             }
@@ -238,9 +262,40 @@ impl Aggregator {
         cycles as u64
     }
 
+    /// # Description
+    /// - each input node is scaled before it's added, only by the symmetric normalization
+    fn scales_input(&self) -> bool {
+        self.normalization == Normalization::Symmetric
+    }
+
+    /// # Description
+    /// - the output nodes are scaled once after the last input window
+    fn scales_output(&self, is_last_row: bool) -> bool {
+        is_last_row && self.normalization != Normalization::None
+    }
+
     pub fn finished_aggregation(&mut self) {
         self.state = AggregatorState::Idle;
     }
+
+    /// # Description
+    /// - the number of following cycles that the aggregator will only count down
+    /// - `u64::MAX` means it's idle and will never change by itself
+    pub(super) fn get_quiet_cycles(&self) -> u64 {
+        match self.state {
+            AggregatorState::Idle => u64::MAX,
+            AggregatorState::Working => self.current_task_remaining_cycles,
+            AggregatorState::Finished => 0,
+        }
+    }
+
+    /// # Description
+    /// - skip `cycles` cycles, `cycles` should not be larger than `get_quiet_cycles`
+    pub(super) fn fast_forward(&mut self, cycles: u64) {
+        if self.state == AggregatorState::Working {
+            self.current_task_remaining_cycles -= cycles;
+        }
+    }
 }
 
 // #[cfg(test)]
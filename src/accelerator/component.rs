@@ -1,3 +1,5 @@
+use crate::error::GcnAggError;
+
 pub trait Component {
-    fn cycle(&mut self)->Result<(), Box<dyn std::error::Error>>;
+    fn cycle(&mut self)->Result<(), GcnAggError>;
 }
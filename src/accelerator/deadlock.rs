@@ -0,0 +1,176 @@
+//! # Description
+//! - detect the deadlock of the system: no handler is triggered for `threshold` cycles
+//! - when a deadlock is found, the system builds a wait-for graph over the buffer states,
+//!   each edge is a component(and the window it holds) waiting for another component
+//!
+
+use std::fmt::Display;
+
+use super::window_id::WindowId;
+
+/// # Description
+/// - the components in the wait-for graph
+/// - `WindowIterator`: the sliding window that is not yet added to the input buffer
+/// - `Memory`: the memory backend behind the mem interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComponentName {
+    WindowIterator,
+    InputBuffer,
+    Aggregator,
+    AggBuffer,
+    Mlp,
+    SparsifyBuffer,
+    Sparsifier,
+    OutputBuffer,
+    WeightBuffer,
+    MemInterface,
+    Memory,
+}
+
+impl Display for ComponentName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ComponentName::WindowIterator => "window_iterator",
+            ComponentName::InputBuffer => "input_buffer",
+            ComponentName::Aggregator => "aggregator",
+            ComponentName::AggBuffer => "agg_buffer",
+            ComponentName::Mlp => "mlp",
+            ComponentName::SparsifyBuffer => "sparsify_buffer",
+            ComponentName::Sparsifier => "sparsifier",
+            ComponentName::OutputBuffer => "output_buffer",
+            ComponentName::WeightBuffer => "weight_buffer",
+            ComponentName::MemInterface => "mem_interface",
+            ComponentName::Memory => "memory",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// # Description
+/// - an edge of the wait-for graph: `waiter` cannot move on until `holder` makes progress
+/// - `window` is the window held by `waiter`, if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaitFor {
+    pub waiter: ComponentName,
+    pub holder: ComponentName,
+    pub window: Option<WindowId>,
+}
+
+impl WaitFor {
+    pub fn new(waiter: ComponentName, holder: ComponentName, window: Option<WindowId>) -> Self {
+        WaitFor {
+            waiter,
+            holder,
+            window,
+        }
+    }
+}
+
+impl Display for WaitFor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.window {
+            Some(id) => write!(
+                f,
+                "{}(layer {} output {} input {}) waits for {}",
+                self.waiter, id.layer_id, id.output_id, id.input_id, self.holder
+            ),
+            None => write!(f, "{} waits for {}", self.waiter, self.holder),
+        }
+    }
+}
+
+/// # Description
+/// - count the cycles that no handler is triggered and nothing is counting down, see `System::is_counting_down`
+/// # Fields
+/// - `threshold`: report a deadlock after this number of cycles without progress, 0 means never
+/// - `snapshot_path`: write the snapshot of the system to this file when a deadlock is found
+/// - `stalled_cycles`: the cycles since the last progress
+#[derive(Debug)]
+pub struct DeadlockDetector {
+    threshold: u64,
+    snapshot_path: Option<String>,
+    stalled_cycles: u64,
+}
+
+impl DeadlockDetector {
+    pub fn new(threshold: u64, snapshot_path: Option<String>) -> Self {
+        DeadlockDetector {
+            threshold,
+            snapshot_path,
+            stalled_cycles: 0,
+        }
+    }
+
+    /// # Description
+    /// - some handler is triggered, reset the counter
+    pub fn progress(&mut self) {
+        self.stalled_cycles = 0;
+    }
+
+    /// # Description
+    /// - nothing changed in the last `cycles` cycles
+    pub fn stall(&mut self, cycles: u64) {
+        self.stalled_cycles += cycles;
+    }
+
+    pub fn is_deadlocked(&self) -> bool {
+        self.threshold != 0 && self.stalled_cycles >= self.threshold
+    }
+
+    /// # Description
+    /// - the stalled cycles left before the deadlock is reported, `u64::MAX` if it's disabled
+    pub fn get_cycles_to_deadlock(&self) -> u64 {
+        match self.threshold {
+            0 => u64::MAX,
+            threshold => threshold.saturating_sub(self.stalled_cycles),
+        }
+    }
+
+    pub fn get_stalled_cycles(&self) -> u64 {
+        self.stalled_cycles
+    }
+
+    pub fn get_snapshot_path(&self) -> Option<&str> {
+        self.snapshot_path.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detector() {
+        let mut detector = DeadlockDetector::new(10, None);
+        detector.stall(9);
+        assert!(!detector.is_deadlocked());
+        detector.progress();
+        detector.stall(9);
+        assert!(!detector.is_deadlocked());
+        assert_eq!(detector.get_cycles_to_deadlock(), 1);
+        detector.stall(1);
+        assert!(detector.is_deadlocked());
+        assert_eq!(detector.get_stalled_cycles(), 10);
+
+        // never report
+        let mut detector = DeadlockDetector::new(0, None);
+        detector.stall(u64::MAX);
+        assert!(!detector.is_deadlocked());
+        assert_eq!(detector.get_cycles_to_deadlock(), u64::MAX);
+    }
+
+    #[test]
+    fn test_wait_for_display() {
+        let wait = WaitFor::new(
+            ComponentName::InputBuffer,
+            ComponentName::MemInterface,
+            Some(WindowId::new(1, 2, 0)),
+        );
+        assert_eq!(
+            wait.to_string(),
+            "input_buffer(layer 0 output 1 input 2) waits for mem_interface"
+        );
+        let wait = WaitFor::new(ComponentName::MemInterface, ComponentName::Memory, None);
+        assert_eq!(wait.to_string(), "mem_interface waits for memory");
+    }
+}
@@ -0,0 +1,405 @@
+//! # Description
+//! - the functional simulation: carry the real f32 values through the windows scheduled by the system
+//! - the aggregator accumulates `A·X` window by window, the mlp multiplies the weights,
+//!   the sparsifier applies relu(not in the final layer) and writes the input of the next layer
+//! - with the degree data of the graph, each input node is scaled before the accumulation and
+//!   each output node is scaled before the mlp, see `graph::preprocess::DegreeData`
+//! - in the end, the output of every layer is compared with a straightforward reference on the whole graph,
+//!   so a wrong tiling or a wrong order of the windows will be found
+//!
+//! # Notes
+//! - the values only depend on the first node features, the sparsity of the following layers is still
+//!   decided by the node features files, so the cycles are not changed by the functional simulation
+//! - the output nodes without any input edge are aggregated by empty windows, they stay zero like the reference
+
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::sliding_window::{InputWindow, OutputWindow};
+use crate::{
+    error::GcnAggError,
+    gcn_result::FunctionalStatistics,
+    graph::{preprocess::DegreeData, Graph},
+    node_features::NodeFeatures,
+    settings::FunctionalSettings,
+};
+
+/// # Description
+/// - the values of the simulated gcn
+/// # Fields
+/// - `weights`: the weight of each layer, `dims[layer]` rows and `dims[layer + 1]` columns
+/// - `features`: the dense input features of each layer, the last one is the output of the final layer
+/// - `agg_results`: the partial `A·X` of the output windows being aggregated, the key is (layer, output_id)
+/// - `mlp_results`: the results of the mlp waiting for the sparsifier, the key is (layer, output_id)
+/// - `degree_data`: the normalization of the graph, `None` for `A·X`
+#[derive(Debug)]
+pub struct FunctionalModel {
+    weights: Vec<Vec<Vec<f32>>>,
+    features: Vec<Vec<Vec<f32>>>,
+    degree_data: Option<DegreeData>,
+    agg_results: HashMap<(usize, usize), Vec<Vec<f32>>>,
+    mlp_results: HashMap<(usize, usize), Vec<Vec<f32>>>,
+    tolerance: f32,
+}
+
+impl FunctionalModel {
+    /// # Description
+    /// - build the model from the first node features, the weights are loaded or randomly generated
+    /// # Errors
+    /// - `Config` if the number of weight files does not match the number of layers
+    /// - `InputFormat` if a weight file or the node features do not match the dimensions
+    pub fn new(
+        graph: &Graph,
+        node_features: &NodeFeatures,
+        gcn_hidden_size: &[usize],
+        settings: &FunctionalSettings,
+    ) -> Result<Self, GcnAggError> {
+        let num_layers = gcn_hidden_size.len() + 1;
+        let dims = std::iter::once(graph.get_feature_size())
+            .chain(gcn_hidden_size.iter().cloned())
+            .chain(std::iter::once(settings.output_dim))
+            .collect::<Vec<_>>();
+
+        let weights = match settings.weights_paths.len() {
+            0 => {
+                let mut rng = StdRng::seed_from_u64(settings.seed);
+                (0..num_layers)
+                    .map(|layer| random_weights(dims[layer], dims[layer + 1], &mut rng))
+                    .collect()
+            }
+            n if n == num_layers => settings
+                .weights_paths
+                .iter()
+                .enumerate()
+                .map(|(layer, path)| load_weights(path, dims[layer], dims[layer + 1]))
+                .collect::<Result<Vec<_>, _>>()?,
+            n => {
+                return Err(GcnAggError::Config(format!(
+                    "there are {} weight files, but {} layers",
+                    n, num_layers
+                )))
+            }
+        };
+
+        if node_features.len() != graph.get_num_node() {
+            return Err(GcnAggError::input_format(
+                "node_features",
+                None,
+                format!(
+                    "there are {} nodes, but the graph has {} nodes",
+                    node_features.len(),
+                    graph.get_num_node()
+                ),
+            ));
+        }
+        let mut input = vec![vec![0.; dims[0]]; graph.get_num_node()];
+        for (node, row) in input.iter_mut().enumerate() {
+            let indices = node_features.get_features(node);
+            let values = node_features.get_values(node);
+            for (&index, &value) in indices.iter().zip(values) {
+                *row.get_mut(index).ok_or_else(|| {
+                    GcnAggError::input_format(
+                        "node_features",
+                        Some(node + 1),
+                        format!("feature {} is out of the feature size {}", index, dims[0]),
+                    )
+                })? = value;
+            }
+        }
+        let mut features = vec![input];
+        // the output of each layer is written by the sparsifier
+        features.extend(
+            dims[1..]
+                .iter()
+                .map(|&dim| vec![vec![0.; dim]; graph.get_num_node()]),
+        );
+
+        Ok(FunctionalModel {
+            weights,
+            features,
+            degree_data: graph.get_degree_data().cloned(),
+            agg_results: HashMap::new(),
+            mlp_results: HashMap::new(),
+            tolerance: settings.tolerance,
+        })
+    }
+
+    /// # Description
+    /// - the aggregator starts an input window, accumulate the input nodes into the output window
+    pub fn aggregate(&mut self, window: &InputWindow) {
+        let id = window.get_task_id();
+        let dim = self.weights[id.layer_id].len();
+        let rows = window.end_output_index - window.start_output_index;
+        let inputs = &self.features[id.layer_id];
+        let result = self
+            .agg_results
+            .entry((id.layer_id, id.output_id))
+            .or_insert_with(|| vec![vec![0.; dim]; rows]);
+        for (row, task) in result.iter_mut().zip(window.get_tasks()) {
+            for &input in task.iter() {
+                let scale = src_scale(self.degree_data.as_ref(), input as usize);
+                row.iter_mut()
+                    .zip(&inputs[input as usize])
+                    .for_each(|(x, y)| *x += scale * *y);
+            }
+        }
+    }
+
+    /// # Description
+    /// - the mlp starts an output window, multiply the aggregated result by the weight
+    /// # Errors
+    /// - `InvalidState` if the output window is never aggregated
+    pub fn mlp(&mut self, window: &OutputWindow) -> Result<(), GcnAggError> {
+        let id = window.get_task_id();
+        let key = (id.layer_id, id.output_id);
+        let aggregated = self.agg_results.remove(&key).ok_or_else(|| {
+            GcnAggError::InvalidState(format!("the mlp starts before aggregation: {:?}", id))
+        })?;
+        let weight = &self.weights[id.layer_id];
+        let result = aggregated
+            .iter()
+            .zip(window.start_output_index..window.end_output_index)
+            .map(|(x, node)| {
+                let x = scale(x, dst_scale(self.degree_data.as_ref(), node));
+                mat_vec(&x, weight)
+            })
+            .collect();
+        self.mlp_results.insert(key, result);
+        Ok(())
+    }
+
+    /// # Description
+    /// - the sparsifier starts an output window, apply relu and write the input of the next layer
+    /// # Errors
+    /// - `InvalidState` if the output window never goes through the mlp
+    pub fn sparsify(&mut self, window: &OutputWindow) -> Result<(), GcnAggError> {
+        let id = window.get_task_id();
+        let mut result = self
+            .mlp_results
+            .remove(&(id.layer_id, id.output_id))
+            .ok_or_else(|| {
+                GcnAggError::InvalidState(format!("the sparsifier starts before mlp: {:?}", id))
+            })?;
+        if !window.final_layer {
+            result.iter_mut().for_each(|x| relu(x));
+        }
+        let outputs = &mut self.features[id.layer_id + 1];
+        for (node, row) in (window.start_output_index..window.end_output_index).zip(result) {
+            outputs[node] = row;
+        }
+        Ok(())
+    }
+
+    /// # Description
+    /// - compare the output of every layer with the reference
+    /// # Errors
+    /// - `FunctionalMismatch` for the first output feature out of the tolerance
+    pub fn check(&self, graph: &Graph) -> Result<Vec<FunctionalStatistics>, GcnAggError> {
+        let reference = reference_gcn(graph, &self.features[0], &self.weights);
+        let mut stats = vec![];
+        for (layer, (expected, actual)) in reference.iter().zip(&self.features[1..]).enumerate() {
+            let mut max_error: f32 = 0.;
+            for (node, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                for (index, (&e, &a)) in expected.iter().zip(actual).enumerate() {
+                    let error = (e - a).abs();
+                    if error.is_nan() || error > self.tolerance * e.abs().max(1.) {
+                        return Err(GcnAggError::FunctionalMismatch {
+                            layer,
+                            node,
+                            message: format!(
+                                "feature {} is {}, but the reference is {}",
+                                index, a, e
+                            ),
+                        });
+                    }
+                    max_error = max_error.max(error);
+                }
+            }
+            stats.push(FunctionalStatistics {
+                layer,
+                output_dim: self.weights[layer][0].len(),
+                max_error,
+            });
+        }
+        Ok(stats)
+    }
+}
+
+/// # Description
+/// - the reference gcn on the whole graph: `relu(A·X·W)` for each layer, no relu in the final layer
+/// - `A` is normalized by the degree data of the graph if any
+/// - return the output of each layer
+pub fn reference_gcn(
+    graph: &Graph,
+    input: &[Vec<f32>],
+    weights: &[Vec<Vec<f32>>],
+) -> Vec<Vec<Vec<f32>>> {
+    let mut outputs: Vec<Vec<Vec<f32>>> = vec![];
+    for (layer, weight) in weights.iter().enumerate() {
+        let x = outputs.last().map(|x| x.as_slice()).unwrap_or(input);
+        let output = (0..graph.get_num_node())
+            .map(|i| {
+                let degree_data = graph.get_degree_data();
+                let mut aggregated = vec![0.; weight.len()];
+                for &j in graph.get_csc_row(i) {
+                    let scale = src_scale(degree_data, j as usize);
+                    aggregated
+                        .iter_mut()
+                        .zip(&x[j as usize])
+                        .for_each(|(a, b)| *a += scale * *b);
+                }
+                let aggregated = scale(&aggregated, dst_scale(degree_data, i));
+                let mut row = mat_vec(&aggregated, weight);
+                if layer != weights.len() - 1 {
+                    relu(&mut row);
+                }
+                row
+            })
+            .collect();
+        outputs.push(output);
+    }
+    outputs
+}
+
+/// # Description
+/// - the input features of layer 1, 2, ... computed by the reference gcn, only the nonzeros are kept
+/// - the values are the same as the functional simulation, so its sparsity can replace the features files
+pub fn reference_node_features(
+    graph: &Graph,
+    node_features: &NodeFeatures,
+    gcn_hidden_size: &[usize],
+    settings: &FunctionalSettings,
+) -> Result<Vec<NodeFeatures>, GcnAggError> {
+    let model = FunctionalModel::new(graph, node_features, gcn_hidden_size, settings)?;
+    let mut outputs = reference_gcn(graph, &model.features[0], &model.weights);
+    // the output of the final layer is not an input
+    outputs.pop();
+    Ok(outputs
+        .iter()
+        .map(|x| NodeFeatures::from_dense(x))
+        .collect())
+}
+
+fn src_scale(degree_data: Option<&DegreeData>, node: usize) -> f32 {
+    degree_data.map_or(1., |x| x.src_scale(node))
+}
+
+fn dst_scale(degree_data: Option<&DegreeData>, node: usize) -> f32 {
+    degree_data.map_or(1., |x| x.dst_scale(node))
+}
+
+fn scale(x: &[f32], scale: f32) -> Vec<f32> {
+    x.iter().map(|x| x * scale).collect()
+}
+
+/// `x·W`, `x` is a row vector
+fn mat_vec(x: &[f32], weight: &[Vec<f32>]) -> Vec<f32> {
+    let cols = weight.first().map(|w| w.len()).unwrap_or(0);
+    let mut result = vec![0.; cols];
+    for (&xi, row) in x.iter().zip(weight) {
+        result.iter_mut().zip(row).for_each(|(r, w)| *r += xi * w);
+    }
+    result
+}
+
+fn relu(x: &mut [f32]) {
+    x.iter_mut().for_each(|v| *v = v.max(0.));
+}
+
+/// # Description
+/// - the uniform xavier initialization
+fn random_weights(rows: usize, cols: usize, rng: &mut StdRng) -> Vec<Vec<f32>> {
+    let bound = (6. / (rows + cols) as f32).sqrt();
+    (0..rows)
+        .map(|_| (0..cols).map(|_| rng.gen_range(-bound..=bound)).collect())
+        .collect()
+}
+
+/// # Description
+/// - read the weight from the file, each line is a row of `cols` values, there are `rows` lines
+fn load_weights(path: &str, rows: usize, cols: usize) -> Result<Vec<Vec<f32>>, GcnAggError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| GcnAggError::io(path, e))?;
+    let weight = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(line_number, line)| {
+            let row = line
+                .split_whitespace()
+                .map(|x| x.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| GcnAggError::input_format(path, Some(line_number + 1), e))?;
+            match row.len() == cols {
+                true => Ok(row),
+                false => Err(GcnAggError::input_format(
+                    path,
+                    Some(line_number + 1),
+                    format!("expect {} values, got {}", cols, row.len()),
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match weight.len() == rows {
+        true => Ok(weight),
+        false => Err(GcnAggError::input_format(
+            path,
+            None,
+            format!("expect {} rows, got {}", rows, weight.len()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        graph::preprocess,
+        settings::{Normalization, PreprocessSettings},
+    };
+
+    #[test]
+    fn test_reference_gcn() -> Result<(), Box<dyn std::error::Error>> {
+        // node 0 aggregates node 1 and 2, node 1 aggregates node 0, node 2 aggregates nothing
+        let graph = Graph::from_rows(&[vec![1, 2], vec![0], vec![]], 2)?;
+        let input = vec![vec![1., 2.], vec![3., -4.], vec![0.5, 0.]];
+        let weights = vec![vec![vec![1., -1.], vec![0., 1.]], vec![vec![2.], vec![1.]]];
+        let outputs = reference_gcn(&graph, &input, &weights);
+        // A·X = [[3.5,-4],[1,2],[0,0]], ·W = [[3.5,-7.5],[1,1],[0,0]], relu
+        assert_eq!(outputs[0], vec![vec![3.5, 0.], vec![1., 1.], vec![0., 0.]]);
+        // A·X = [[1,1],[3.5,0],[0,0]], ·W = [[3],[7],[0]], no relu in the final layer
+        assert_eq!(outputs[1], vec![vec![3.], vec![7.], vec![0.]]);
+
+        // the mean of the input nodes
+        let settings = PreprocessSettings {
+            self_loops: false,
+            symmetrize: false,
+            dedup: true,
+            normalization: Normalization::Row,
+        };
+        let graph = preprocess::apply(&settings, graph)?;
+        let outputs = reference_gcn(&graph, &input, &weights);
+        // D^-1·A·X = [[1.75,-2],[1,2],[0,0]], ·W = [[1.75,-3.75],[1,1],[0,0]], relu
+        assert_eq!(outputs[0], vec![vec![1.75, 0.], vec![1., 1.], vec![0., 0.]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_weights() -> Result<(), Box<dyn std::error::Error>> {
+        let path = std::env::temp_dir().join("gcn_agg_weights_functional.txt");
+        std::fs::write(&path, "1 2\n3 4.5\n")?;
+        let path = path.to_string_lossy().to_string();
+        let path = path.as_str();
+        assert_eq!(load_weights(path, 2, 2)?, vec![vec![1., 2.], vec![3., 4.5]]);
+        assert!(matches!(
+            load_weights(path, 3, 2),
+            Err(GcnAggError::InputFormat { line: None, .. })
+        ));
+        assert!(matches!(
+            load_weights(path, 2, 3),
+            Err(GcnAggError::InputFormat { line: Some(1), .. })
+        ));
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use log::debug;
 
 use super::{component::Component, sliding_window::InputWindow, window_id::WindowId};
+use crate::error::GcnAggError;
 use std::mem::swap;
 #[derive(Debug, Clone)]
 pub enum BufferStatus {
@@ -40,7 +41,7 @@ impl Component for InputBuffer<'_> {
     ///
     /// ```
     ///
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
         match (&self.current_state, &self.next_state) {
             // both are empty, do nothing
             (BufferStatus::Empty, BufferStatus::Empty) => {}
@@ -82,7 +83,9 @@ impl<'a> InputBuffer<'a> {
     /// assert_eq!(input_buffer.next_state, BufferStatus::Ready(1));
     ///
     /// ```
-    pub fn receive(&mut self, id_: &WindowId) {
+    /// # Errors
+    /// * `InvalidState` if neither of the loading windows has the id
+    pub fn receive(&mut self, id_: &WindowId) -> Result<(), GcnAggError> {
         // test if id match any
         match (
             &self.current_state,
@@ -113,12 +116,13 @@ impl<'a> InputBuffer<'a> {
             }
 
             _ => {
-                panic!(
+                return Err(GcnAggError::InvalidState(format!(
                     "receive id: {:?} but current state is {:?} and next state is {:?}",
                     id_, self.current_state, self.next_state
-                );
+                )));
             }
         }
+        Ok(())
     }
     /// # Description
     /// * try to get a waiting id to send
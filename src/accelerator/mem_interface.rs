@@ -3,9 +3,12 @@ use std::{
 };
 
 use log::debug;
-use ramulator_wrapper::RamulatorWrapper;
 
-use super::{component::Component, window_id::WindowId};
+use super::{component::Component, memory_backend::MemoryBackend, window_id::WindowId};
+use crate::{
+    error::GcnAggError,
+    gcn_result::{ComponentStatus, MemoryStatistics, StallCause},
+};
 
 #[derive(Debug)]
 pub struct MemWindowIdust {
@@ -26,20 +29,33 @@ impl MemWindowIdust {
 /// # Description
 /// * the MemInterface is the interface between accelerator and memory
 /// # Fields
-/// * `mem`: the ramulator wrapper
+/// * `mem`: the memory backend, see `memory_backend.rs`
 /// * `send_queue`: the queue of requests to be sent to memory
 /// * `recv_queue`: the queue of requests to be received from memory
 /// * `current_waiting_request`: the current request id on flight,key is the request id, value is the request address
-/// * `current_waiting_mem_request`: the current request id on flight,key is the request addr, value is the request id that contains this addr
+/// * `current_waiting_mem_request`: the current request id on flight,key is the request addr, value is the request id that contains this addr,
+///   in the order they are issued, so the requests merged into one addr are returned in a deterministic order
+/// * `blocked_by_memory`: the memory refused a request in the last cycle
+/// * `blocked_by_recv_queue`: the memory had a response but the recv queue was full in the last cycle
+/// * `returned_in_last_cycle`: the memory returned a read in the last cycle
+/// * `issue_cycles`: the issue cycle and the layer of each in flight read addr
+/// * `stats`: the traffic statistics
+/// * `issued_writes`: the write requests that all addrs are sent to memory
 #[derive(Debug)]
 pub struct MemInterface {
-    mem: RamulatorWrapper,
+    mem: Box<dyn MemoryBackend>,
     send_queue: VecDeque<MemWindowIdust>,
     send_size: usize,
     recv_queue: VecDeque<WindowId>,
     recv_size: usize,
     current_waiting_request: HashMap<WindowId, HashSet<u64>>,
-    current_waiting_mem_request: HashMap<u64, HashSet<WindowId>>,
+    current_waiting_mem_request: HashMap<u64, Vec<WindowId>>,
+    blocked_by_memory: bool,
+    blocked_by_recv_queue: bool,
+    returned_in_last_cycle: bool,
+    issue_cycles: HashMap<u64, (u64, usize)>,
+    stats: MemoryStatistics,
+    issued_writes: VecDeque<WindowId>,
 }
 
 impl Component for MemInterface {
@@ -57,8 +73,12 @@ impl Component for MemInterface {
     ///
     /// - should merge the same addr
     ///
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
+        self.blocked_by_memory = false;
+        self.returned_in_last_cycle = false;
         if let Some(req) = self.send_queue.front_mut() {
+            // a read of no address, e.g. an empty window, is done at once
+            let no_addr = !req.is_write && req.addr_vec.is_empty();
             match req.is_write {
                 true => {
                     while let Some(addr) = req.addr_vec.pop() {
@@ -66,9 +86,14 @@ impl Component for MemInterface {
                             debug!("addr: {} ready to send!", addr);
                             // fix bug here, should merge the same addr
                             self.mem.send(addr, req.is_write);
+                            self.stats.record(req.id.layer_id, |x| {
+                                x.write_requests += 1;
+                                x.write_bytes += 64;
+                            });
                         } else {
                             debug!("addr: {} not ready to send!", addr);
                             req.addr_vec.push(addr);
+                            self.blocked_by_memory = true;
                             break;
                         }
                     }
@@ -76,21 +101,34 @@ impl Component for MemInterface {
                 false => {
                     while let Some(addr) = req.addr_vec.pop() {
                         if addr % 64 != 0 {
-                            panic!("addr should be 64 aligned");
+                            return Err(GcnAggError::InvalidState(format!(
+                                "addr {} of {:?} should be 64 aligned",
+                                addr, req.id
+                            )));
                         }
                         debug!("trying to send addr: {} of req: {:?}", addr, req.id);
 
                         if self.current_waiting_mem_request.contains_key(&addr) {
                             debug!("addr: {} is already in current_waiting_mem_request", addr);
                             // the request is already in flight
-                            self.current_waiting_mem_request
+                            let ids = self
+                                .current_waiting_mem_request
                                 .get_mut(&addr)
-                                .unwrap()
-                                .insert(req.id.clone());
+                                .ok_or_else(|| {
+                                    GcnAggError::InvalidState(format!(
+                                        "addr {} is not in flight",
+                                        addr
+                                    ))
+                                })?;
+                            if !ids.contains(&req.id) {
+                                ids.push(req.id.clone());
+                            }
                             self.current_waiting_request
                                 .entry(req.id.clone())
                                 .or_insert_with(HashSet::new)
                                 .insert(addr);
+                            self.stats
+                                .record(req.id.layer_id, |x| x.merged_requests += 1);
                         } else if self.mem.available(addr, req.is_write) {
                             debug!("addr: {} ready to send!", addr);
                             // fix bug here, should merge the same addr
@@ -100,13 +138,18 @@ impl Component for MemInterface {
                                 .or_insert_with(HashSet::new)
                                 .insert(addr);
                             self.current_waiting_mem_request
-                                .entry(addr)
-                                .or_insert_with(HashSet::new)
-                                .insert(req.id.clone());
+                                .insert(addr, vec![req.id.clone()]);
 
                             self.mem.send(addr, req.is_write);
+                            self.issue_cycles
+                                .insert(addr, (self.stats.cycles, req.id.layer_id));
+                            self.stats.record(req.id.layer_id, |x| {
+                                x.read_requests += 1;
+                                x.read_bytes += 64;
+                            });
                         } else {
                             req.addr_vec.push(addr);
+                            self.blocked_by_memory = true;
                             break;
                         }
                     }
@@ -114,6 +157,11 @@ impl Component for MemInterface {
             }
 
             if req.addr_vec.is_empty() {
+                if req.is_write {
+                    self.issued_writes.push_back(req.id.clone());
+                } else if no_addr {
+                    self.recv_queue.push_back(req.id.clone());
+                }
                 self.send_queue.pop_front();
             }
         }
@@ -121,15 +169,24 @@ impl Component for MemInterface {
         while self.mem.ret_available() && self.recv_queue.len() < self.recv_size {
             let addr = self.mem.pop();
             debug!("receive: addr: {:?}", addr);
+            self.returned_in_last_cycle = true;
+            if let Some((issue_cycle, layer)) = self.issue_cycles.remove(&addr) {
+                let latency = self.stats.cycles - issue_cycle;
+                self.stats.record(layer, |x| {
+                    x.returned_reads += 1;
+                    x.total_read_latency += latency;
+                });
+            }
             let id_list = self
                 .current_waiting_mem_request
                 .remove(&addr)
-                .unwrap_or_else(|| panic!("no request for addr {}", addr));
+                .ok_or_else(|| {
+                    GcnAggError::InvalidState(format!("no request for returned addr {}", addr))
+                })?;
             for id in id_list {
-                let req = self
-                    .current_waiting_request
-                    .get_mut(&id)
-                    .unwrap_or_else(|| panic!("no request for id {:?}", id));
+                let req = self.current_waiting_request.get_mut(&id).ok_or_else(|| {
+                    GcnAggError::InvalidState(format!("no request for id {:?}", id))
+                })?;
                 req.remove(&addr);
                 if req.is_empty() {
                     self.current_waiting_request.remove(&id);
@@ -138,21 +195,31 @@ impl Component for MemInterface {
                 }
             }
         }
+        self.blocked_by_recv_queue =
+            self.recv_queue.len() >= self.recv_size && self.mem.ret_available();
+        self.stats.record_occupancy(self.send_queue.len(), 1);
+        self.stats.cycles += 1;
         self.mem.cycle();
         Ok(())
     }
 }
 
 impl MemInterface {
-    pub fn new(send_size: usize, recv_size: usize, config_name: &str, stats_name: &str) -> Self {
+    pub fn new(send_size: usize, recv_size: usize, mem: Box<dyn MemoryBackend>) -> Self {
         MemInterface {
-            mem: RamulatorWrapper::new(config_name, stats_name),
+            mem,
             send_queue: VecDeque::new(),
             send_size,
             recv_queue: VecDeque::new(),
             recv_size,
             current_waiting_request: HashMap::new(),
             current_waiting_mem_request: HashMap::new(),
+            blocked_by_memory: false,
+            blocked_by_recv_queue: false,
+            returned_in_last_cycle: false,
+            issue_cycles: HashMap::new(),
+            stats: MemoryStatistics::default(),
+            issued_writes: VecDeque::new(),
         }
     }
     /// # Description
@@ -184,6 +251,63 @@ impl MemInterface {
         self.recv_queue.front()
     }
     /// # Description
+    /// * the number of following cycles that nothing will change in this interface if no new request is sent
+    /// * `u64::MAX` means nothing is on flight
+    pub fn get_quiet_cycles(&self) -> u64 {
+        if !self.send_queue.is_empty() || !self.recv_queue.is_empty() {
+            return 0;
+        }
+        if self.current_waiting_mem_request.is_empty() {
+            // the write requests will never return
+            return u64::MAX;
+        }
+        self.mem.get_cycles_to_next_return().unwrap_or(0)
+    }
+    /// # Description
+    /// * the interface can change without the system: requests to send, or a read returned in the last cycle
+    /// * waiting for the reads on flight is not counted, whatever the memory tells about the next return,
+    ///   so a read that never returns is a deadlock
+    pub fn has_pending_requests(&self) -> bool {
+        !self.send_queue.is_empty() || self.returned_in_last_cycle
+    }
+    /// # Description
+    /// * the status of the interface in the last cycle
+    /// * busy when there are requests to send, on flight or to be received
+    pub fn get_status(&self) -> ComponentStatus {
+        if self.blocked_by_memory {
+            ComponentStatus::Stalled(StallCause::Memory)
+        } else if self.blocked_by_recv_queue {
+            ComponentStatus::Stalled(StallCause::DownstreamFull)
+        } else if !self.send_queue.is_empty()
+            || !self.recv_queue.is_empty()
+            || !self.current_waiting_mem_request.is_empty()
+        {
+            ComponentStatus::Busy
+        } else {
+            ComponentStatus::Idle
+        }
+    }
+    /// # Description
+    /// * skip `cycles` cycles, `cycles` should not be larger than `get_quiet_cycles`
+    pub fn fast_forward(&mut self, cycles: u64) {
+        // the send queue is empty during the quiet cycles
+        self.stats.record_occupancy(0, cycles);
+        self.stats.cycles += cycles;
+        self.mem.fast_forward(cycles);
+    }
+    /// # Description
+    /// * the traffic statistics with the averages filled
+    pub fn get_statistics(&self) -> MemoryStatistics {
+        let mut stats = self.stats.clone();
+        stats.update_averages(self.mem.get_peak_bandwidth());
+        stats
+    }
+    /// # Description
+    /// * pop a write request that all addrs are sent to memory, the writes will never return
+    pub fn pop_issued_write(&mut self) -> Option<WindowId> {
+        self.issued_writes.pop_front()
+    }
+    /// # Description
     /// * receive a response from memory and pop that request
     #[allow(dead_code)]
     pub fn receive_pop(&mut self) -> Option<WindowId> {
@@ -195,14 +319,35 @@ impl MemInterface {
 mod tests {
 
     use super::*;
-    use crate::accelerator::window_id::WindowId;
+    use crate::accelerator::{
+        memory_backend::{FixedLatencyMemory, IdealMemory},
+        window_id::WindowId,
+    };
 
+    #[cfg(feature = "ramulator")]
     #[test]
     fn test_mem_interface() -> Result<(), Box<dyn std::error::Error>> {
         std::fs::create_dir_all("output")?;
+        let mem = ramulator_wrapper::RamulatorWrapper::new(
+            "HBM-config.cfg",
+            "output/test3_mem_stat.txt",
+        );
+        run_mem_interface(Box::new(mem))
+    }
 
+    #[test]
+    fn test_mem_interface_fixed_latency() -> Result<(), Box<dyn std::error::Error>> {
+        run_mem_interface(Box::new(FixedLatencyMemory::new(20, 1)))
+    }
+
+    #[test]
+    fn test_mem_interface_ideal() -> Result<(), Box<dyn std::error::Error>> {
+        run_mem_interface(Box::new(IdealMemory::new()))
+    }
+
+    fn run_mem_interface(mem: Box<dyn MemoryBackend>) -> Result<(), Box<dyn std::error::Error>> {
         simple_logger::init_with_level(log::Level::Info).unwrap_or_default();
-        let mut mem_interface = super::MemInterface::new(10, 10, "HBM-config.cfg","output/test3_mem_stat.txt");
+        let mut mem_interface = super::MemInterface::new(10, 10, mem);
         assert!(mem_interface.available());
         // assert_eq!(mem_interface.receive().is_some(), false);
         mem_interface.send(WindowId::new(1, 1, 1), vec![0], false);
@@ -230,4 +375,64 @@ mod tests {
         assert!(mem_interface.receive().is_none());
         Ok(())
     }
+
+    #[test]
+    fn test_mem_interface_statistics() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mem_interface =
+            super::MemInterface::new(10, 10, Box::new(FixedLatencyMemory::new(10, 1)));
+        mem_interface.send(WindowId::new(0, 0, 0), vec![0, 64], false);
+        // 64 is merged into the in flight request
+        mem_interface.send(WindowId::new(0, 0, 1), vec![64], false);
+        mem_interface.send(WindowId::new(0, 0, 0), vec![128], true);
+        loop {
+            mem_interface.cycle()?;
+            while mem_interface.receive_pop().is_some() {}
+            // all requests are finished
+            if mem_interface.get_quiet_cycles() == u64::MAX {
+                break;
+            }
+        }
+        let stats = mem_interface.get_statistics();
+        assert_eq!(stats.total.read_requests, 2);
+        assert_eq!(stats.total.merged_requests, 1);
+        assert_eq!(stats.total.write_requests, 1);
+        assert_eq!(stats.total.read_bytes, 128);
+        assert_eq!(stats.total.write_bytes, 64);
+        assert_eq!(stats.total.returned_reads, 2);
+        assert!(stats.total.average_read_latency >= 10.);
+        assert_eq!(stats.layers.len(), 2);
+        assert_eq!(stats.layers[0].read_requests, 2);
+        assert_eq!(stats.layers[1].merged_requests, 1);
+        assert_eq!(stats.send_queue_occupancy.iter().sum::<u64>(), stats.cycles);
+        let utilization = stats.bandwidth_utilization.expect("no peak bandwidth");
+        assert!(utilization > 0. && utilization <= 1.);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mem_interface_merged_order() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mem_interface =
+            super::MemInterface::new(10, 10, Box::new(FixedLatencyMemory::new(10, 1)));
+        // the later requests are merged into the first one in flight
+        let ids = [
+            WindowId::new(0, 2, 0),
+            WindowId::new(0, 0, 0),
+            WindowId::new(0, 3, 0),
+            WindowId::new(0, 1, 0),
+        ];
+        for id in ids.iter() {
+            mem_interface.send(id.clone(), vec![0], false);
+            mem_interface.cycle()?;
+        }
+        let mut received = vec![];
+        while received.len() < ids.len() {
+            mem_interface.cycle()?;
+            while let Some(id) = mem_interface.receive_pop() {
+                received.push(id);
+            }
+        }
+        // returned in the order they are issued, not the order of a hash set
+        assert_eq!(received, ids);
+        Ok(())
+    }
 }
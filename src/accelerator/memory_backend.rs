@@ -0,0 +1,256 @@
+//! # Description
+//! - the memory models behind the `MemInterface`
+//! - all models work on 64 bytes requests, and only the read requests will be returned
+//!
+//! # Backends
+//! - `Ramulator`: the cycle accurate dram simulator, need the `ramulator` feature
+//! - `FixedLatency`: each read returns after `latency` cycles, accept at most `bandwidth` requests per cycle
+//! - `Ideal`: zero latency and unlimited bandwidth
+//!
+
+use std::{collections::VecDeque, fmt::Debug};
+
+use crate::{
+    error::GcnAggError,
+    settings::{MemoryBackendType, MemorySettings},
+};
+
+/// # Description
+/// - the interface of a memory model, it's the same as the interface of ramulator
+pub trait MemoryBackend: Debug {
+    /// is the memory ready to receive a request
+    fn available(&mut self, addr: u64, is_write: bool) -> bool;
+    /// send a request to the memory
+    fn send(&mut self, addr: u64, is_write: bool);
+    /// is there any returned read request
+    fn ret_available(&mut self) -> bool;
+    /// pop the returned read request
+    fn pop(&mut self) -> u64;
+    /// update the cycle of the memory
+    fn cycle(&mut self);
+    /// the number of following cycles that no read request can be returned
+    /// - `None` means unknown, the memory may return a request in the next cycle
+    fn get_cycles_to_next_return(&self) -> Option<u64> {
+        None
+    }
+    /// the max bytes that can be transferred in one cycle, `None` if it's not fixed
+    fn get_peak_bandwidth(&self) -> Option<f64> {
+        None
+    }
+    /// skip `cycles` cycles, there is no request sent or returned during these cycles
+    fn fast_forward(&mut self, cycles: u64) {
+        for _ in 0..cycles {
+            self.cycle();
+        }
+    }
+}
+
+#[cfg(feature = "ramulator")]
+impl MemoryBackend for ramulator_wrapper::RamulatorWrapper {
+    fn available(&mut self, addr: u64, is_write: bool) -> bool {
+        ramulator_wrapper::RamulatorWrapper::available(self, addr, is_write)
+    }
+    fn send(&mut self, addr: u64, is_write: bool) {
+        ramulator_wrapper::RamulatorWrapper::send(self, addr, is_write)
+    }
+    fn ret_available(&mut self) -> bool {
+        ramulator_wrapper::RamulatorWrapper::ret_available(self)
+    }
+    fn pop(&mut self) -> u64 {
+        ramulator_wrapper::RamulatorWrapper::pop(self)
+    }
+    fn cycle(&mut self) {
+        ramulator_wrapper::RamulatorWrapper::cycle(self)
+    }
+}
+
+/// # Description
+/// - a memory with fixed latency and fixed bandwidth
+/// - each read request returns after `latency` cycles
+/// - at most `bandwidth` requests are accepted in each cycle
+#[derive(Debug)]
+pub struct FixedLatencyMemory {
+    latency: u64,
+    bandwidth: usize,
+    current_cycle: u64,
+    sent_this_cycle: usize,
+    // the ready cycle and the addr of the in flight reads, in order of the ready cycle
+    in_flight: VecDeque<(u64, u64)>,
+}
+
+impl FixedLatencyMemory {
+    pub fn new(latency: u64, bandwidth: usize) -> Self {
+        FixedLatencyMemory {
+            latency,
+            bandwidth,
+            current_cycle: 0,
+            sent_this_cycle: 0,
+            in_flight: VecDeque::new(),
+        }
+    }
+}
+
+impl MemoryBackend for FixedLatencyMemory {
+    fn available(&mut self, _addr: u64, _is_write: bool) -> bool {
+        self.sent_this_cycle < self.bandwidth
+    }
+    fn send(&mut self, addr: u64, is_write: bool) {
+        self.sent_this_cycle += 1;
+        if !is_write {
+            self.in_flight
+                .push_back((self.current_cycle + self.latency, addr));
+        }
+    }
+    fn ret_available(&mut self) -> bool {
+        matches!(self.in_flight.front(), Some(&(ready, _)) if ready <= self.current_cycle)
+    }
+    fn pop(&mut self) -> u64 {
+        self.in_flight
+            .pop_front()
+            .expect("no request returned from the memory")
+            .1
+    }
+    fn cycle(&mut self) {
+        self.current_cycle += 1;
+        self.sent_this_cycle = 0;
+    }
+    fn get_cycles_to_next_return(&self) -> Option<u64> {
+        match self.in_flight.front() {
+            Some(&(ready, _)) => Some(ready.saturating_sub(self.current_cycle)),
+            None => Some(u64::MAX),
+        }
+    }
+    fn get_peak_bandwidth(&self) -> Option<f64> {
+        Some((self.bandwidth * 64) as f64)
+    }
+    fn fast_forward(&mut self, cycles: u64) {
+        if cycles > 0 {
+            self.current_cycle += cycles;
+            self.sent_this_cycle = 0;
+        }
+    }
+}
+
+/// # Description
+/// - a memory with zero latency and unlimited bandwidth, the read request is returned immediately
+#[derive(Debug, Default)]
+pub struct IdealMemory {
+    returned: VecDeque<u64>,
+}
+
+impl IdealMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MemoryBackend for IdealMemory {
+    fn available(&mut self, _addr: u64, _is_write: bool) -> bool {
+        true
+    }
+    fn send(&mut self, addr: u64, is_write: bool) {
+        if !is_write {
+            self.returned.push_back(addr);
+        }
+    }
+    fn ret_available(&mut self) -> bool {
+        !self.returned.is_empty()
+    }
+    fn pop(&mut self) -> u64 {
+        self.returned
+            .pop_front()
+            .expect("no request returned from the memory")
+    }
+    fn cycle(&mut self) {}
+    fn get_cycles_to_next_return(&self) -> Option<u64> {
+        match self.returned.is_empty() {
+            true => Some(u64::MAX),
+            false => Some(0),
+        }
+    }
+    fn fast_forward(&mut self, _cycles: u64) {}
+}
+
+/// # Description
+/// - build the memory backend from the settings
+/// # Arguments
+/// - memory_settings: the settings of the memory backend
+/// - config_name: the config file of ramulator
+/// - stats_name: the file for ramulator to save the statistics
+pub fn build_memory_backend(
+    memory_settings: &MemorySettings,
+    config_name: &str,
+    stats_name: &str,
+) -> Result<Box<dyn MemoryBackend>, GcnAggError> {
+    match memory_settings.backend {
+        #[cfg(feature = "ramulator")]
+        MemoryBackendType::Ramulator => Ok(Box::new(ramulator_wrapper::RamulatorWrapper::new(
+            config_name,
+            stats_name,
+        ))),
+        #[cfg(not(feature = "ramulator"))]
+        MemoryBackendType::Ramulator => {
+            let _ = (config_name, stats_name);
+            Err(GcnAggError::Config(
+                "the ramulator backend needs the `ramulator` feature".into(),
+            ))
+        }
+        MemoryBackendType::FixedLatency => {
+            if memory_settings.bandwidth == 0 {
+                return Err(GcnAggError::Config(
+                    "the bandwidth of the fixed latency memory should not be 0".into(),
+                ));
+            }
+            Ok(Box::new(FixedLatencyMemory::new(
+                memory_settings.latency,
+                memory_settings.bandwidth,
+            )))
+        }
+        MemoryBackendType::Ideal => Ok(Box::new(IdealMemory::new())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fixed_latency() {
+        let mut mem = FixedLatencyMemory::new(10, 2);
+        assert!(mem.available(0, false));
+        mem.send(0, false);
+        mem.send(64, true);
+        // the bandwidth is used up
+        assert!(!mem.available(128, false));
+        let mut cycles = 0;
+        while !mem.ret_available() {
+            mem.cycle();
+            cycles += 1;
+        }
+        assert_eq!(cycles, 10);
+        assert_eq!(mem.pop(), 0);
+        // the write is not returned
+        assert!(!mem.ret_available());
+        assert!(mem.available(128, false));
+
+        mem.send(192, false);
+        assert_eq!(mem.get_cycles_to_next_return(), Some(10));
+        mem.fast_forward(9);
+        assert!(!mem.ret_available());
+        mem.cycle();
+        assert!(mem.ret_available());
+        assert_eq!(mem.pop(), 192);
+    }
+
+    #[test]
+    fn test_ideal() {
+        let mut mem = IdealMemory::new();
+        mem.send(0, false);
+        mem.send(64, false);
+        mem.send(128, true);
+        assert!(mem.ret_available());
+        assert_eq!(mem.pop(), 0);
+        assert_eq!(mem.pop(), 64);
+        assert!(!mem.ret_available());
+    }
+}
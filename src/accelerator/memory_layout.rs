@@ -0,0 +1,332 @@
+//! # Description
+//! - place the features, the dense features and the weights of every layer in their own regions of the memory,
+//!   so the reads of one layer and the writes of the next one never alias in the dram
+//! - the regions are laid out in the order of the layers, each region starts at `alignment`
+//! - a sparse node is `nnz * (index + value)` bytes, a dense node is `dim * element_bytes`,
+//!   with `row_padding` every node starts at a 64 bytes boundary
+//! - with more than one channel, the linear addresses are interleaved: each `interleave_bytes` goes to the next
+//!   channel, and channel `i` owns the addresses from `i * channel_stride`
+//!
+
+use log::info;
+
+use crate::{
+    error::GcnAggError,
+    gcn_result::{LayoutReport, RegionKind, RegionRecord},
+    graph::Graph,
+    node_features::NodeFeatures,
+    settings::{LayoutSettings, RunningMode},
+};
+
+/// the size of one memory request
+pub const REQUEST_BYTES: u64 = 64;
+
+/// # Description
+/// - the regions of one layer
+/// - `row_offsets`: the address of each sparse node and the end, in the features region
+/// - `dense`: the start and the row stride of the dense features, `None` if no window reads it
+#[derive(Debug, Clone)]
+struct LayerLayout {
+    row_offsets: Vec<u64>,
+    dense: Option<(u64, u64)>,
+    weights: (u64, u64),
+}
+
+/// # Description
+/// - the addresses of all tensors in the memory, built by `System::new`
+/// - the requests are the 64 bytes aligned addresses after the interleaving
+#[derive(Debug, Clone)]
+pub struct MemoryLayout {
+    settings: LayoutSettings,
+    channel_stride: u64,
+    total_bytes: u64,
+    layers: Vec<LayerLayout>,
+    regions: Vec<RegionRecord>,
+}
+
+impl MemoryLayout {
+    /// # Description
+    /// - build the layout of `node_features`, the dims of each layer are the same as the windows,
+    ///   the final layer outputs 1 element for each node
+    /// - the dense features are only placed when `running_mode` is not `Sparse`
+    /// # Errors
+    /// - `Config` if the alignment or the interleaving is not a multiple of 64, or `channels` is 0
+    pub fn new(
+        graph: &Graph,
+        node_features: &[NodeFeatures],
+        gcn_hidden_size: &[usize],
+        running_mode: &RunningMode,
+        settings: &LayoutSettings,
+    ) -> Result<Self, GcnAggError> {
+        let LayoutSettings {
+            alignment,
+            row_padding,
+            channels,
+            interleave_bytes,
+        } = *settings;
+        if alignment == 0 || alignment % REQUEST_BYTES != 0 {
+            return Err(GcnAggError::Config(format!(
+                "the alignment {} is not a multiple of {}",
+                alignment, REQUEST_BYTES
+            )));
+        }
+        if channels == 0 || interleave_bytes == 0 || interleave_bytes % REQUEST_BYTES != 0 {
+            return Err(GcnAggError::Config(format!(
+                "cannot interleave {} bytes over {} channels",
+                interleave_bytes, channels
+            )));
+        }
+        let pad_row = |bytes: u64| match row_padding {
+            true => round_up(bytes, REQUEST_BYTES),
+            false => bytes,
+        };
+
+        let mut next = 0;
+        let mut regions = vec![];
+        let mut add_region = |layer, kind, bytes: u64, data_bytes| {
+            let start = next;
+            next = round_up(start + bytes, alignment);
+            regions.push(RegionRecord {
+                layer,
+                kind,
+                start,
+                end: next,
+                data_bytes,
+            });
+            start
+        };
+        let input_dims: Vec<_> = std::iter::once(graph.get_feature_size())
+            .chain(gcn_hidden_size.iter().cloned())
+            .collect();
+        let output_dims: Vec<_> = gcn_hidden_size.iter().cloned().chain([1]).collect();
+        let mut layers = vec![];
+        for (layer, features) in node_features.iter().enumerate() {
+            let encoding = features.get_encoding();
+            let mut row_offsets = vec![0];
+            for i in 0..features.len() {
+                let end = row_offsets[i] + pad_row(features.get_node_bytes(i) as u64);
+                row_offsets.push(end);
+            }
+            let data_bytes = features.start_addrs.last().copied().unwrap_or(0);
+            let base = add_region(
+                layer,
+                RegionKind::Features,
+                row_offsets[features.len()],
+                data_bytes,
+            );
+            row_offsets.iter_mut().for_each(|x| *x += base);
+
+            let element_bytes = encoding.element_bytes() as u64;
+            let dense_row = input_dims[layer] as u64 * element_bytes;
+            let dense = match running_mode {
+                RunningMode::Sparse => None,
+                _ => {
+                    let stride = pad_row(dense_row);
+                    let num_nodes = features.len() as u64;
+                    let start = add_region(
+                        layer,
+                        RegionKind::DenseFeatures,
+                        stride * num_nodes,
+                        dense_row * num_nodes,
+                    );
+                    Some((start, stride))
+                }
+            };
+            let weight_bytes = input_dims[layer] as u64 * output_dims[layer] as u64 * element_bytes;
+            let weights = (
+                add_region(layer, RegionKind::Weights, weight_bytes, weight_bytes),
+                weight_bytes,
+            );
+            layers.push(LayerLayout {
+                row_offsets,
+                dense,
+                weights,
+            });
+        }
+
+        let channel_stride = match channels {
+            1 => next,
+            _ => round_up(next.div_ceil(channels), interleave_bytes).next_power_of_two(),
+        };
+        let layout = MemoryLayout {
+            settings: settings.clone(),
+            channel_stride,
+            total_bytes: next,
+            layers,
+            regions,
+        };
+        for region in &layout.regions {
+            info!(
+                "memory layout: layer {} {:?} {:#x}..{:#x}, {} bytes",
+                region.layer, region.kind, region.start, region.end, region.data_bytes
+            );
+        }
+        Ok(layout)
+    }
+
+    /// # Description
+    /// - the requests of the sparse nodes `start..end` of `layer`
+    pub fn sparse_requests(&self, layer: usize, start: usize, end: usize) -> Vec<u64> {
+        let row_offsets = &self.layers[layer].row_offsets;
+        self.requests(row_offsets[start], row_offsets[end])
+    }
+
+    /// # Description
+    /// - the requests of the dense nodes `start..end` of `layer`
+    /// # Errors
+    /// - `InvalidState` if the dense features of `layer` are not placed
+    pub fn dense_requests(
+        &self,
+        layer: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<u64>, GcnAggError> {
+        let (base, stride) = self.layers[layer].dense.ok_or_else(|| {
+            GcnAggError::InvalidState(format!("layer {} has no dense features", layer))
+        })?;
+        Ok(self.requests(base + start as u64 * stride, base + end as u64 * stride))
+    }
+
+    /// # Description
+    /// - the requests of the whole weight matrix of `layer`
+    pub fn weight_requests(&self, layer: usize) -> Vec<u64> {
+        let (base, bytes) = self.layers[layer].weights;
+        self.requests(base, base + bytes)
+    }
+
+    /// # Description
+    /// - the regions and the settings, written to the result
+    pub fn report(&self) -> LayoutReport {
+        LayoutReport {
+            alignment: self.settings.alignment,
+            row_padding: self.settings.row_padding,
+            channels: self.settings.channels,
+            interleave_bytes: self.settings.interleave_bytes,
+            channel_stride: self.channel_stride,
+            total_bytes: self.total_bytes,
+            regions: self.regions.clone(),
+        }
+    }
+
+    /// the 64 bytes requests covering the linear addresses `start..end`
+    fn requests(&self, start: u64, end: u64) -> Vec<u64> {
+        (start / REQUEST_BYTES * REQUEST_BYTES..end)
+            .step_by(REQUEST_BYTES as usize)
+            .map(|x| self.interleave(x))
+            .collect()
+    }
+
+    /// the address of the linear address `addr` in the channels
+    fn interleave(&self, addr: u64) -> u64 {
+        let LayoutSettings {
+            channels,
+            interleave_bytes,
+            ..
+        } = self.settings;
+        if channels == 1 {
+            return addr;
+        }
+        let chunk = addr / interleave_bytes;
+        (chunk % channels) * self.channel_stride
+            + (chunk / channels) * interleave_bytes
+            + addr % interleave_bytes
+    }
+}
+
+fn round_up(value: u64, align: u64) -> u64 {
+    value.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::generators;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_memory_layout() -> Result<(), GcnAggError> {
+        let graph = generators::grid(2, 2, 4)?;
+        // the sparse nodes of layer 0 are 8, 16, 0 and 32 bytes
+        let node_features = vec![
+            NodeFeatures::from_dense(&[
+                vec![1., 0., 0., 0.],
+                vec![1., 1., 0., 0.],
+                vec![0.; 4],
+                vec![1.; 4],
+            ]),
+            NodeFeatures::from_dense(&vec![vec![1., 1.]; 4]),
+        ];
+        let mut settings = LayoutSettings {
+            alignment: 128,
+            row_padding: false,
+            channels: 1,
+            interleave_bytes: 128,
+        };
+        let build = |settings: &LayoutSettings| {
+            MemoryLayout::new(&graph, &node_features, &[2], &RunningMode::Mixed, settings)
+        };
+        let layout = build(&settings)?;
+        let regions: Vec<_> = layout
+            .report()
+            .regions
+            .iter()
+            .map(|x| (x.layer, x.kind, x.start, x.end, x.data_bytes))
+            .collect();
+        assert_eq!(
+            regions,
+            vec![
+                (0, RegionKind::Features, 0, 128, 56),
+                (0, RegionKind::DenseFeatures, 128, 256, 64),
+                (0, RegionKind::Weights, 256, 384, 32),
+                (1, RegionKind::Features, 384, 512, 64),
+                (1, RegionKind::DenseFeatures, 512, 640, 32),
+                (1, RegionKind::Weights, 640, 768, 8),
+            ]
+        );
+        assert_eq!(layout.sparse_requests(0, 1, 3), vec![0]);
+        // the write back of layer 0 goes to the features of layer 1
+        assert_eq!(layout.sparse_requests(1, 0, 4), vec![384]);
+        assert_eq!(layout.dense_requests(0, 2, 4)?, vec![128]);
+        assert_eq!(layout.weight_requests(1), vec![640]);
+
+        // every node starts at 64 bytes
+        settings.row_padding = true;
+        let layout = build(&settings)?;
+        assert_eq!(layout.sparse_requests(0, 1, 3), vec![64]);
+        assert_eq!(layout.sparse_requests(0, 3, 4), vec![128]);
+        assert_eq!(layout.dense_requests(0, 0, 4)?, vec![256, 320, 384, 448]);
+
+        // 128 bytes to each of the 2 channels in turn
+        settings.row_padding = false;
+        settings.channels = 2;
+        let layout = build(&settings)?;
+        let stride = layout.report().channel_stride;
+        assert_eq!(stride, 512);
+        assert_eq!(layout.dense_requests(0, 0, 4)?, vec![stride]);
+        assert_eq!(layout.weight_requests(0), vec![128]);
+        assert_eq!(layout.sparse_requests(1, 0, 4), vec![stride + 128]);
+        let requests: Vec<_> = (0..2)
+            .flat_map(|layer| {
+                let mut x = layout.sparse_requests(layer, 0, 4);
+                x.extend(layout.dense_requests(layer, 0, 4).unwrap());
+                x.extend(layout.weight_requests(layer));
+                x
+            })
+            .collect();
+        assert!(requests.iter().all_unique());
+
+        // a sparse-only run has no dense features
+        let layout = MemoryLayout::new(
+            &graph,
+            &node_features,
+            &[2],
+            &RunningMode::Sparse,
+            &settings,
+        )?;
+        assert!(layout.dense_requests(0, 0, 1).is_err());
+
+        settings.alignment = 100;
+        assert!(matches!(build(&settings), Err(GcnAggError::Config(_))));
+        Ok(())
+    }
+}
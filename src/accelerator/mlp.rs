@@ -1,5 +1,7 @@
 use log::info;
 
+use crate::{error::GcnAggError, settings::RunningMode};
+
 use super::{component::Component, sliding_window::OutputWindow, temp_agg_result::TempAggResult};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -19,7 +21,7 @@ pub struct Mlp {
 }
 
 impl Component for Mlp {
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
         if self.state == MlpState::Working {
             if self.remaining_cycle == 0 {
                 self.state = MlpState::Finished;
@@ -68,8 +70,8 @@ impl Mlp {
         output_window: &OutputWindow,
         output_results: &Option<TempAggResult>,
     ) {
-        match output_results {
-            Some(_output_results) => {
+        match (output_window.get_running_mode(), output_results) {
+            (RunningMode::Sparse, Some(_output_results)) => {
                 // the sparse mlp
                 info!("start sparse mlp");
                 self.state = MlpState::Working;
@@ -79,7 +81,7 @@ impl Mlp {
                 total_cycle *= 2;
                 self.remaining_cycle = total_cycle as u64;
             }
-            None => {
+            _ => {
                 info!("start dense mlp");
                 // the dense mlp
                 self.state = MlpState::Working;
@@ -110,4 +112,23 @@ impl Mlp {
     pub fn get_remaining_cycle(&self) -> u64 {
         self.remaining_cycle
     }
+
+    /// # Description
+    /// - the number of following cycles that the mlp will only count down
+    /// - `u64::MAX` means it's idle and will never change by itself
+    pub(super) fn get_quiet_cycles(&self) -> u64 {
+        match self.state {
+            MlpState::Idle => u64::MAX,
+            MlpState::Working => self.remaining_cycle,
+            MlpState::Finished => 0,
+        }
+    }
+
+    /// # Description
+    /// - skip `cycles` cycles, `cycles` should not be larger than `get_quiet_cycles`
+    pub(super) fn fast_forward(&mut self, cycles: u64) {
+        if self.state == MlpState::Working {
+            self.remaining_cycle -= cycles;
+        }
+    }
 }
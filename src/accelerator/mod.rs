@@ -7,21 +7,42 @@
 //! - system: the main sub module, all the components are in system
 //! - agg_buffer and other buffers: provide data for aggregator and mlp
 //! - aggregator and mlp, the module for calculating the result
+//! - weight_buffer: hold the weights of the mlp, loaded once per layer or streamed in tiles
 //! - mem_interface: the interface between system and memory(ramulator)
+//! - memory_backend: the memory models behind mem_interface(ramulator, fixed latency or ideal)
+//! - memory_layout: the regions of the features and the weights of every layer in the memory
+//! - address_generator: the requests of the windows in the sparse or the dense dataflow
+//! - tracer: record the spans of the pipeline as chrome trace-event json
+//! - deadlock: detect the deadlock and build the wait-for graph of the components
+//! - functional: compute the real values of the windows and check them with the reference gcn
+//! - window_plan: build the windows of every layer without running the system, for `--dry-run`
 //! 
 
 
 pub(self) mod aggregator;
 pub(self) mod system;
 pub(self) mod mem_interface;
+pub(self) mod memory_backend;
+pub(self) mod memory_layout;
+pub(self) mod address_generator;
 pub(self) mod input_buffer;
 pub(self) mod sparsify_buffer;
 pub(self) mod agg_buffer;
 pub(self) mod sliding_window;
 pub(self) mod window_id;
 pub(self) mod mlp;
+pub(self) mod weight_buffer;
 pub(self) mod component;
 pub(self) mod temp_agg_result;
 pub(self) mod sparsifier;
 pub(self) mod output_buffer;
-pub use system::System;
\ No newline at end of file
+pub(self) mod tracer;
+pub(self) mod deadlock;
+pub(self) mod functional;
+pub(self) mod window_plan;
+pub use deadlock::{ComponentName, WaitFor};
+pub use functional::reference_node_features;
+pub use memory_layout::MemoryLayout;
+pub use system::System;
+pub use window_plan::plan_windows;
+pub use window_id::WindowId;
@@ -5,6 +5,7 @@
 use std::{mem::swap, rc::Rc};
 
 use super::{component::Component, sliding_window::OutputWindow};
+use crate::error::GcnAggError;
 #[derive(Debug, PartialEq)]
 pub enum BufferStatus {
     Empty,
@@ -43,8 +44,10 @@ impl Component for OutputBuffer {
     ///
     /// ```
     ///
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if let (BufferStatus::WaitingToWriteBack, BufferStatus::Empty) = (&self.current_state, &self.next_state) {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
+        if let (BufferStatus::WaitingToWriteBack, BufferStatus::Empty) =
+            (&self.current_state, &self.next_state)
+        {
             swap(&mut self.current_state, &mut self.next_state);
             swap(&mut self.current_window, &mut self.next_window);
         }
@@ -62,17 +65,44 @@ impl OutputBuffer {
         }
     }
 
-    pub fn start_sparsify(&mut self, window: Rc<OutputWindow>) {
-        assert_eq!(self.current_state, BufferStatus::Empty);
+    /// # Description
+    /// - return `InvalidState` if `state` is not `expected`
+    fn check_state(
+        action: &str,
+        state: &BufferStatus,
+        expected: BufferStatus,
+    ) -> Result<(), GcnAggError> {
+        match *state == expected {
+            true => Ok(()),
+            false => Err(GcnAggError::InvalidState(format!(
+                "output_buffer {}: the state is {:?}, expected {:?}",
+                action, state, expected
+            ))),
+        }
+    }
+
+    pub fn start_sparsify(&mut self, window: Rc<OutputWindow>) -> Result<(), GcnAggError> {
+        Self::check_state("start_sparsify", &self.current_state, BufferStatus::Empty)?;
         self.current_state = BufferStatus::Writing;
         self.current_window = Some(window);
+        Ok(())
     }
-    pub fn finished_sparsify(&mut self) {
-        assert_eq!(self.current_state, BufferStatus::Writing);
+    pub fn finished_sparsify(&mut self) -> Result<(), GcnAggError> {
+        Self::check_state(
+            "finished_sparsify",
+            &self.current_state,
+            BufferStatus::Writing,
+        )?;
         self.current_state = BufferStatus::WaitingToWriteBack;
+        Ok(())
     }
-    pub fn start_write_back(&mut self) {
-        assert_eq!(self.next_state, BufferStatus::WaitingToWriteBack);
+    pub fn start_write_back(&mut self) -> Result<(), GcnAggError> {
+        Self::check_state(
+            "start_write_back",
+            &self.next_state,
+            BufferStatus::WaitingToWriteBack,
+        )?;
         self.next_state = BufferStatus::Empty;
+        Ok(())
     }
 }
@@ -1,9 +1,13 @@
 use log::{debug, info};
 
 use super::window_id::WindowId;
-use crate::{graph::Graph, node_features::NodeFeatures, settings::RunningMode};
-use core::panic;
-use std::{cmp, collections::btree_set::Range, rc::Rc};
+use crate::{
+    error::GcnAggError,
+    graph::Graph,
+    node_features::NodeFeatures,
+    settings::{MixedGranularity, MixedSettings, RunningMode},
+};
+use std::{cmp, rc::Rc};
 pub struct WindowIterSettings {
     pub agg_buffer_size: usize,
     pub input_buffer_size: usize,
@@ -11,17 +15,20 @@ pub struct WindowIterSettings {
     pub gcn_hidden_size: Vec<usize>,
     pub final_layer: bool,
     pub running_mode: RunningMode,
+    pub mixed_settings: MixedSettings,
 }
 #[derive(Debug, Clone)]
 pub struct InputWindow<'a> {
     pub task_id: WindowId,
-    tasks: Rc<Vec<Range<'a, usize>>>,
+    tasks: Rc<Vec<&'a [u32]>>,
     pub start_output_index: usize,
     pub start_input_index: usize,
     pub end_output_index: usize,
     pub end_input_index: usize,
     pub output_window: Rc<OutputWindow>,
     pub is_last_row: bool,
+    /// the empty rows cut from the end of the window, they fit in the input buffer but have no edge
+    pub shrunk_rows: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -33,9 +40,12 @@ pub struct OutputWindow {
     pub input_node_dim: usize,
     pub final_window: bool,
     pub final_layer: bool,
+    /// the dataflow of this window, never be `Mixed`
+    pub running_mode: RunningMode,
 }
 
 impl OutputWindow {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         start_output_index: usize,
         end_output_index: usize,
@@ -44,6 +54,7 @@ impl OutputWindow {
         input_node_dim: usize,
         final_window: bool,
         final_layer: bool,
+        running_mode: RunningMode,
     ) -> Self {
         OutputWindow {
             start_output_index,
@@ -53,6 +64,7 @@ impl OutputWindow {
             input_node_dim,
             final_window,
             final_layer,
+            running_mode,
         }
     }
     pub fn get_output_len(&self) -> usize {
@@ -67,6 +79,9 @@ impl OutputWindow {
     pub fn get_task_id(&self) -> &WindowId {
         &self.task_id
     }
+    pub fn get_running_mode(&self) -> &RunningMode {
+        &self.running_mode
+    }
 }
 
 pub struct RangeIndex {
@@ -79,7 +94,7 @@ pub struct RangeIndex {
 impl<'a> InputWindow<'a> {
     pub(super) fn new(
         task_id: WindowId,
-        tasks: Rc<Vec<Range<'a, usize>>>,
+        tasks: Rc<Vec<&'a [u32]>>,
         range_index: RangeIndex,
         output_window: Rc<OutputWindow>,
         is_last_row: bool,
@@ -100,12 +115,13 @@ impl<'a> InputWindow<'a> {
             end_input_index,
             output_window,
             is_last_row,
+            shrunk_rows: 0,
         }
     }
     pub(super) fn get_task_id(&self) -> &WindowId {
         &self.task_id
     }
-    pub(super) fn get_tasks(&self) -> &Vec<Range<'a, usize>> {
+    pub(super) fn get_tasks(&self) -> &Vec<&'a [u32]> {
         &self.tasks
     }
     #[allow(dead_code)]
@@ -132,14 +148,21 @@ pub struct OutputWindowIterator<'a> {
     gcn_hidden_size: Vec<usize>,
     pub final_layer: bool,
     running_mode: RunningMode,
+    mixed_settings: MixedSettings,
 }
 
 impl<'a> OutputWindowIterator<'a> {
+    /// # Description
+    /// - build the output window iterator of a layer
+    /// # Errors
+    /// - `Config` if `gcn_hidden_size` has no size for this layer, or the feature dimension of this layer is 0
+    /// - `BufferTooSmall` if the agg buffer cannot hold one output node,
+    ///   or the input buffer cannot hold one input node
     pub fn new(
         graph: &'a Graph,
         node_features: &'a NodeFeatures,
         window_iter_settings: WindowIterSettings,
-    ) -> OutputWindowIterator<'a> {
+    ) -> Result<OutputWindowIterator<'a>, GcnAggError> {
         let WindowIterSettings {
             agg_buffer_size,
             input_buffer_size,
@@ -147,8 +170,9 @@ impl<'a> OutputWindowIterator<'a> {
             gcn_hidden_size,
             final_layer,
             running_mode,
+            mixed_settings,
         } = window_iter_settings;
-        OutputWindowIterator {
+        let iter = OutputWindowIterator {
             graph,
             node_features,
             agg_buffer_size,
@@ -162,30 +186,105 @@ impl<'a> OutputWindowIterator<'a> {
             gcn_hidden_size,
             final_layer,
             running_mode,
+            mixed_settings,
+        };
+        iter.check_hidden_size()?;
+        iter.check_buffer_size()?;
+        Ok(iter)
+    }
+
+    /// # Description
+    /// - make sure `gcn_hidden_size` has the input and the output dimensions of this layer
+    fn check_hidden_size(&self) -> Result<(), GcnAggError> {
+        let layer = self.task_id.layer_id;
+        let required = match self.final_layer {
+            true => layer,
+            false => layer + 1,
+        };
+        match self.gcn_hidden_size.len() < required {
+            true => Err(GcnAggError::Config(format!(
+                "gcn_hidden_size has {} layers, layer {} needs {}",
+                self.gcn_hidden_size.len(),
+                layer,
+                required
+            ))),
+            false => Ok(()),
         }
     }
-}
-impl<'a> Iterator for OutputWindowIterator<'a> {
-    type Item = InputWindowIterator<'a>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current_start_output_index >= self.graph.get_num_node() {
-            return None;
+    /// # Description
+    /// - make sure every window of this layer has at least one output node and one input node
+    fn check_buffer_size(&self) -> Result<(), GcnAggError> {
+        let layer = self.task_id.layer_id;
+        let input_dim = self.get_input_dim();
+        if input_dim == 0 {
+            return Err(GcnAggError::Config(format!(
+                "the input dimension of layer {} is 0",
+                layer
+            )));
+        }
+        let element_bytes = self.node_features.get_encoding().element_bytes();
+        if self.get_output_size() == 0 {
+            return Err(GcnAggError::BufferTooSmall {
+                buffer: "agg_buffer".into(),
+                size: self.agg_buffer_size / 2,
+                required: input_dim * element_bytes,
+                layer,
+            });
         }
+        // the input buffer is double buffered
+        let half_input_buffer = self.input_buffer_size / 2;
+        let check_dense = || match input_dim * element_bytes > half_input_buffer {
+            true => Err(GcnAggError::BufferTooSmall {
+                buffer: "input_buffer".into(),
+                size: half_input_buffer,
+                required: input_dim * element_bytes,
+                layer,
+            }),
+            false => Ok(()),
+        };
+        let check_sparse = || {
+            let max_row = (0..self.node_features.len())
+                .map(|i| self.node_features.get_node_bytes(i))
+                .max()
+                .unwrap_or(0);
+            // at least one byte is needed to start a window
+            match half_input_buffer == 0 || max_row > half_input_buffer {
+                true => Err(GcnAggError::BufferTooSmall {
+                    buffer: "input_buffer".into(),
+                    size: half_input_buffer,
+                    required: max_row.max(1),
+                    layer,
+                }),
+                false => Ok(()),
+            }
+        };
+        match self.running_mode {
+            RunningMode::Sparse => check_sparse(),
+            RunningMode::Dense => check_dense(),
+            // the dataflow of each window is unknown yet
+            RunningMode::Mixed => check_sparse().and_then(|_| check_dense()),
+        }
+    }
+
+    /// # Description
+    /// - the number of output nodes in each output window
+    fn get_output_size(&self) -> usize {
         // let output_size = (self.agg_buffer_size / 2) / (self.graph.get_feature_size() * 4);
         // fix bug here, the output feature size is gcn_hidden layer size!
         // fix bug again, the aggregated result size is unknown! we need to have enought space to store the aggregated result!
         // let output_size = self.gcn_hidden_size[self.task_id.layer_id] * 4;
         // let output_size = (self.agg_buffer_size / 2) / output_size;
         // fix another bug!, when the layer is not zero, the outout size is the gcn_hidden layer size!
-        let output_size = match self.task_id.layer_id {
+        let element_bytes = self.node_features.get_encoding().element_bytes();
+        match self.task_id.layer_id {
             0 => {
                 debug!(
                     "it's the first layer, the agg buffer is:{}, the node size is:{}",
                     self.agg_buffer_size / 2,
-                    self.graph.get_feature_size() * 4
+                    self.graph.get_feature_size() * element_bytes
                 );
-                (self.agg_buffer_size / 2) / (self.graph.get_feature_size() * 4)
+                (self.agg_buffer_size / 2) / (self.graph.get_feature_size() * element_bytes)
             }
             _ => {
                 debug!(
@@ -195,15 +294,96 @@ impl<'a> Iterator for OutputWindowIterator<'a> {
                 );
                 self.gcn_hidden_size[self.task_id.layer_id - 1]
             }
-        };
+        }
+    }
 
-        if output_size == 0 {
-            panic!(
-                "Output size is 0,agg_buffer_size:{},feature_size:{}",
-                self.agg_buffer_size / 2,
-                self.graph.get_feature_size() * 4
-            );
+    fn get_input_dim(&self) -> usize {
+        match self.task_id.layer_id {
+            0 => self.graph.get_feature_size(),
+            _ => self.gcn_hidden_size[self.task_id.layer_id - 1],
+        }
+    }
+
+    /// the final layer outputs one value per node
+    fn get_output_dim(&self) -> usize {
+        match self.final_layer {
+            true => 1,
+            false => self.gcn_hidden_size[self.task_id.layer_id],
+        }
+    }
+
+    /// # Description
+    /// - decide the dataflow of the output window from `start_output_index` to `end_output_index`
+    /// - in `Mixed` mode, the density is measured from the node features:
+    ///   - `Layer`: the density of all nodes in this layer
+    ///   - `Window`: the density of all input nodes(count by edges) aggregated by this output window, the previous
+    ///     layer writes back each node in the dataflows of the windows that read it, see `NodeReadModes`
+    fn resolve_running_mode(
+        &self,
+        start_output_index: usize,
+        end_output_index: usize,
+    ) -> RunningMode {
+        match self.running_mode {
+            RunningMode::Mixed => {
+                let input_dim = self.get_input_dim();
+                let density = match self.mixed_settings.granularity {
+                    MixedGranularity::Layer => self.node_features.get_density(input_dim),
+                    MixedGranularity::Window => {
+                        let (edges, nnz) = (start_output_index..end_output_index)
+                            .flat_map(|i| self.graph.get_csc_row(i))
+                            .fold((0, 0), |(edges, nnz), &j| {
+                                (
+                                    edges + 1,
+                                    nnz + self.node_features.get_features(j as usize).len(),
+                                )
+                            });
+                        match edges * input_dim {
+                            0 => 0.,
+                            total => nnz as f64 / total as f64,
+                        }
+                    }
+                };
+                let mode = get_running_mode_by_density(density, &self.mixed_settings);
+                debug!(
+                    "mixed mode, layer: {}, output: {}..{}, density: {}, use {:?}",
+                    self.task_id.layer_id, start_output_index, end_output_index, density, mode
+                );
+                mode
+            }
+            ref mode => mode.clone(),
         }
+    }
+}
+
+/// # Description
+/// - the dataflow of a whole layer in `Mixed` mode, by the density of all nodes of `dim` dims
+/// - all windows of the layer read in it with the `Layer` granularity, so the previous layer writes back in it,
+///   with the `Window` granularity it's the dataflow of the nodes that no window reads
+pub fn get_layer_running_mode(
+    node_features: &NodeFeatures,
+    dim: usize,
+    mixed_settings: &MixedSettings,
+) -> RunningMode {
+    get_running_mode_by_density(node_features.get_density(dim), mixed_settings)
+}
+
+fn get_running_mode_by_density(density: f64, mixed_settings: &MixedSettings) -> RunningMode {
+    if density >= mixed_settings.dense_threshold {
+        RunningMode::Dense
+    } else {
+        RunningMode::Sparse
+    }
+}
+
+impl<'a> Iterator for OutputWindowIterator<'a> {
+    type Item = InputWindowIterator<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_start_output_index >= self.graph.get_num_node() {
+            return None;
+        }
+        // checked by `check_buffer_size`, never be 0
+        let output_size = self.get_output_size();
         let end_output_index = cmp::min(
             self.current_start_output_index + output_size,
             self.graph.get_num_node(),
@@ -213,10 +393,14 @@ impl<'a> Iterator for OutputWindowIterator<'a> {
             input_buffer_size: self.input_buffer_size,
             start_output_index: self.current_start_output_index,
             end_output_index,
-            gcn_hidden_size: self.gcn_hidden_size.clone(),
+            input_node_dim: self.get_input_dim(),
+            output_node_dim: self.get_output_dim(),
             final_iter,
             final_layer: self.final_layer,
-            running_mode: self.running_mode.clone(),
+            running_mode: self.resolve_running_mode(
+                self.current_start_output_index,
+                end_output_index,
+            ),
         };
         let intput_iter = InputWindowIterator::new(
             self.task_id.clone(),
@@ -241,7 +425,8 @@ pub struct InputWindowIterator<'a> {
     // current window information
     current_window_start_input_index: usize,
     current_window_end_input_index: usize,
-    gcn_hidden_size: Vec<usize>,
+    input_node_dim: usize,
+    output_node_dim: usize,
     final_iter: bool,
     final_layer: bool,
     running_mode: RunningMode,
@@ -251,7 +436,8 @@ pub struct InputIterSettings {
     pub input_buffer_size: usize,
     pub start_output_index: usize,
     pub end_output_index: usize,
-    pub gcn_hidden_size: Vec<usize>,
+    pub input_node_dim: usize,
+    pub output_node_dim: usize,
     pub final_iter: bool,
     pub final_layer: bool,
     pub running_mode: RunningMode,
@@ -267,7 +453,8 @@ impl<'a> InputWindowIterator<'a> {
             input_buffer_size,
             start_output_index,
             end_output_index,
-            gcn_hidden_size,
+            input_node_dim,
+            output_node_dim,
             final_iter,
             final_layer,
             running_mode,
@@ -281,7 +468,8 @@ impl<'a> InputWindowIterator<'a> {
             end_output_index,
             current_window_end_input_index: 0,
             current_window_start_input_index: 0,
-            gcn_hidden_size,
+            input_node_dim,
+            output_node_dim,
             final_iter,
             final_layer,
             running_mode,
@@ -298,34 +486,55 @@ impl<'a> Iterator for InputWindowIterator<'a> {
         } else {
             // first skip all emtpy rows
             while self.current_window_start_input_index < self.graph.get_num_node() {
-                if self
-                    .graph
-                    .is_row_range_empty(
-                        self.current_window_start_input_index,
-                        self.start_output_index,
-                        self.end_output_index,
-                    )
-                    .expect("is_row_range_empty should always return Some")
-                {
+                if self.graph.is_row_range_empty(
+                    self.current_window_start_input_index,
+                    self.start_output_index,
+                    self.end_output_index,
+                ) {
                     self.current_window_start_input_index += 1;
                 } else {
                     break;
                 }
             }
-            if self.current_window_start_input_index == self.graph.get_num_node() {
-                return None;
-            }
             let task_id = self.task_id.clone();
 
-            let input_node_dim = match task_id.layer_id {
-                0 => self.graph.get_feature_size(),
-                _ => *self.gcn_hidden_size.get(task_id.layer_id - 1).unwrap(),
-            };
-
-            let output_node_dim = match self.final_layer {
-                true => 1,
-                false => *self.gcn_hidden_size.get(self.task_id.layer_id).unwrap(),
-            };
+            // checked by `OutputWindowIterator::new`
+            let input_node_dim = self.input_node_dim;
+            let output_node_dim = self.output_node_dim;
+            if self.current_window_start_input_index == self.graph.get_num_node() {
+                if task_id.input_id != 0 {
+                    return None;
+                }
+                // no edge goes to this output window, still yield one empty window,
+                // otherwise the output window is never written and the next layer waits forever
+                let num_nodes = self.graph.get_num_node();
+                let tasks = (self.start_output_index..self.end_output_index)
+                    .map(|_| &[][..])
+                    .collect();
+                let range_index = RangeIndex {
+                    start_input_index: num_nodes,
+                    end_input_index: num_nodes,
+                    start_output_index: self.start_output_index,
+                    end_output_index: self.end_output_index,
+                };
+                self.task_id.input_id += 1;
+                return Some(InputWindow::new(
+                    task_id.clone(),
+                    Rc::new(tasks),
+                    range_index,
+                    Rc::new(OutputWindow::new(
+                        self.start_output_index,
+                        self.end_output_index,
+                        task_id,
+                        output_node_dim,
+                        input_node_dim,
+                        self.final_iter,
+                        self.final_layer,
+                        self.running_mode.clone(),
+                    )),
+                    true,
+                ));
+            }
 
             // build the window
             let mut x_size = 0;
@@ -339,9 +548,7 @@ impl<'a> Iterator for InputWindowIterator<'a> {
                     {
                         let new_size = self
                             .node_features
-                            .get_features(self.current_window_start_input_index + x_len)
-                            .len()
-                            * 4;
+                            .get_node_bytes(self.current_window_start_input_index + x_len);
                         debug!(
                             "old size: {},new size: {}, max size: {}",
                             x_size,
@@ -367,41 +574,47 @@ impl<'a> Iterator for InputWindowIterator<'a> {
                 RunningMode::Dense => {
                     // dense
                     info!("build dense window");
-                    x_len += (self.input_buffer_size / 2) / (input_node_dim * 4);
+                    let element_bytes = self.node_features.get_encoding().element_bytes();
+                    x_len += (self.input_buffer_size / 2) / (input_node_dim * element_bytes);
+                    // fix bug here, the last window should not go beyond the last node,
+                    // the shrinking below reads the row before the end of the window
+                    x_len = cmp::min(
+                        x_len,
+                        self.graph.get_num_node() - self.current_window_start_input_index,
+                    );
                     info!("x_len:{}", x_len);
                 }
                 RunningMode::Mixed => {
-                    todo!()
+                    unreachable!("the mixed mode should be resolved by the output window iterator")
                 }
             };
 
             debug!("the x_len is {}", x_len);
             if x_len == 0 {
-                panic!("x_len is 0, the while input buffer cannot add one more node");
+                unreachable!(
+                    "x_len is 0, the input buffer size is checked by OutputWindowIterator::new"
+                );
             }
             // shrink the window
             self.current_window_end_input_index = self.current_window_start_input_index + x_len;
 
-            while self
-                .graph
-                .is_row_range_empty(
-                    self.current_window_end_input_index - 1,
-                    self.start_output_index,
-                    self.end_output_index,
-                )
-                .expect("is_row_range_empty should always return Some")
-            {
+            while self.graph.is_row_range_empty(
+                self.current_window_end_input_index - 1,
+                self.start_output_index,
+                self.end_output_index,
+            ) {
                 debug!("shrink the window!");
                 self.current_window_end_input_index -= 1;
             }
 
             // build the current window
-            let csc = self.graph.get_csc();
             let mut tasks = Vec::new();
             let mut output_node_ids = Vec::new();
             for i in self.start_output_index..self.end_output_index {
-                let task = csc.get(i).unwrap().range(
-                    self.current_window_start_input_index..self.current_window_end_input_index,
+                let task = self.graph.get_csc_row_range(
+                    i,
+                    self.current_window_start_input_index,
+                    self.current_window_end_input_index,
                 );
 
                 tasks.push(task);
@@ -416,15 +629,11 @@ impl<'a> Iterator for InputWindowIterator<'a> {
             let mut is_last_row = true;
 
             while next_start_row < self.graph.get_num_node() {
-                if !self
-                    .graph
-                    .is_row_range_empty(
-                        next_start_row,
-                        self.start_output_index,
-                        self.end_output_index,
-                    )
-                    .expect("is_row_range_empty should always return Some")
-                {
+                if !self.graph.is_row_range_empty(
+                    next_start_row,
+                    self.start_output_index,
+                    self.end_output_index,
+                ) {
                     is_last_row = false;
                     break;
                 }
@@ -438,7 +647,7 @@ impl<'a> Iterator for InputWindowIterator<'a> {
                 start_output_index: self.start_output_index,
                 end_output_index: self.end_output_index,
             };
-            let current_window = InputWindow::new(
+            let mut current_window = InputWindow::new(
                 task_id.clone(),
                 tasks,
                 range_index,
@@ -450,9 +659,12 @@ impl<'a> Iterator for InputWindowIterator<'a> {
                     input_node_dim,
                     final_window,
                     self.final_layer,
+                    self.running_mode.clone(),
                 )),
                 is_last_row,
             );
+            current_window.shrunk_rows =
+                self.current_window_start_input_index + x_len - self.current_window_end_input_index;
 
             // prepare the next start x and start y
             self.current_window_start_input_index = next_start_row;
@@ -470,6 +682,10 @@ mod test {
     use log::debug;
 
     use super::*;
+    use crate::{
+        accelerator::memory_layout::MemoryLayout,
+        settings::{ElementType, EncodingSettings, LayoutSettings, SparseEncoding},
+    };
     #[test]
     fn sliding_window_test() {
         simple_logger::init_with_level(log::Level::Info).unwrap_or_default();
@@ -490,14 +706,18 @@ mod test {
         let gcn_hidden_size = vec![2];
         let window_iter_settings = WindowIterSettings {
             agg_buffer_size: 32,
-            input_buffer_size: 32,
+            input_buffer_size: 64,
             layer: 0,
             final_layer: false,
             running_mode: RunningMode::Sparse,
+            mixed_settings: MixedSettings {
+                dense_threshold: 0.5,
+                granularity: MixedGranularity::Window,
+            },
             gcn_hidden_size,
         };
         let output_window_iter =
-            OutputWindowIterator::new(&graph, &node_features, window_iter_settings);
+            OutputWindowIterator::new(&graph, &node_features, window_iter_settings).unwrap();
         for i in output_window_iter {
             debug!("{:?}\n", i);
             for j in i {
@@ -531,14 +751,18 @@ mod test {
         // max input num=2, max output num=1
         let window_iter_settings = WindowIterSettings {
             agg_buffer_size: 48,
-            input_buffer_size: 32,
+            input_buffer_size: 64,
             layer: 0,
             final_layer: false,
             running_mode: RunningMode::Sparse,
+            mixed_settings: MixedSettings {
+                dense_threshold: 0.5,
+                granularity: MixedGranularity::Window,
+            },
             gcn_hidden_size: gcn_hidden_size.clone(),
         };
         let output_window_iter =
-            OutputWindowIterator::new(&graph, &node_features1, window_iter_settings);
+            OutputWindowIterator::new(&graph, &node_features1, window_iter_settings)?;
         let mut total_windows = 0;
         for i in output_window_iter {
             debug!("{:?}\n\n", i);
@@ -549,14 +773,18 @@ mod test {
         }
         let window_iter_settings = WindowIterSettings {
             agg_buffer_size: 48,
-            input_buffer_size: 32,
+            input_buffer_size: 64,
             layer: 1,
             final_layer: true,
             running_mode: RunningMode::Sparse,
+            mixed_settings: MixedSettings {
+                dense_threshold: 0.5,
+                granularity: MixedGranularity::Window,
+            },
             gcn_hidden_size,
         };
         let output_window_iter =
-            OutputWindowIterator::new(&graph, &node_features2, window_iter_settings);
+            OutputWindowIterator::new(&graph, &node_features2, window_iter_settings)?;
         for i in output_window_iter {
             debug!("{:?}\n\n", i);
             for j in i {
@@ -567,4 +795,189 @@ mod test {
         assert_eq!(total_windows, 20);
         Ok(())
     }
+
+    #[test]
+    fn sliding_window_test_mixed() -> Result<(), Box<dyn std::error::Error>> {
+        simple_logger::init_with_level(log::Level::Info).unwrap_or_default();
+
+        let graph = Graph::from_rows(
+            &[
+                vec![1, 2],
+                vec![2, 3, 4],
+                vec![0, 1, 4],
+                vec![0, 2, 4],
+                vec![2, 4],
+            ],
+            6,
+        )?;
+        let node_features = NodeFeatures::from_dense(&[
+            vec![1., 1., 0., 0., 1., 1.],
+            vec![1., 0., 0., 0., 0., 0.],
+            vec![1., 1., 1., 0., 0., 1.],
+            vec![1., 1., 1., 1., 1., 1.],
+            vec![0., 0., 0., 0., 0., 1.],
+        ]);
+        let get_modes = |dense_threshold, granularity| {
+            let window_iter_settings = WindowIterSettings {
+                agg_buffer_size: 48,
+                input_buffer_size: 128,
+                layer: 0,
+                final_layer: false,
+                running_mode: RunningMode::Mixed,
+                gcn_hidden_size: vec![2],
+                mixed_settings: MixedSettings {
+                    dense_threshold,
+                    granularity,
+                },
+            };
+            OutputWindowIterator::new(&graph, &node_features, window_iter_settings)
+                .unwrap()
+                .flat_map(|input_iter| input_iter.map(|w| w.output_window.running_mode.clone()))
+                .collect::<Vec<_>>()
+        };
+        // the layer density is 16/30
+        assert!(get_modes(0.5, MixedGranularity::Layer)
+            .iter()
+            .all(|x| *x == RunningMode::Dense));
+        assert!(get_modes(0.6, MixedGranularity::Layer)
+            .iter()
+            .all(|x| *x == RunningMode::Sparse));
+
+        // the first output window(node 0) aggregates node 1 and 2, density is 5/12
+        // the second output window(node 1) aggregates node 2,3 and 4, density is 11/18
+        let modes = get_modes(0.5, MixedGranularity::Window);
+        assert_eq!(modes.first(), Some(&RunningMode::Sparse));
+        assert!(modes.contains(&RunningMode::Dense));
+        Ok(())
+    }
+
+    #[test]
+    fn sliding_window_test_dense() -> Result<(), Box<dyn std::error::Error>> {
+        // every node aggregates all nodes, so no window is shrunk
+        let graph = Graph::from_rows(&vec![vec![0, 1, 2, 3, 4]; 5], 6)?;
+        let node_features = vec![NodeFeatures::from_dense(&vec![vec![1.; 6]; 5])];
+        let window_iter_settings = WindowIterSettings {
+            agg_buffer_size: 240,
+            input_buffer_size: 128,
+            layer: 0,
+            final_layer: false,
+            running_mode: RunningMode::Dense,
+            gcn_hidden_size: vec![2],
+            mixed_settings: MixedSettings {
+                dense_threshold: 0.5,
+                granularity: MixedGranularity::Layer,
+            },
+        };
+        // the dense row is 24 bytes, 2 nodes fit in half of the input buffer,
+        // the last window stops at the last node
+        let windows = OutputWindowIterator::new(&graph, &node_features[0], window_iter_settings)?
+            .flatten()
+            .map(|w| (w.start_input_index, w.end_input_index, w.is_last_row))
+            .collect::<Vec<_>>();
+        assert_eq!(windows, vec![(0, 2, false), (2, 4, false), (4, 5, true)]);
+
+        // the dense rows are packed, the requests start at the 64 bytes boundary before the window
+        let layout = MemoryLayout::new(
+            &graph,
+            &node_features,
+            &[2],
+            &RunningMode::Dense,
+            &LayoutSettings {
+                alignment: 1024,
+                row_padding: false,
+                channels: 1,
+                interleave_bytes: 256,
+            },
+        )?;
+        let base = layout.dense_requests(0, 0, 1)?[0];
+        let requests = windows
+            .iter()
+            .map(|&(start, end, _)| {
+                layout
+                    .dense_requests(0, start, end)
+                    .map(|x| x.iter().map(|addr| addr - base).collect::<Vec<_>>())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(requests, vec![vec![0], vec![0, 64], vec![64]]);
+        Ok(())
+    }
+
+    #[test]
+    fn sliding_window_test_buffer_too_small() -> Result<(), Box<dyn std::error::Error>> {
+        let graph = Graph::from_rows(&[vec![0, 1, 2], vec![1, 2, 0], vec![2, 0, 1]], 6)?;
+        let rows = [
+            vec![0., 0., 1., 0., 1., 0.],
+            vec![1., 0., 0., 1., 1., 1.],
+            vec![1., 1., 0., 0., 0., 1.],
+        ];
+        let node_features = NodeFeatures::from_dense(&rows);
+        let quantized = NodeFeatures::from_dense(&rows).with_encoding(
+            EncodingSettings {
+                element_type: ElementType::Int8,
+                index_bytes: 2,
+                sparse_encoding: SparseEncoding::Csr,
+            },
+            6,
+        )?;
+        let new_iter = |node_features, running_mode, input_buffer_size| {
+            let window_iter_settings = WindowIterSettings {
+                agg_buffer_size: 64,
+                input_buffer_size,
+                layer: 0,
+                final_layer: false,
+                running_mode,
+                gcn_hidden_size: vec![2],
+                mixed_settings: MixedSettings {
+                    dense_threshold: 0.5,
+                    granularity: MixedGranularity::Window,
+                },
+            };
+            OutputWindowIterator::new(&graph, node_features, window_iter_settings).map(|_| ())
+        };
+        // the longest sparse row is 4 elements of 4 bytes index and 4 bytes value, 32 bytes
+        assert_eq!(new_iter(&node_features, RunningMode::Sparse, 64), Ok(()));
+        assert_eq!(
+            new_iter(&node_features, RunningMode::Sparse, 48),
+            Err(GcnAggError::BufferTooSmall {
+                buffer: "input_buffer".into(),
+                size: 24,
+                required: 32,
+                layer: 0,
+            })
+        );
+        // the dense row is 6 elements, 24 bytes
+        assert_eq!(
+            new_iter(&node_features, RunningMode::Dense, 32),
+            Err(GcnAggError::BufferTooSmall {
+                buffer: "input_buffer".into(),
+                size: 16,
+                required: 24,
+                layer: 0,
+            })
+        );
+        // int8 with 16 bits index: the sparse row is 12 bytes, the dense row is 6 bytes
+        assert_eq!(new_iter(&quantized, RunningMode::Sparse, 24), Ok(()));
+        assert_eq!(new_iter(&quantized, RunningMode::Dense, 12), Ok(()));
+
+        // no hidden size for the output of layer 1
+        let new_layer_iter = |layer, final_layer| {
+            let window_iter_settings = WindowIterSettings {
+                agg_buffer_size: 64,
+                input_buffer_size: 64,
+                layer,
+                final_layer,
+                running_mode: RunningMode::Sparse,
+                gcn_hidden_size: vec![2],
+                mixed_settings: MixedSettings {
+                    dense_threshold: 0.5,
+                    granularity: MixedGranularity::Layer,
+                },
+            };
+            OutputWindowIterator::new(&graph, &node_features, window_iter_settings).map(|_| ())
+        };
+        assert!(matches!(new_layer_iter(1, false), Err(GcnAggError::Config(_))));
+        assert!(matches!(new_layer_iter(2, true), Err(GcnAggError::Config(_))));
+        assert_eq!(new_layer_iter(1, true), Ok(()));
+        Ok(())
+    }
 }
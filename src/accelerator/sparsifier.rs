@@ -1,8 +1,18 @@
 //! the mod sparsifier is a component to translate the dense result to sparse result
 //!
+//! # Cost
+//! - each core compares one value or emits one entry in a cycle, the work is divided across the cores
+//! - every value of the rows out of the mlp is compared, then the entries of the output encoding are emitted:
+//!   - `Csr`: the index and value of each nonzero, and the row pointer of each row
+//!   - `Bitmap`: a 32 bits word for each 32 columns of each row, and the value of each nonzero
+//!   - `RunLength`: each run-length entry, see `SparseEncoding::RunLength`
 //!
 
-use crate::node_features::NodeFeatures;
+use crate::{
+    error::GcnAggError,
+    node_features::NodeFeatures,
+    settings::{run_length_entries, EncodingSettings, SparseEncoding},
+};
 
 use super::component::Component;
 
@@ -17,10 +27,11 @@ pub struct Sparsifier {
     pub state: SparsifierState,
     pub remaining_cycle: u64,
     pub num_cores: usize,
+    encoding: EncodingSettings,
 }
 
 impl Component for Sparsifier {
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
         if self.state == SparsifierState::Working {
             if self.remaining_cycle == 0 {
                 self.state = SparsifierState::Idle;
@@ -33,38 +44,134 @@ impl Component for Sparsifier {
 }
 
 impl Sparsifier {
-    pub fn new(num_cores: usize) -> Sparsifier {
-        Sparsifier {
+    /// # Description
+    /// - the sparsifier of `num_cores` cores, producing the sparse nodes in `encoding`
+    /// # Errors
+    /// - `Config` if `num_cores` is 0
+    pub fn new(num_cores: usize, encoding: EncodingSettings) -> Result<Sparsifier, GcnAggError> {
+        if num_cores == 0 {
+            return Err(GcnAggError::Config(
+                "the sparsifier should have at least 1 core".into(),
+            ));
+        }
+        Ok(Sparsifier {
             state: SparsifierState::Idle,
             remaining_cycle: 0,
             num_cores,
-        }
+            encoding,
+        })
     }
 
     /// # Description
     /// - this is so ***tricky***! be careful!
-    /// - we already know the input dimension and know the result sparse vector, so we do not need to know what is the input data!
+    /// - we already know the result sparse vector, so we do not need to know what is the input data!
     /// # Arguments
-    /// - input_dim: the input dimension
-    /// - input_node_num: the number of input nodes
-    /// - output_feature: the csc sparse vector of output nodes
+    /// - dim: the number of values of each row out of the mlp
+    /// - start, end: the output nodes of the window
+    /// - output_feature: the node features of the next layer, the result of the sparsifier
     ///
     pub fn add_task(
         &mut self,
-        _input_dim: usize,
-        _input_node_num: usize,
-        _output_feature: &NodeFeatures,
+        dim: usize,
+        start: usize,
+        end: usize,
+        output_feature: &NodeFeatures,
     ) {
-        self.remaining_cycle = 10;
+        self.remaining_cycle = self.get_cycles(dim, start, end, output_feature);
         self.state = SparsifierState::Working;
     }
 
-    pub fn add_task_last_layer(&mut self) {
-        self.remaining_cycle = 1;
+    /// # Description
+    /// - the final layer is not sparsified, the `num_nodes` output values are only moved to the output buffer
+    pub fn add_task_last_layer(&mut self, num_nodes: usize) {
+        self.remaining_cycle = num_nodes.div_ceil(self.num_cores) as u64;
         self.state = SparsifierState::Working;
     }
 
+    /// # Description
+    /// - the cycles to sparsify the rows `start..end` of `dim` values into `output_feature`, see the module doc
+    pub fn get_cycles(
+        &self,
+        dim: usize,
+        start: usize,
+        end: usize,
+        output_feature: &NodeFeatures,
+    ) -> u64 {
+        let rows = end - start;
+        let entries: usize = (start..end)
+            .map(|i| output_feature.get_features(i))
+            .map(|x| match self.encoding.sparse_encoding {
+                SparseEncoding::Csr => x.len() + 1,
+                SparseEncoding::Bitmap => dim.div_ceil(32) + x.len(),
+                SparseEncoding::RunLength => run_length_entries(x),
+            })
+            .sum();
+        (rows * dim + entries).div_ceil(self.num_cores) as u64
+    }
+
     pub fn finished_sparsify(&self) -> bool {
         self.state == SparsifierState::Idle
     }
+
+    /// # Description
+    /// - the number of following cycles that the sparsifier will only count down
+    /// - `u64::MAX` means it's idle and will never change by itself
+    pub fn get_quiet_cycles(&self) -> u64 {
+        match self.state {
+            SparsifierState::Idle => u64::MAX,
+            SparsifierState::Working => self.remaining_cycle,
+        }
+    }
+
+    /// # Description
+    /// - skip `cycles` cycles, `cycles` should not be larger than `get_quiet_cycles`
+    pub fn fast_forward(&mut self, cycles: u64) {
+        if self.state == SparsifierState::Working {
+            self.remaining_cycle -= cycles;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sparsifier_cycles() -> Result<(), GcnAggError> {
+        // 3 nodes of 64 columns, with 0, 2 and 64 nonzeros
+        let mut rows = vec![vec![0.; 64]; 3];
+        rows[1][3] = 1.;
+        rows[1][40] = 1.;
+        rows[2] = vec![1.; 64];
+        let features = NodeFeatures::from_dense(&rows);
+        let sparsifier = |sparse_encoding, num_cores| {
+            let encoding = EncodingSettings {
+                sparse_encoding,
+                ..Default::default()
+            };
+            Sparsifier::new(num_cores, encoding)
+        };
+
+        // 192 values to compare, the nonzeros and 3 row pointers
+        let csr = sparsifier(SparseEncoding::Csr, 1)?;
+        assert_eq!(csr.get_cycles(64, 0, 3, &features), 192 + 66 + 3);
+        assert_eq!(csr.get_cycles(64, 0, 2, &features), 128 + 2 + 2);
+        // 2 words of the bitmap for each row
+        let bitmap = sparsifier(SparseEncoding::Bitmap, 1)?;
+        assert_eq!(bitmap.get_cycles(64, 0, 3, &features), 192 + 66 + 6);
+        let run_length = sparsifier(SparseEncoding::RunLength, 1)?;
+        assert_eq!(run_length.get_cycles(64, 0, 3, &features), 192 + 66);
+        // divided across the cores
+        let csr = sparsifier(SparseEncoding::Csr, 4)?;
+        assert_eq!(csr.get_cycles(64, 0, 3, &features), 261_u64.div_ceil(4));
+
+        let mut csr = csr;
+        csr.add_task_last_layer(9);
+        assert_eq!(csr.get_quiet_cycles(), 3);
+        assert!(matches!(
+            sparsifier(SparseEncoding::Csr, 0),
+            Err(GcnAggError::Config(_))
+        ));
+        Ok(())
+    }
 }
@@ -8,6 +8,7 @@
 use std::{mem::swap, rc::Rc};
 
 use super::{component::Component, sliding_window::OutputWindow};
+use crate::error::GcnAggError;
 #[derive(Debug, PartialEq)]
 pub enum BufferStatus {
     Empty,
@@ -47,7 +48,7 @@ impl Component for SparsifyBuffer {
     ///
     /// ```
     ///
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
         if let (BufferStatus::WaitingToSparsify, BufferStatus::Empty) = (&self.current_state, &self.next_state) {
             swap(&mut self.current_state, &mut self.next_state);
             swap(&mut self.current_window, &mut self.next_window);
@@ -66,21 +67,49 @@ impl SparsifyBuffer {
         }
     }
 
-    pub fn start_mlp(&mut self, output_window: Rc<OutputWindow>) {
-        assert_eq!(self.current_state, BufferStatus::Empty);
+    /// # Description
+    /// - return `InvalidState` if `state` is not `expected`
+    fn check_state(
+        action: &str,
+        state: &BufferStatus,
+        expected: BufferStatus,
+    ) -> Result<(), GcnAggError> {
+        match *state == expected {
+            true => Ok(()),
+            false => Err(GcnAggError::InvalidState(format!(
+                "sparsify_buffer {}: the state is {:?}, expected {:?}",
+                action, state, expected
+            ))),
+        }
+    }
+
+    pub fn start_mlp(&mut self, output_window: Rc<OutputWindow>) -> Result<(), GcnAggError> {
+        Self::check_state("start_mlp", &self.current_state, BufferStatus::Empty)?;
         self.current_state = BufferStatus::Writing;
         self.current_window = Some(output_window);
+        Ok(())
     }
-    pub fn finished_mlp(&mut self) {
-        assert_eq!(self.current_state, BufferStatus::Writing);
+    pub fn finished_mlp(&mut self) -> Result<(), GcnAggError> {
+        Self::check_state("finished_mlp", &self.current_state, BufferStatus::Writing)?;
         self.current_state = BufferStatus::WaitingToSparsify;
+        Ok(())
     }
-    pub fn start_sparsify(&mut self) {
-        assert_eq!(self.next_state, BufferStatus::WaitingToSparsify);
+    pub fn start_sparsify(&mut self) -> Result<(), GcnAggError> {
+        Self::check_state(
+            "start_sparsify",
+            &self.next_state,
+            BufferStatus::WaitingToSparsify,
+        )?;
         self.next_state = BufferStatus::Sparsifying;
+        Ok(())
     }
-    pub fn finished_sparsify(&mut self) {
-        assert_eq!(self.next_state, BufferStatus::Sparsifying);
+    pub fn finished_sparsify(&mut self) -> Result<(), GcnAggError> {
+        Self::check_state(
+            "finished_sparsify",
+            &self.next_state,
+            BufferStatus::Sparsifying,
+        )?;
         self.next_state = BufferStatus::Empty;
+        Ok(())
     }
 }
@@ -1,17 +1,29 @@
 use super::{
+    address_generator::{get_address_generator, NodeReadModes},
     agg_buffer::{self, AggBuffer},
     aggregator::{self, Aggregator},
     component::Component,
+    deadlock::{ComponentName, DeadlockDetector, WaitFor},
+    functional::FunctionalModel,
     input_buffer::{self, InputBuffer},
     mem_interface::MemInterface,
+    memory_backend::build_memory_backend,
+    memory_layout::{MemoryLayout, REQUEST_BYTES},
     mlp::{self, Mlp},
     output_buffer::{self, OutputBuffer},
-    sliding_window::{InputWindow, InputWindowIterator, OutputWindowIterator},
+    sliding_window::{
+        get_layer_running_mode, InputWindow, InputWindowIterator, OutputWindow,
+        OutputWindowIterator,
+    },
     sparsifier::{self, Sparsifier},
     sparsify_buffer::{self, SparsifyBuffer},
+    tracer::{TraceTrack, Tracer},
+    weight_buffer::{self, WeightBuffer},
+    window_id::WindowId,
 };
 
-use log::{debug, warn};
+use itertools::Itertools;
+use log::{debug, info, warn};
 /// # Description
 /// the state for the system
 /// * `Idle` means this is the very first of each layer, need to init the new output and input iter
@@ -29,9 +41,14 @@ enum SystemState {
 
 use crate::{
     accelerator::sliding_window::WindowIterSettings,
-    gcn_result::GcnStatistics,
+    error::GcnAggError,
+    gcn_result::{
+        ComponentStatus, ComponentsStatistics, GcnStatistics, LayerStatistics, StallCause,
+    },
     settings::{
-        AcceleratorSettings, AggregatorSettings, MlpSettings, RunningMode, SparsifierSettings,
+        AcceleratorSettings, AggregatorSettings, DeadlockSettings, MemoryBackendType,
+        MixedGranularity, MixedSettings, MlpSettings, Normalization, RunningMode,
+        SparsifierSettings,
     },
 };
 use crate::{graph::Graph, node_features::NodeFeatures};
@@ -46,9 +63,12 @@ pub struct System<'a> {
     output_buffer: OutputBuffer,
     sparsify_buffer: SparsifyBuffer,
     agg_buffer: AggBuffer,
+    weight_buffer: WeightBuffer,
     mem_interface: MemInterface,
+    memory_layout: MemoryLayout,
     sparsifier: Sparsifier,
     running_mode: RunningMode,
+    mixed_settings: MixedSettings,
     mlp: Mlp,
 
     graph: &'a Graph,
@@ -64,8 +84,22 @@ pub struct System<'a> {
     gcn_layer_num: usize,
     gcn_hidden_size: Vec<usize>,
 
-    possible_deadloack_count: usize,
-    deadlock_count: usize,
+    deadlock_detector: DeadlockDetector,
+
+    fast_forward: bool,
+    // no handler is triggered in the last cycle
+    idle_cycle: bool,
+    skipped_cycles: u64,
+
+    component_stats: ComponentsStatistics,
+    layer_stats: Vec<LayerStatistics>,
+    // the dataflows that the next layer reads the output nodes of each layer in, only for the `Window` granularity
+    next_layer_read_modes: Vec<NodeReadModes>,
+    // the statistics file of ramulator
+    mem_stats_file: Option<String>,
+    tracer: Option<Tracer>,
+    // compute the real values if the functional simulation is enabled
+    functional: Option<FunctionalModel>,
 }
 
 impl Component for System<'_> {
@@ -74,7 +108,8 @@ impl Component for System<'_> {
     /// * will update each component's status
     /// * will ***NOT*** update the cycle
     ///
-    fn cycle(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn cycle(&mut self) -> Result<(), GcnAggError> {
+        self.idle_cycle = false;
         match &self.state {
             SystemState::Working => {
                 //debug!("running,working:{}", self.total_cycle);
@@ -98,6 +133,9 @@ impl Component for System<'_> {
                 if self.handle_input_buffer_to_mem()? {
                     return Ok(());
                 }
+                if self.handle_weight_buffer_to_mem()? {
+                    return Ok(());
+                }
                 if self.handle_mem_to_input_buffer()? {
                     return Ok(());
                 }
@@ -126,24 +164,8 @@ impl Component for System<'_> {
                     return Ok(());
                 }
 
-                // did nothings
-                self.possible_deadloack_count += 1;
-                if self.possible_deadloack_count == 200000 {
-                    warn!("possible deadlock, current cycle:{}", self.total_cycle);
-                    self.possible_deadloack_count = 0;
-                    warn!("input_buffer:{:?}", self.input_buffer);
-                    warn!("output_buffer:{:?}", self.output_buffer);
-                    warn!("agg_buffer:{:?}", self.agg_buffer);
-                    warn!("mem_interface:{:?}", self.mem_interface);
-                    warn!("sparsifier:{:?}", self.sparsifier);
-                    warn!("aggregator:{:?}", self.aggregator);
-                    warn!("mlp:{:?}", self.mlp);
-                    warn!("sparsify_buffer:{:?}\n\n\n\n\n", self.sparsify_buffer);
-                    self.deadlock_count += 1;
-                    if self.deadlock_count == 10 {
-                        panic!("deadlock");
-                    }
-                }
+                // did nothings, the deadlock detector is checked in run
+                self.idle_cycle = true;
             }
             SystemState::NoMoreWindow => {
                 // debug!("no more window");
@@ -160,6 +182,9 @@ impl Component for System<'_> {
                 if self.handle_input_buffer_to_mem()? {
                     return Ok(());
                 }
+                if self.handle_weight_buffer_to_mem()? {
+                    return Ok(());
+                }
                 if self.handle_mem_to_input_buffer()? {
                     return Ok(());
                 }
@@ -188,24 +213,8 @@ impl Component for System<'_> {
                     return Ok(());
                 }
 
-                // did nothings
-                self.possible_deadloack_count += 1;
-                if self.possible_deadloack_count == 200000 {
-                    warn!("possible deadlock, current cycle:{}", self.total_cycle);
-                    self.possible_deadloack_count = 0;
-                    warn!("input_buffer:{:?}", self.input_buffer);
-                    warn!("output_buffer:{:?}", self.output_buffer);
-                    warn!("agg_buffer:{:?}", self.agg_buffer);
-                    warn!("mem_interface:{:?}", self.mem_interface);
-                    warn!("sparsifier:{:?}", self.sparsifier);
-                    warn!("aggregator:{:?}", self.aggregator);
-                    warn!("mlp:{:?}", self.mlp);
-                    warn!("sparsify_buffer:{:?}\n\n\n\n\n", self.sparsify_buffer);
-                    self.deadlock_count += 1;
-                    if self.deadlock_count == 10 {
-                        panic!("deadlock");
-                    }
-                }
+                // did nothings, the deadlock detector is checked in run
+                self.idle_cycle = true;
             }
             &SystemState::ChangedLayer => {
                 // cannot add new task until the current layer is finished(triggle by handle_start_writeback)
@@ -222,6 +231,9 @@ impl Component for System<'_> {
                 if self.handle_input_buffer_to_mem()? {
                     return Ok(());
                 }
+                if self.handle_weight_buffer_to_mem()? {
+                    return Ok(());
+                }
                 if self.handle_mem_to_input_buffer()? {
                     return Ok(());
                 }
@@ -250,24 +262,8 @@ impl Component for System<'_> {
                     return Ok(());
                 }
 
-                // did nothings
-                self.possible_deadloack_count += 1;
-                if self.possible_deadloack_count == 200000 {
-                    warn!("possible deadlock, current cycle:{}", self.total_cycle);
-                    self.possible_deadloack_count = 0;
-                    warn!("input_buffer:{:?}", self.input_buffer);
-                    warn!("output_buffer:{:?}", self.output_buffer);
-                    warn!("agg_buffer:{:?}", self.agg_buffer);
-                    warn!("mem_interface:{:?}", self.mem_interface);
-                    warn!("sparsifier:{:?}", self.sparsifier);
-                    warn!("aggregator:{:?}", self.aggregator);
-                    warn!("mlp:{:?}", self.mlp);
-                    warn!("sparsify_buffer:{:?}\n\n\n\n\n", self.sparsify_buffer);
-                    self.deadlock_count += 1;
-                    if self.deadlock_count == 10 {
-                        panic!("deadlock");
-                    }
-                }
+                // did nothings, the deadlock detector is checked in run
+                self.idle_cycle = true;
             }
             SystemState::Finished => {
                 debug!("finished");
@@ -279,22 +275,38 @@ impl Component for System<'_> {
 }
 
 impl<'a> System<'a> {
+    /// # Description
+    /// - build the system, the settings and the inputs are checked here
+    /// # Errors
+    /// - `Config` if the number of layers does not match, the node features are not encoded by `encoding_settings`,
+    ///   or the memory backend cannot be built
+    /// - `InputFormat` if the node features do not match the graph
+    /// - `BufferTooSmall` if any layer cannot fit into the buffers
     pub fn new(
         graph: &'a Graph,
         node_features: &'a [NodeFeatures],
         acc_settings: AcceleratorSettings,
         stats_name: &str,
-    ) -> System<'a> {
+    ) -> Result<System<'a>, GcnAggError> {
         let AcceleratorSettings {
             input_buffer_size,
             agg_buffer_size,
+            weight_buffer_size,
             gcn_hidden_size,
             aggregator_settings,
             mlp_settings,
             sparsifier_settings,
             // output_buffer_size,
             running_mode,
+            mixed_settings,
+            encoding_settings,
+            layout_settings,
+            memory_settings,
             mem_config_name,
+            fast_forward,
+            trace_path,
+            deadlock_settings,
+            functional_settings,
         } = acc_settings;
 
         let AggregatorSettings {
@@ -310,42 +322,133 @@ impl<'a> System<'a> {
             mlp_sparse_cores,
         } = mlp_settings;
 
+        if node_features.is_empty() {
+            return Err(GcnAggError::Config("node_features is empty".into()));
+        }
+        if node_features.len() != gcn_hidden_size.len() + 1 {
+            return Err(GcnAggError::Config(format!(
+                "there are {} layers of node features, but {} gcn hidden sizes",
+                node_features.len(),
+                gcn_hidden_size.len()
+            )));
+        }
+        if graph.get_num_node() == 0 {
+            return Err(GcnAggError::Config("the graph is empty".into()));
+        }
+        for (layer, features) in node_features.iter().enumerate() {
+            if features.len() != graph.get_num_node() {
+                return Err(GcnAggError::input_format(
+                    "node_features",
+                    None,
+                    format!(
+                        "layer {} has {} nodes, but the graph has {} nodes",
+                        layer,
+                        features.len(),
+                        graph.get_num_node()
+                    ),
+                ));
+            }
+            if *features.get_encoding() != encoding_settings {
+                return Err(GcnAggError::Config(format!(
+                    "layer {} is encoded as {:?}, but the accelerator uses {:?}",
+                    layer,
+                    features.get_encoding(),
+                    encoding_settings
+                )));
+            }
+        }
+
         let SparsifierSettings { sparsifier_cores } = sparsifier_settings;
-        let aggregator = Aggregator::new(sparse_cores, sparse_width, dense_cores, dense_width);
+        let DeadlockSettings {
+            threshold,
+            snapshot_path,
+        } = deadlock_settings;
+        let normalization = graph
+            .get_degree_data()
+            .map_or(Normalization::None, |x| x.normalization);
+        let aggregator = Aggregator::new(
+            sparse_cores,
+            sparse_width,
+            dense_cores,
+            dense_width,
+            normalization,
+        );
 
         let input_buffer = InputBuffer::new();
         let output_buffer = OutputBuffer::new();
         let sparsify_buffer = SparsifyBuffer::new();
         let agg_buffer = AggBuffer::new(graph.get_num_node(), running_mode.clone());
 
-        let mem_interface = MemInterface::new(64, 64, &mem_config_name, stats_name);
+        let mem_stats_file = match memory_settings.backend {
+            MemoryBackendType::Ramulator => Some(stats_name.to_string()),
+            _ => None,
+        };
+        let mem_interface = MemInterface::new(
+            64,
+            64,
+            build_memory_backend(&memory_settings, &mem_config_name, stats_name)?,
+        );
         let mlp = Mlp::new(systolic_rows, systolic_cols, mlp_sparse_cores);
         let gcn_layer_num = node_features.len();
-        let window_iter_settings = WindowIterSettings {
-            agg_buffer_size,
-            input_buffer_size,
-            gcn_hidden_size: gcn_hidden_size.clone(),
-            final_layer: gcn_layer_num == 1,
-            running_mode: running_mode.clone(),
-            layer: 0,
-        };
-        let mut current_output_iter = OutputWindowIterator::new(
+        let memory_layout = MemoryLayout::new(
             graph,
-            node_features.get(0).expect("node_features is empty"),
-            window_iter_settings,
-        );
+            node_features,
+            &gcn_hidden_size,
+            &running_mode,
+            &layout_settings,
+        )?;
+        let sparsifier = Sparsifier::new(sparsifier_cores, encoding_settings)?;
+        let weight_buffer = WeightBuffer::new(weight_buffer_size);
+        for layer in 0..gcn_layer_num {
+            weight_buffer.requests_per_tile(layer, memory_layout.weight_requests(layer).len())?;
+        }
+        // check all the layers before running, the first layer is used directly
+        let mut output_iters: Vec<_> = (0..gcn_layer_num)
+            .map(|layer| {
+                let window_iter_settings = WindowIterSettings {
+                    agg_buffer_size,
+                    input_buffer_size,
+                    gcn_hidden_size: gcn_hidden_size.clone(),
+                    final_layer: layer == gcn_layer_num - 1,
+                    running_mode: running_mode.clone(),
+                    mixed_settings: mixed_settings.clone(),
+                    layer,
+                };
+                OutputWindowIterator::new(graph, &node_features[layer], window_iter_settings)
+            })
+            .try_collect()?;
+        // with the `Window` granularity, the windows of the next layer decide the dataflows of the writeback
+        let next_layer_read_modes = match (&running_mode, mixed_settings.granularity) {
+            (RunningMode::Mixed, MixedGranularity::Window) => output_iters
+                .drain(1..)
+                .enumerate()
+                .map(|(layer, output_iter)| {
+                    let fallback = get_layer_running_mode(
+                        &node_features[layer + 1],
+                        gcn_hidden_size[layer],
+                        &mixed_settings,
+                    );
+                    NodeReadModes::new(graph.get_num_node(), output_iter.flatten(), fallback)
+                })
+                .collect(),
+            _ => vec![],
+        };
+        let functional = functional_settings
+            .map(|settings| {
+                FunctionalModel::new(graph, &node_features[0], &gcn_hidden_size, &settings)
+            })
+            .transpose()?;
+        let mut current_output_iter = output_iters.swap_remove(0);
         let mut current_input_iter = current_output_iter
             .next()
-            .expect("cannot build the first input iter");
-        let current_window = Some(
-            current_input_iter
-                .next()
-                .expect("cannot build the first window"),
-        );
+            .ok_or_else(|| GcnAggError::InvalidState("cannot build the first input iter".into()))?;
+        let current_window = Some(current_input_iter.next().ok_or_else(|| {
+            GcnAggError::input_format("graph", None, "the first node has no edge")
+        })?);
 
         let state = SystemState::Working;
         debug!("finished build the system");
-        System {
+        Ok(System {
             state,
             finished: false,
             total_cycle: 0,
@@ -354,8 +457,11 @@ impl<'a> System<'a> {
             output_buffer,
             sparsify_buffer,
             agg_buffer,
+            weight_buffer,
             running_mode,
+            mixed_settings,
             mem_interface,
+            memory_layout,
             graph,
             node_features,
             input_buffer_size,
@@ -368,17 +474,25 @@ impl<'a> System<'a> {
             gcn_layer_num,
             gcn_hidden_size,
             mlp,
-            sparsifier: Sparsifier::new(sparsifier_cores),
-            possible_deadloack_count: 0,
-            deadlock_count: 0,
-        }
+            sparsifier,
+            deadlock_detector: DeadlockDetector::new(threshold, snapshot_path),
+            fast_forward,
+            idle_cycle: false,
+            skipped_cycles: 0,
+            component_stats: ComponentsStatistics::default(),
+            layer_stats: (0..gcn_layer_num).map(LayerStatistics::new).collect(),
+            next_layer_read_modes,
+            mem_stats_file,
+            tracer: trace_path.map(|path| Tracer::new(&path)),
+            functional,
+        })
     }
     /// # Description
     /// - this function just move to the next window, or change the layer. ***don't modify any states here***!!!
     ///
     /// ---
     /// sjq
-    pub fn move_to_next_window(&mut self) {
+    pub fn move_to_next_window(&mut self) -> Result<(), GcnAggError> {
         // go through the current_input_iter and current_output_iter to get the next window
         // if the current_input_iter is finished, then get the next input iter
         // if the current_output_iter is finished, then get the next output iter
@@ -402,7 +516,7 @@ impl<'a> System<'a> {
                     debug!("No more window!");
                     // self.finished = true;
                     self.state = SystemState::NoMoreWindow;
-                    return;
+                    return Ok(());
                 }
                 let window_iter_settings = WindowIterSettings {
                     agg_buffer_size: self.agg_buffer_size,
@@ -410,42 +524,74 @@ impl<'a> System<'a> {
                     gcn_hidden_size: self.gcn_hidden_size.clone(),
                     final_layer: self.current_layer == self.gcn_layer_num - 1,
                     running_mode: self.running_mode.clone(),
+                    mixed_settings: self.mixed_settings.clone(),
                     layer: self.current_layer,
                 };
 
                 self.current_output_iter = OutputWindowIterator::new(
                     self.graph,
-                    self.node_features
-                        .get(self.current_layer)
-                        .unwrap_or_else(|| {
-                            panic!("node_features is empty, layer: {}", self.current_layer)
-                        }),
+                    self.node_features.get(self.current_layer).ok_or_else(|| {
+                        GcnAggError::InvalidState(format!(
+                            "no node features for layer: {}",
+                            self.current_layer
+                        ))
+                    })?,
                     window_iter_settings,
-                );
-                self.current_input_iter = self
-                    .current_output_iter
-                    .next()
-                    .expect("cannot build the first input iter");
+                )?;
+                self.current_input_iter = self.current_output_iter.next().ok_or_else(|| {
+                    GcnAggError::InvalidState("cannot build the first input iter".into())
+                })?;
                 next_window = self.current_input_iter.next();
                 debug!("cannot get the next window,move to the next output iter and input iter and get the next window:{:?}", next_window);
                 self.state = SystemState::ChangedLayer;
             }
         }
         self.current_window = next_window;
+        Ok(())
     }
     /// # Description
     /// keep running until all finished
     /// * for each cycle, it will call the cycle function
     /// * and increase the total_cycle
-    pub fn run(&mut self) -> Result<GcnStatistics, Box<dyn std::error::Error>> {
+    pub fn run(&mut self) -> Result<GcnStatistics, GcnAggError> {
         debug!("start running");
         while !self.finished {
             self.cycle()?;
+            self.handle_issued_writes();
+            // waiting for a countdown or the memory is not a stall
+            match self.idle_cycle && !self.is_counting_down() {
+                true => self.deadlock_detector.stall(1),
+                false => self.deadlock_detector.progress(),
+            }
+            if self.deadlock_detector.is_deadlocked() {
+                return Err(self.report_deadlock());
+            }
             self.total_cycle += 1;
+            self.record_component_stats(1);
+            if self.fast_forward && self.idle_cycle {
+                self.skip_quiet_cycles();
+            }
         }
         self.print_stats();
         let mut gcn_statistics = GcnStatistics::new();
         gcn_statistics.cycle = self.total_cycle;
+        gcn_statistics.components = self.component_stats.clone();
+        gcn_statistics.memory = self.mem_interface.get_statistics();
+        gcn_statistics.memory.stats_file = self.mem_stats_file.clone();
+        if let Some(tracer) = &self.tracer {
+            tracer.write()?;
+            gcn_statistics.trace_file = Some(tracer.get_path().to_string());
+        }
+        if let Some(functional) = &self.functional {
+            let stats = functional.check(self.graph)?;
+            for layer in &stats {
+                info!(
+                    "layer {}: functional check passed, max error: {:e}",
+                    layer.layer, layer.max_error
+                );
+            }
+            gcn_statistics.functional = Some(stats);
+        }
         Ok(gcn_statistics)
     }
 
@@ -454,93 +600,623 @@ impl<'a> System<'a> {
     }
     fn print_stats(&self) {
         println!("Total cycles: {}", self.total_cycle);
+        let memory = self.mem_interface.get_statistics();
+        println!(
+            "Memory read bytes: {}, write bytes: {}, merged requests: {}, average read latency: {:.2}",
+            memory.total.read_bytes,
+            memory.total.write_bytes,
+            memory.total.merged_requests,
+            memory.total.average_read_latency
+        );
+        match memory.bandwidth_utilization {
+            Some(utilization) => println!(
+                "Memory bandwidth: {:.2} bytes/cycle, utilization: {:.2}%",
+                memory.average_bandwidth,
+                utilization * 100.
+            ),
+            None => println!(
+                "Memory bandwidth: {:.2} bytes/cycle",
+                memory.average_bandwidth
+            ),
+        }
+        if self.fast_forward {
+            println!("Skipped cycles: {}", self.skipped_cycles);
+        }
+        let stats = &self.component_stats;
+        for (name, stat) in [
+            ("aggregator", &stats.aggregator),
+            ("mlp", &stats.mlp),
+            ("sparsifier", &stats.sparsifier),
+            ("input_buffer", &stats.input_buffer),
+            ("agg_buffer", &stats.agg_buffer),
+            ("sparsify_buffer", &stats.sparsify_buffer),
+            ("output_buffer", &stats.output_buffer),
+            ("weight_buffer", &stats.weight_buffer),
+            ("mem_interface", &stats.mem_interface),
+        ] {
+            println!(
+                "{} utilization: {:.2}%, stall cycles: {}",
+                name,
+                stat.get_utilization() * 100.,
+                stat.stall_cycles
+            );
+        }
+        for layer in &self.layer_stats {
+            println!(
+                "layer {}: cycles: {}, aggregation cycles: {}, mlp cycles: {}, bytes read: {}, bytes written: {}, weight bytes read: {}, weight fetches: {}",
+                layer.layer,
+                layer.get_cycles(),
+                layer.aggregation_cycles,
+                layer.mlp_cycles,
+                layer.bytes_read,
+                layer.bytes_written,
+                layer.weight_bytes_read,
+                layer.weight_fetches
+            );
+        }
+    }
+
+    /// # Description
+    /// - start a span in the tracer, do nothing if the tracer is disabled
+    fn trace_begin(&mut self, track: TraceTrack, window: &WindowId) {
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.begin(track, window, self.total_cycle);
+        }
+    }
+
+    /// # Description
+    /// - finish a span in the tracer, do nothing if the tracer is disabled
+    fn trace_end(&mut self, track: TraceTrack, window: &WindowId) {
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.end(track, window, self.total_cycle);
+        }
     }
 
-    fn handle_input_buffer_to_mem(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    /// # Description
+    /// - the writeback is finished when all its addrs are sent to memory
+    /// - the writes are issued in order, so the layer ends with its last issued write
+    fn handle_issued_writes(&mut self) {
+        while let Some(id) = self.mem_interface.pop_issued_write() {
+            self.layer_stats[id.layer_id].end_cycle = self.total_cycle;
+            self.trace_end(TraceTrack::Writeback, &id);
+        }
+    }
+
+    /// # Description
+    /// - build the error of the deadlock, and write the snapshot if `snapshot_path` is set
+    /// - a failed snapshot is only warned, the deadlock is the error to report
+    fn report_deadlock(&self) -> GcnAggError {
+        let waits = self.get_wait_for_graph();
+        let snapshot = self.deadlock_detector.get_snapshot_path().and_then(|path| {
+            match self.write_snapshot(path, &waits) {
+                Ok(()) => Some(path.to_string()),
+                Err(e) => {
+                    warn!("cannot write the deadlock snapshot: {}", e);
+                    None
+                }
+            }
+        });
+        GcnAggError::Deadlock {
+            cycle: self.total_cycle,
+            stalled_cycles: self.deadlock_detector.get_stalled_cycles(),
+            waits,
+            snapshot,
+        }
+    }
+
+    /// # Description
+    /// - the wait-for graph of the components, built from the buffer states
+    /// - each buffer waits for the component that will move its window to the next stage
+    fn get_wait_for_graph(&self) -> Vec<WaitFor> {
+        use ComponentName::*;
+        let mut waits = vec![];
+        let mem_available = self.mem_interface.available();
+
+        // the next window waits for a free slot of the input buffer
+        if let Some(window) = &self.current_window {
+            let id = Some(window.get_task_id().clone());
+            match self.state {
+                // the next layer starts after the last window of this layer is written back
+                SystemState::ChangedLayer => {
+                    waits.push(WaitFor::new(WindowIterator, OutputBuffer, id))
+                }
+                SystemState::Working
+                    if !matches!(
+                        self.input_buffer.get_next_state(),
+                        input_buffer::BufferStatus::Empty
+                    ) =>
+                {
+                    waits.push(WaitFor::new(WindowIterator, InputBuffer, id))
+                }
+                _ => {}
+            }
+        }
+
+        for (state, window) in [
+            (
+                self.input_buffer.get_current_state(),
+                self.input_buffer.get_current_window(),
+            ),
+            (
+                self.input_buffer.get_next_state(),
+                self.input_buffer.get_next_window(),
+            ),
+        ] {
+            let id = window.map(|x| x.get_task_id().clone());
+            match state {
+                input_buffer::BufferStatus::WaitingToLoad if !mem_available => {
+                    waits.push(WaitFor::new(InputBuffer, MemInterface, id))
+                }
+                input_buffer::BufferStatus::Loading => {
+                    waits.push(WaitFor::new(InputBuffer, MemInterface, id))
+                }
+                input_buffer::BufferStatus::Reading => {
+                    waits.push(WaitFor::new(InputBuffer, Aggregator, id))
+                }
+                input_buffer::BufferStatus::Ready
+                    if self.aggregator.get_state() != &aggregator::AggregatorState::Idle =>
+                {
+                    waits.push(WaitFor::new(InputBuffer, Aggregator, id))
+                }
+                // the agg buffer is not ready for a new output window
+                input_buffer::BufferStatus::Ready => {
+                    waits.push(WaitFor::new(InputBuffer, AggBuffer, id))
+                }
+                _ => {}
+            }
+        }
+
+        match self.agg_buffer.get_current_state() {
+            agg_buffer::BufferStatus::Writing
+                if self.aggregator.get_state() == &aggregator::AggregatorState::Idle =>
+            {
+                let id = self.agg_buffer.get_current_window().get_task_id().clone();
+                waits.push(WaitFor::new(AggBuffer, InputBuffer, Some(id)));
+            }
+            agg_buffer::BufferStatus::WaitingToMlp => {
+                let id = self.agg_buffer.get_current_window().get_task_id().clone();
+                waits.push(WaitFor::new(AggBuffer, Mlp, Some(id)));
+            }
+            _ => {}
+        }
+        match self.agg_buffer.get_next_state() {
+            agg_buffer::BufferStatus::WaitingToMlp
+                if self.mlp.get_state() == &mlp::MlpState::Idle
+                    && !self
+                        .weight_buffer
+                        .is_ready(self.agg_buffer.get_next_window().get_task_id().layer_id) =>
+            {
+                let id = self.agg_buffer.get_next_window().get_task_id().clone();
+                waits.push(WaitFor::new(AggBuffer, WeightBuffer, Some(id)));
+            }
+            agg_buffer::BufferStatus::WaitingToMlp
+                if self.mlp.get_state() == &mlp::MlpState::Idle =>
+            {
+                let id = self.agg_buffer.get_next_window().get_task_id().clone();
+                waits.push(WaitFor::new(AggBuffer, SparsifyBuffer, Some(id)));
+            }
+            agg_buffer::BufferStatus::WaitingToMlp | agg_buffer::BufferStatus::Mlp => {
+                let id = self.agg_buffer.get_next_window().get_task_id().clone();
+                waits.push(WaitFor::new(AggBuffer, Mlp, Some(id)));
+            }
+            _ => {}
+        }
+
+        if self.mlp_waits_for_weights() {
+            let id = self.agg_buffer.get_next_window().get_task_id().clone();
+            waits.push(WaitFor::new(Mlp, WeightBuffer, Some(id)));
+        }
+        if matches!(
+            self.weight_buffer.get_state(),
+            weight_buffer::BufferStatus::WaitingToLoad | weight_buffer::BufferStatus::Loading
+        ) {
+            waits.push(WaitFor::new(WeightBuffer, MemInterface, None));
+        }
+
+        let sparsify_buffer = &self.sparsify_buffer;
+        for (state, window) in [
+            (
+                &sparsify_buffer.current_state,
+                &sparsify_buffer.current_window,
+            ),
+            (&sparsify_buffer.next_state, &sparsify_buffer.next_window),
+        ] {
+            let id = window.as_ref().map(|x| x.get_task_id().clone());
+            match state {
+                sparsify_buffer::BufferStatus::Writing => {
+                    waits.push(WaitFor::new(SparsifyBuffer, Mlp, id))
+                }
+                sparsify_buffer::BufferStatus::WaitingToSparsify
+                    if self.sparsifier.state == sparsifier::SparsifierState::Idle =>
+                {
+                    waits.push(WaitFor::new(SparsifyBuffer, OutputBuffer, id))
+                }
+                sparsify_buffer::BufferStatus::WaitingToSparsify
+                | sparsify_buffer::BufferStatus::Sparsifying => {
+                    waits.push(WaitFor::new(SparsifyBuffer, Sparsifier, id))
+                }
+                _ => {}
+            }
+        }
+
+        let output_buffer = &self.output_buffer;
+        for (state, window) in [
+            (&output_buffer.current_state, &output_buffer.current_window),
+            (&output_buffer.next_state, &output_buffer.next_window),
+        ] {
+            let id = window.as_ref().map(|x| x.get_task_id().clone());
+            match state {
+                output_buffer::BufferStatus::Writing => {
+                    waits.push(WaitFor::new(OutputBuffer, Sparsifier, id))
+                }
+                output_buffer::BufferStatus::WaitingToWriteBack => {
+                    waits.push(WaitFor::new(OutputBuffer, MemInterface, id))
+                }
+                _ => {}
+            }
+        }
+
+        if self.mem_interface.get_status() != ComponentStatus::Idle {
+            waits.push(WaitFor::new(MemInterface, Memory, None));
+        }
+        waits
+    }
+
+    /// # Description
+    /// - write the states of all components and the wait-for graph to `path`
+    fn write_snapshot(&self, path: &str, waits: &[WaitFor]) -> Result<(), GcnAggError> {
+        let mut snapshot = format!(
+            "deadlock at cycle {}, no progress for {} cycles\nsystem state: {:?}, layer: {}\n",
+            self.total_cycle,
+            self.deadlock_detector.get_stalled_cycles(),
+            self.state,
+            self.current_layer
+        );
+        snapshot += &format!(
+            "next window: {:?}\n\n# wait-for graph\n",
+            self.current_window.as_ref().map(|x| x.get_task_id())
+        );
+        for wait in waits {
+            snapshot += &format!("{}\n", wait);
+        }
+        snapshot += "\n# components\n";
+        snapshot += &format!("input_buffer: {:#?}\n", self.input_buffer);
+        snapshot += &format!("aggregator: {:#?}\n", self.aggregator);
+        snapshot += &format!("agg_buffer: {:#?}\n", self.agg_buffer);
+        snapshot += &format!("mlp: {:#?}\n", self.mlp);
+        snapshot += &format!("sparsify_buffer: {:#?}\n", self.sparsify_buffer);
+        snapshot += &format!("sparsifier: {:#?}\n", self.sparsifier);
+        snapshot += &format!("output_buffer: {:#?}\n", self.output_buffer);
+        snapshot += &format!("weight_buffer: {:#?}\n", self.weight_buffer);
+        snapshot += &format!("mem_interface: {:#?}\n", self.mem_interface);
+        std::fs::write(path, snapshot).map_err(|e| GcnAggError::io(path, e))
+    }
+
+    /// # Description
+    /// - the addresses of the features and the weights in the memory
+    pub fn get_memory_layout(&self) -> &MemoryLayout {
+        &self.memory_layout
+    }
+
+    /// # Description
+    /// - the statistics of each gcn layer, complete after `run`
+    pub fn get_layer_stats(&self) -> &[LayerStatistics] {
+        &self.layer_stats
+    }
+
+    /// # Description
+    /// - the number of cycles skipped by the fast forward
+    pub fn get_skipped_cycles(&self) -> u64 {
+        self.skipped_cycles
+    }
+
+    /// # Description
+    /// - the system can change without any handler: the aggregator, mlp or sparsifier is counting down,
+    ///   or the memory interface has requests to send or returned a read
+    /// - only waiting for the memory is a stall, a read is lost if nothing returns within the threshold
+    fn is_counting_down(&self) -> bool {
+        self.is_computing() || self.mem_interface.has_pending_requests()
+    }
+
+    /// # Description
+    /// - the aggregator, mlp or sparsifier is counting down
+    fn is_computing(&self) -> bool {
+        self.aggregator.get_state() == &aggregator::AggregatorState::Working
+            || self.mlp.get_state() == &mlp::MlpState::Working
+            || self.sparsifier.state == sparsifier::SparsifierState::Working
+    }
+
+    /// # Description
+    /// - called after an idle cycle(no handler triggered), the states of all buffers are stable now.
+    /// - nothing will happen until the aggregator, mlp or sparsifier finishes its countdown, or the memory returns a request
+    /// - so jump to the earliest of them directly, the result is the same as running cycle by cycle
+    /// - the skipped cycles are stalls if only the memory is waited for, the jump stops before the deadlock
+    ///   is reported, so it is reported at the same cycle as running cycle by cycle
+    fn skip_quiet_cycles(&mut self) {
+        // the mlp waiting for the weights is woken up by the memory
+        let mlp = match self.mlp_waits_for_weights() {
+            true => u64::MAX,
+            false => self.mlp.get_quiet_cycles(),
+        };
+        let cycles = [
+            self.aggregator.get_quiet_cycles(),
+            mlp,
+            self.sparsifier.get_quiet_cycles(),
+            self.mem_interface.get_quiet_cycles(),
+        ]
+        .into_iter()
+        .min()
+        .unwrap_or(0);
+        // u64::MAX means nothing will ever change, leave it to the deadlock detection
+        if cycles == u64::MAX {
+            return;
+        }
+        let computing = self.is_computing();
+        let cycles = match computing {
+            true => cycles,
+            false => cycles.min(self.deadlock_detector.get_cycles_to_deadlock() - 1),
+        };
+        if cycles == 0 {
+            return;
+        }
+        debug!(
+            "fast forward {} cycles from cycle {}",
+            cycles, self.total_cycle
+        );
+        self.aggregator.fast_forward(cycles);
+        self.mlp.fast_forward(cycles);
+        self.sparsifier.fast_forward(cycles);
+        self.mem_interface.fast_forward(cycles);
+        match computing {
+            true => self.deadlock_detector.progress(),
+            false => self.deadlock_detector.stall(cycles),
+        }
+        // the states are not changed during the skipped cycles
+        self.record_component_stats(cycles);
+        self.total_cycle += cycles;
+        self.skipped_cycles += cycles;
+    }
+
+    /// # Description
+    /// - classify each component by the states at the end of the cycle, and record it for `cycles` cycles
+    fn record_component_stats(&mut self, cycles: u64) {
+        let aggregator = self.get_aggregator_status();
+        let mlp = self.get_mlp_status();
+        let sparsifier = self.get_sparsifier_status();
+        let input_buffer = self.get_input_buffer_status();
+        let agg_buffer = self.get_agg_buffer_status();
+        let sparsify_buffer = self.get_sparsify_buffer_status();
+        let output_buffer = self.get_output_buffer_status();
+        let weight_buffer = self.get_weight_buffer_status();
+        let mem_interface = self.mem_interface.get_status();
+
+        let stats = &mut self.component_stats;
+        stats.aggregator.record(aggregator, cycles);
+        stats.mlp.record(mlp, cycles);
+        stats.sparsifier.record(sparsifier, cycles);
+        stats.input_buffer.record(input_buffer, cycles);
+        stats.agg_buffer.record(agg_buffer, cycles);
+        stats.sparsify_buffer.record(sparsify_buffer, cycles);
+        stats.output_buffer.record(output_buffer, cycles);
+        stats.weight_buffer.record(weight_buffer, cycles);
+        stats.mem_interface.record(mem_interface, cycles);
+
+        if aggregator == ComponentStatus::Busy {
+            if let Some(window) = self.input_buffer.get_current_window() {
+                self.layer_stats[window.get_task_id().layer_id].aggregation_cycles += cycles;
+            }
+        }
+        if mlp == ComponentStatus::Busy {
+            let layer = self.agg_buffer.get_next_window().get_task_id().layer_id;
+            self.layer_stats[layer].mlp_cycles += cycles;
+        }
+    }
+
+    /// is there any work left for the aggregator, including the windows not yet added
+    fn aggregator_has_work(&self) -> bool {
+        self.aggregator.get_state() != &aggregator::AggregatorState::Idle
+            || !matches!(
+                self.input_buffer.get_current_state(),
+                input_buffer::BufferStatus::Empty
+            )
+            || !matches!(
+                self.input_buffer.get_next_state(),
+                input_buffer::BufferStatus::Empty
+            )
+            || self.current_window.is_some()
+    }
+
+    /// is there any work left for the mlp, including the work of the aggregator
+    fn mlp_has_work(&self) -> bool {
+        self.mlp.get_state() != &mlp::MlpState::Idle
+            || self.agg_buffer.get_current_state() != &agg_buffer::BufferStatus::Empty
+            || self.agg_buffer.get_next_state() != &agg_buffer::BufferStatus::Empty
+            || self.aggregator_has_work()
+    }
+
+    /// is there any work left for the sparsifier, including the work of the mlp
+    fn sparsifier_has_work(&self) -> bool {
+        self.sparsifier.state != sparsifier::SparsifierState::Idle
+            || self.sparsify_buffer.current_state != sparsify_buffer::BufferStatus::Empty
+            || self.sparsify_buffer.next_state != sparsify_buffer::BufferStatus::Empty
+            || self.mlp_has_work()
+    }
+
+    /// - the stall cause when a component has nothing to do
+    fn starved_or_idle(has_work: bool) -> ComponentStatus {
+        match has_work {
+            true => ComponentStatus::Stalled(StallCause::Upstream),
+            false => ComponentStatus::Idle,
+        }
+    }
+
+    fn get_aggregator_status(&self) -> ComponentStatus {
+        use input_buffer::BufferStatus::*;
+        if self.aggregator.get_state() != &aggregator::AggregatorState::Idle {
+            return ComponentStatus::Busy;
+        }
+        match (
+            self.input_buffer.get_current_state(),
+            self.input_buffer.get_next_state(),
+        ) {
+            (Reading, _) => ComponentStatus::Busy,
+            // the agg buffer is waiting for the mlp
+            (Ready, _) => ComponentStatus::Stalled(StallCause::DownstreamFull),
+            (WaitingToLoad | Loading, _) | (Empty, WaitingToLoad | Loading) => {
+                ComponentStatus::Stalled(StallCause::Memory)
+            }
+            _ => Self::starved_or_idle(self.aggregator_has_work()),
+        }
+    }
+
+    fn get_mlp_status(&self) -> ComponentStatus {
+        if self.mlp_waits_for_weights() {
+            return ComponentStatus::Stalled(StallCause::Memory);
+        }
+        if self.mlp.get_state() != &mlp::MlpState::Idle {
+            return ComponentStatus::Busy;
+        }
+        match self.agg_buffer.get_next_state() {
+            agg_buffer::BufferStatus::Mlp => ComponentStatus::Busy,
+            agg_buffer::BufferStatus::WaitingToMlp
+                if !self
+                    .weight_buffer
+                    .is_ready(self.agg_buffer.get_next_window().get_task_id().layer_id) =>
+            {
+                ComponentStatus::Stalled(StallCause::Memory)
+            }
+            // the sparsify buffer is waiting for the sparsifier
+            agg_buffer::BufferStatus::WaitingToMlp => {
+                ComponentStatus::Stalled(StallCause::DownstreamFull)
+            }
+            _ => Self::starved_or_idle(self.mlp_has_work()),
+        }
+    }
+
+    fn get_sparsifier_status(&self) -> ComponentStatus {
+        if self.sparsifier.state != sparsifier::SparsifierState::Idle {
+            return ComponentStatus::Busy;
+        }
+        match self.sparsify_buffer.next_state {
+            sparsify_buffer::BufferStatus::Sparsifying => ComponentStatus::Busy,
+            // the output buffer is waiting for the writeback
+            sparsify_buffer::BufferStatus::WaitingToSparsify => {
+                ComponentStatus::Stalled(StallCause::DownstreamFull)
+            }
+            _ => Self::starved_or_idle(self.sparsifier_has_work()),
+        }
+    }
+
+    fn get_input_buffer_status(&self) -> ComponentStatus {
+        use input_buffer::BufferStatus::*;
+        let states = [
+            self.input_buffer.get_current_state(),
+            self.input_buffer.get_next_state(),
+        ];
+        if states.iter().any(|s| matches!(s, Reading)) {
+            ComponentStatus::Busy
+        } else if states.iter().any(|s| matches!(s, WaitingToLoad | Loading)) {
+            ComponentStatus::Stalled(StallCause::Memory)
+        } else if states.iter().any(|s| matches!(s, Ready)) {
+            ComponentStatus::Stalled(StallCause::DownstreamFull)
+        } else {
+            Self::starved_or_idle(self.current_window.is_some())
+        }
+    }
+
+    fn get_agg_buffer_status(&self) -> ComponentStatus {
+        use agg_buffer::BufferStatus::*;
+        match (
+            self.agg_buffer.get_current_state(),
+            self.agg_buffer.get_next_state(),
+        ) {
+            (_, Mlp) => ComponentStatus::Busy,
+            (Writing, _) if self.aggregator.get_state() != &aggregator::AggregatorState::Idle => {
+                ComponentStatus::Busy
+            }
+            (WaitingToMlp, _) | (_, WaitingToMlp) => {
+                ComponentStatus::Stalled(StallCause::DownstreamFull)
+            }
+            _ => Self::starved_or_idle(self.aggregator_has_work()),
+        }
+    }
+
+    fn get_sparsify_buffer_status(&self) -> ComponentStatus {
+        use sparsify_buffer::BufferStatus::*;
+        match (
+            &self.sparsify_buffer.current_state,
+            &self.sparsify_buffer.next_state,
+        ) {
+            (Writing, _) | (_, Sparsifying) => ComponentStatus::Busy,
+            (WaitingToSparsify, _) | (_, WaitingToSparsify) => {
+                ComponentStatus::Stalled(StallCause::DownstreamFull)
+            }
+            _ => Self::starved_or_idle(self.mlp_has_work()),
+        }
+    }
+
+    /// the mlp has finished its countdown, but the last tiles of the weights are not loaded yet
+    fn mlp_waits_for_weights(&self) -> bool {
+        self.mlp.get_state() == &mlp::MlpState::Finished && !self.weight_buffer.all_loaded()
+    }
+
+    fn get_weight_buffer_status(&self) -> ComponentStatus {
+        use weight_buffer::BufferStatus::*;
+        match self.weight_buffer.get_state() {
+            WaitingToLoad | Loading => ComponentStatus::Stalled(StallCause::Memory),
+            // the mlp is reading the weights
+            Ready if self.mlp.get_state() != &mlp::MlpState::Idle => ComponentStatus::Busy,
+            _ => Self::starved_or_idle(self.mlp_has_work()),
+        }
+    }
+
+    fn get_output_buffer_status(&self) -> ComponentStatus {
+        use output_buffer::BufferStatus::*;
+        match (
+            &self.output_buffer.current_state,
+            &self.output_buffer.next_state,
+        ) {
+            (Writing, _) => ComponentStatus::Busy,
+            // waiting for the mem interface to accept the writeback
+            (WaitingToWriteBack, _) | (_, WaitingToWriteBack) => {
+                ComponentStatus::Stalled(StallCause::Memory)
+            }
+            _ => Self::starved_or_idle(self.sparsifier_has_work()),
+        }
+    }
+
+    fn handle_input_buffer_to_mem(&mut self) -> Result<bool, GcnAggError> {
         // add task to current input_buffer or send request to memory
         if let input_buffer::BufferStatus::WaitingToLoad = self.input_buffer.get_current_state() {
             if self.mem_interface.available() {
                 // generate addr from the req and window
-
-                let mut addr_vec = vec![];
                 let window = self
                     .input_buffer
                     .get_current_window()
-                    .expect("no window in input buffer");
-                let window_layer = window.get_task_id().layer_id;
-                match self.running_mode {
-                    RunningMode::Sparse => {
-                        let start_addrs = &self
-                            .node_features
-                            .get(window_layer)
-                            .expect("no such layer in nodefeatures")
-                            .start_addrs;
-                        let mut start_addr = start_addrs[window.start_input_index];
-                        let end_addr = start_addrs[window.end_input_index];
-                        // round start_addr to the nearest 64
-                        start_addr = start_addr / 64 * 64;
-                        while start_addr < end_addr {
-                            addr_vec.push(start_addr);
-                            start_addr += 64;
-                        }
-                        self.mem_interface
-                            .send(window.get_task_id().clone(), addr_vec, false);
-                        self.input_buffer.send_req(true);
-                        return Ok(true);
-                    }
-                    RunningMode::Dense => {
-                        // dense
-                        let base_addr: u64 = (window_layer * 0x10000000) as u64;
-                        let mut start_addr = base_addr
-                            + window.start_input_index as u64
-                                * window.get_output_window().get_input_dim() as u64
-                                * 4;
-                        let end_addr = base_addr
-                            + window.end_input_index as u64
-                                * window.get_output_window().get_input_dim() as u64
-                                * 4;
-                        while start_addr < end_addr {
-                            addr_vec.push(start_addr);
-                            start_addr += 64;
-                        }
-                        self.mem_interface
-                            .send(window.get_task_id().clone(), addr_vec, false);
-                        self.input_buffer.send_req(true);
-                        return Ok(true);
-                    }
-                    RunningMode::Mixed => {
-                        todo!()
-                    }
-                }
+                    .ok_or_else(|| GcnAggError::InvalidState("no window in input buffer".into()))?;
+                let addr_vec = self.input_requests(window)?;
+                let id = window.get_task_id().clone();
+                self.layer_stats[id.layer_id].bytes_read += addr_vec.len() as u64 * REQUEST_BYTES;
+                self.mem_interface.send(id.clone(), addr_vec, false);
+                self.input_buffer.send_req(true);
+                self.trace_begin(TraceTrack::MemoryLoad, &id);
+                return Ok(true);
             }
         }
         // add task to next input_buffer or send request to memory
         if let input_buffer::BufferStatus::WaitingToLoad = self.input_buffer.get_next_state() {
             if self.mem_interface.available() {
                 // generate addr from the req and window
-
-                let mut addr_vec = vec![];
                 let window = self
                     .input_buffer
                     .get_next_window()
-                    .expect("no window in input buffer");
-                let window_layer = window.get_task_id().layer_id;
-                let start_addrs = &self
-                    .node_features
-                    .get(window_layer)
-                    .expect("no such layer in nodefeatures")
-                    .start_addrs;
-                let mut start_addr = start_addrs[window.start_input_index];
-                let end_addr = start_addrs[window.end_input_index];
-                // round start_addr to the nearest 64
-                start_addr = start_addr / 64 * 64;
-                while start_addr < end_addr {
-                    addr_vec.push(start_addr);
-                    start_addr += 64;
-                }
-                self.mem_interface
-                    .send(window.get_task_id().clone(), addr_vec, false);
+                    .ok_or_else(|| GcnAggError::InvalidState("no window in input buffer".into()))?;
+                let addr_vec = self.input_requests(window)?;
+                let id = window.get_task_id().clone();
+                self.layer_stats[id.layer_id].bytes_read += addr_vec.len() as u64 * REQUEST_BYTES;
+                self.mem_interface.send(id.clone(), addr_vec, false);
                 self.input_buffer.send_req(false);
+                self.trace_begin(TraceTrack::MemoryLoad, &id);
                 return Ok(true);
             }
         }
@@ -548,42 +1224,132 @@ impl<'a> System<'a> {
         Ok(false)
     }
 
-    fn handle_input_buffer_add_task(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    /// # Description
+    /// - the requests to load the input nodes of `window` in its dataflow, the same for both slots
+    fn input_requests(&self, window: &InputWindow) -> Result<Vec<u64>, GcnAggError> {
+        get_address_generator(window.get_output_window().get_running_mode())?.read_requests(
+            &self.memory_layout,
+            window.get_task_id().layer_id,
+            window.start_input_index,
+            window.end_input_index,
+        )
+    }
+
+    /// # Description
+    /// - the requests to write the output nodes of `window` in the dataflows that the next layer reads them in
+    fn writeback_requests(&self, window: &OutputWindow) -> Result<Vec<u64>, GcnAggError> {
+        let layer_id = window.get_task_id().layer_id;
+        let (start, end) = (window.start_output_index, window.end_output_index);
+        match self.next_layer_read_modes.get(layer_id) {
+            Some(read_modes) => {
+                read_modes.write_requests(&self.memory_layout, layer_id, start, end)
+            }
+            None => get_address_generator(&self.writeback_mode(window))?.write_requests(
+                &self.memory_layout,
+                layer_id,
+                start,
+                end,
+            ),
+        }
+    }
+
+    /// # Description
+    /// - the dataflow that the next layer reads the output nodes of `window` in, except the `Window` granularity
+    /// - in `Mixed` mode it's the dataflow of the whole next layer, the same rule as its windows
+    fn writeback_mode(&self, window: &OutputWindow) -> RunningMode {
+        match self.running_mode {
+            RunningMode::Mixed => get_layer_running_mode(
+                &self.node_features[window.get_task_id().layer_id + 1],
+                window.get_output_dim(),
+                &self.mixed_settings,
+            ),
+            ref mode => mode.clone(),
+        }
+    }
+
+    fn handle_input_buffer_add_task(&mut self) -> Result<bool, GcnAggError> {
         // add task to current input_buffer or send request to memory
         if let input_buffer::BufferStatus::Empty = self.input_buffer.get_current_state() {
             // add a task to the input buffer
             // self.input_buffer.send_req(self.current_input_iter.as_ref().unwrap());
-            let window = self.current_window.take().unwrap();
+            let window = self
+                .current_window
+                .take()
+                .ok_or_else(|| GcnAggError::InvalidState("no window to add".into()))?;
             debug!("add task to inputbuffer's current window:{:?}", &window);
+            self.record_new_input_window(&window);
 
             self.input_buffer.add_task_to_current(window);
-            self.move_to_next_window();
+            self.move_to_next_window()?;
             return Ok(true);
         }
 
         if let input_buffer::BufferStatus::Empty = self.input_buffer.get_next_state() {
             // add a task to the input buffer
             // self.input_buffer.send_req(self.current_input_iter.as_ref().unwrap());
-            let window = self.current_window.take().unwrap();
+            let window = self
+                .current_window
+                .take()
+                .ok_or_else(|| GcnAggError::InvalidState("no window to add".into()))?;
             debug!("add task to inputbuffer's next window:{:?}", &window);
+            self.record_new_input_window(&window);
             self.input_buffer.add_task_to_next(window);
-            self.move_to_next_window();
+            self.move_to_next_window()?;
             return Ok(true);
         }
 
         Ok(false)
     }
 
-    fn handle_mem_to_input_buffer(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
-        // test if there are memory request return
+    fn record_new_input_window(&mut self, window: &InputWindow) {
+        let layer = &mut self.layer_stats[window.get_task_id().layer_id];
+        if layer.num_input_windows == 0 {
+            layer.start_cycle = self.total_cycle;
+        }
+        layer.num_input_windows += 1;
+    }
+
+    fn handle_mem_to_input_buffer(&mut self) -> Result<bool, GcnAggError> {
+        // test if there are memory request return, the weight tiles go to the weight buffer
         if let Some(ret_req) = self.mem_interface.receive_pop() {
-            self.input_buffer.receive(&ret_req);
+            if ret_req.is_weights() {
+                self.weight_buffer.receive()?;
+                return Ok(true);
+            }
+            self.input_buffer.receive(&ret_req)?;
+            self.trace_end(TraceTrack::MemoryLoad, &ret_req);
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn handle_start_aggregator(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    /// # Description
+    /// - fetch the weights of the window waiting for the mlp if they are not in the weight buffer,
+    ///   then send the tiles to the memory one by one
+    fn handle_weight_buffer_to_mem(&mut self) -> Result<bool, GcnAggError> {
+        if let (agg_buffer::BufferStatus::WaitingToMlp, mlp::MlpState::Idle) =
+            (self.agg_buffer.get_next_state(), self.mlp.get_state())
+        {
+            let layer = self.agg_buffer.get_next_window().get_task_id().layer_id;
+            if !self.weight_buffer.holds(layer) {
+                let requests = self.memory_layout.weight_requests(layer);
+                self.weight_buffer.start_load(layer, requests)?;
+                self.layer_stats[layer].weight_fetches += 1;
+                return Ok(true);
+            }
+        }
+        if self.mem_interface.available() {
+            if let Some((id, addr_vec)) = self.weight_buffer.send_next_tile() {
+                self.layer_stats[id.layer_id].weight_bytes_read +=
+                    addr_vec.len() as u64 * REQUEST_BYTES;
+                self.mem_interface.send(id, addr_vec, false);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn handle_start_aggregator(&mut self) -> Result<bool, GcnAggError> {
         // test if the aggregator is ready to start
         if let (
             input_buffer::BufferStatus::Ready,
@@ -599,24 +1365,33 @@ impl<'a> System<'a> {
                 "start the aggregator,agg window: {:?}",
                 self.input_buffer.get_current_window()
             );
-            let current_window = self.input_buffer.get_current_window().unwrap();
+            let current_window = self
+                .input_buffer
+                .get_current_window()
+                .ok_or_else(|| GcnAggError::InvalidState("no window to aggregate".into()))?;
             let window_layer = current_window.get_task_id().layer_id;
+            let id = current_window.get_task_id().clone();
+
+            if let Some(functional) = self.functional.as_mut() {
+                functional.aggregate(current_window);
+            }
 
             // start the aggregator
             self.agg_buffer
                 .add_task(current_window.get_output_window().clone());
             self.aggregator.add_task(
                 current_window,
-                self.node_features.get(window_layer).unwrap(),
+                &self.node_features[window_layer],
                 self.agg_buffer.get_current_temp_result_mut(),
             );
             self.input_buffer.start_aggragating();
+            self.trace_begin(TraceTrack::Aggregator, &id);
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn handle_finish_aggregator(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    fn handle_finish_aggregator(&mut self) -> Result<bool, GcnAggError> {
         // test if the aggregator is finished
         if self.aggregator.get_state() == &aggregator::AggregatorState::Finished {
             // 1. make the aggregator idle
@@ -624,20 +1399,25 @@ impl<'a> System<'a> {
             // 2. set the input buffer to empty
             self.input_buffer.finished_aggregation();
             // 3. set the aggregator buffer to finished or writing
-            let window = self.input_buffer.get_current_window().unwrap();
+            let window = self
+                .input_buffer
+                .get_current_window()
+                .ok_or_else(|| GcnAggError::InvalidState("no window is aggregated".into()))?;
             debug!("finished aggregation, window: {:?}", &window);
+            let id = window.get_task_id().clone();
+            let is_last_row = window.is_last_row;
+            self.trace_end(TraceTrack::Aggregator, &id);
 
-            match window.is_last_row {
-                true => self.agg_buffer.finished_aggregation(),
-                false => {}
-            };
+            if is_last_row {
+                self.agg_buffer.finished_aggregation();
+            }
 
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn handle_start_mlp(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    fn handle_start_mlp(&mut self) -> Result<bool, GcnAggError> {
         // test if start the mlp
         //
         if let (
@@ -649,35 +1429,49 @@ impl<'a> System<'a> {
             self.mlp.get_state(),
             &self.sparsify_buffer.current_state,
         ) {
-            // start the mlp
+            // start the mlp when the first tile of the weights is on chip
             let current_window = self.agg_buffer.get_next_window();
+            if !self
+                .weight_buffer
+                .is_ready(current_window.get_task_id().layer_id)
+            {
+                return Ok(false);
+            }
             debug!("start the mlp, window: {:?}", &current_window);
+            if let Some(functional) = self.functional.as_mut() {
+                functional.mlp(current_window)?;
+            }
             self.mlp
                 .start_mlp(current_window, self.agg_buffer.get_next_temp_result());
-            self.sparsify_buffer.start_mlp(current_window.clone());
-            self.agg_buffer.start_mlp();
+            self.sparsify_buffer.start_mlp(current_window.clone())?;
+            let id = current_window.get_task_id().clone();
+            self.agg_buffer.start_mlp()?;
+            self.trace_begin(TraceTrack::Mlp, &id);
 
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn handle_finish_mlp(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
-        // test if the mlp is finished
-        if self.mlp.get_state() == &mlp::MlpState::Finished {
+    fn handle_finish_mlp(&mut self) -> Result<bool, GcnAggError> {
+        // test if the mlp is finished, the streamed weights should be all loaded
+        if self.mlp.get_state() == &mlp::MlpState::Finished && self.weight_buffer.all_loaded() {
             // 1. make the mlp idle
             self.mlp.finished_mlp();
+            self.weight_buffer.finished_mlp();
             // 2. set the output buffer to empty
-            self.sparsify_buffer.finished_mlp();
+            self.sparsify_buffer.finished_mlp()?;
             let window = self.agg_buffer.get_next_window();
             debug!("finished mlp, window: {:?}", &window);
-            self.agg_buffer.finished_mlp();
+            let id = window.get_task_id().clone();
+            self.agg_buffer.finished_mlp()?;
+            self.trace_end(TraceTrack::Mlp, &id);
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn handle_start_sparsify(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    fn handle_start_sparsify(&mut self) -> Result<bool, GcnAggError> {
         // test if start the sparsifier
         //
         if let (
@@ -691,34 +1485,46 @@ impl<'a> System<'a> {
         ) {
             // start the sparsifier
             // if it's the last layer, do some special thing
-            let current_window = self.sparsify_buffer.next_window.as_ref().unwrap();
+            let current_window =
+                self.sparsify_buffer.next_window.as_ref().ok_or_else(|| {
+                    GcnAggError::InvalidState("no window in sparsify buffer".into())
+                })?;
             debug!("start the sparsifier: {:?}", &current_window);
+            if let Some(functional) = self.functional.as_mut() {
+                functional.sparsify(current_window)?;
+            }
 
             let window_layer = current_window.get_task_id().layer_id;
+            let id = current_window.get_task_id().clone();
             if window_layer == self.gcn_layer_num - 1 {
                 // no need to sparsify
                 debug!("no need to sparsify, layer:{}", window_layer);
-                self.sparsifier.add_task_last_layer();
-                self.output_buffer.start_sparsify(current_window.clone());
-                self.sparsify_buffer.start_sparsify();
+                self.sparsifier.add_task_last_layer(
+                    current_window.end_output_index - current_window.start_output_index,
+                );
+                self.output_buffer.start_sparsify(current_window.clone())?;
+                self.sparsify_buffer.start_sparsify()?;
             } else {
-                let input_dim = current_window.get_input_dim();
-                let output_dim = current_window.get_output_dim();
                 let output_layer_id = current_window.get_task_id().layer_id + 1;
-                let output_feature = self.node_features.get(output_layer_id).unwrap();
+                let output_feature = &self.node_features[output_layer_id];
 
-                self.sparsifier
-                    .add_task(input_dim, output_dim, output_feature);
-                self.output_buffer.start_sparsify(current_window.clone());
+                self.sparsifier.add_task(
+                    current_window.get_output_dim(),
+                    current_window.start_output_index,
+                    current_window.end_output_index,
+                    output_feature,
+                );
+                self.output_buffer.start_sparsify(current_window.clone())?;
 
-                self.sparsify_buffer.start_sparsify();
+                self.sparsify_buffer.start_sparsify()?;
             }
+            self.trace_begin(TraceTrack::Sparsifier, &id);
             return Ok(true);
         }
         Ok(false)
     }
 
-    fn handle_finish_sparsify(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    fn handle_finish_sparsify(&mut self) -> Result<bool, GcnAggError> {
         if let (
             sparsifier::SparsifierState::Idle,
             sparsify_buffer::BufferStatus::Sparsifying,
@@ -728,20 +1534,25 @@ impl<'a> System<'a> {
             &self.sparsify_buffer.next_state,
             &self.output_buffer.current_state,
         ) {
-            let window = self.sparsify_buffer.next_window.as_ref().unwrap();
+            let window =
+                self.sparsify_buffer.next_window.as_ref().ok_or_else(|| {
+                    GcnAggError::InvalidState("no window in sparsify buffer".into())
+                })?;
             debug!("finished sparsify, window: {:?}", &window);
+            let id = window.get_task_id().clone();
+            self.trace_end(TraceTrack::Sparsifier, &id);
             // 1. make the sparsifier idle
             self.sparsifier.finished_sparsify();
             // 2. set the output buffer to empty
-            self.output_buffer.finished_sparsify();
-            self.sparsify_buffer.finished_sparsify();
+            self.output_buffer.finished_sparsify()?;
+            self.sparsify_buffer.finished_sparsify()?;
             return Ok(true);
         }
 
         Ok(false)
     }
 
-    fn handle_start_writeback(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+    fn handle_start_writeback(&mut self) -> Result<bool, GcnAggError> {
         // test if start the writeback
         if let (output_buffer::BufferStatus::WaitingToWriteBack, true) = (
             &self.output_buffer.next_state,
@@ -750,11 +1561,19 @@ impl<'a> System<'a> {
             // start the writeback
             // the write back traffic is compressed
             debug!("start writeback");
-            let current_window = self.output_buffer.next_window.as_ref().unwrap().clone();
+            let current_window = self
+                .output_buffer
+                .next_window
+                .as_ref()
+                .ok_or_else(|| GcnAggError::InvalidState("no window in output buffer".into()))?
+                .clone();
+            let layer_stat = &mut self.layer_stats[current_window.get_task_id().layer_id];
+            layer_stat.num_output_windows += 1;
             if current_window.final_layer {
                 // do nothing,
                 // the final layer is not written back
                 if current_window.final_window {
+                    layer_stat.end_cycle = self.total_cycle;
                     // do nothing, this is the class output, just return and set simulator to finished
                     debug!(
                         "finish the simulation, the last window is : {:?}",
@@ -763,34 +1582,30 @@ impl<'a> System<'a> {
                     self.state = SystemState::Finished;
                     self.finished = true;
                 }
-                self.output_buffer.start_write_back();
+                self.output_buffer.start_write_back()?;
                 return Ok(true);
             }
 
             // else, the write back traffic is decided be next layer's input.
             let layer_id = current_window.get_task_id().layer_id;
-            let node_feature = self.node_features.get(layer_id + 1).unwrap();
-            let mut addr_vec = vec![];
-
-            let start_addrs = &node_feature.start_addrs;
-            let mut start_addr = start_addrs[current_window.start_output_index];
-            let end_addr = start_addrs[current_window.end_output_index];
-            // round start_addr to the nearest 64
-            start_addr = start_addr / 64 * 64;
-            while start_addr < end_addr {
-                addr_vec.push(start_addr);
-                start_addr += 64;
-            }
+            let addr_vec = self.writeback_requests(&current_window)?;
+            self.layer_stats[layer_id].bytes_written += addr_vec.len() as u64 * REQUEST_BYTES;
             self.mem_interface
                 .send(current_window.get_task_id().clone(), addr_vec, true);
+            self.trace_begin(TraceTrack::Writeback, current_window.get_task_id());
 
             if current_window.final_window {
                 // do nothing, this is the class output, just return and set simulator to finished
                 debug!("finish current layer: {:?}", current_window);
-                assert_eq!(self.state, SystemState::ChangedLayer);
+                if self.state != SystemState::ChangedLayer {
+                    return Err(GcnAggError::InvalidState(format!(
+                        "the last window of layer {} is written back in state {:?}",
+                        layer_id, self.state
+                    )));
+                }
                 self.state = SystemState::Working;
             }
-            self.output_buffer.start_write_back();
+            self.output_buffer.start_write_back()?;
 
             return Ok(true);
         }
@@ -805,42 +1620,801 @@ mod test {
     use chrono::Local;
 
     use super::*;
-    use std::{fs::File, io::Write};
+    use crate::{
+        accelerator::{
+            memory_backend::{FixedLatencyMemory, IdealMemory, MemoryBackend},
+            plan_windows,
+        },
+        gcn_result::RegionKind,
+        graph::preprocess,
+        settings::{
+            EncodingSettings, FunctionalSettings, LayoutSettings, MemorySettings, MixedGranularity,
+            PreprocessSettings, SparseEncoding,
+        },
+    };
+    use std::{cell::RefCell, collections::BTreeSet, fs::File, io::Write, rc::Rc};
+    #[cfg(feature = "ramulator")]
     #[test]
     fn test_system() -> Result<(), Box<dyn std::error::Error>> {
+        run_test_system(
+            RunningMode::Sparse,
+            MemoryBackendType::Ramulator,
+            false,
+            None,
+        )?;
+        Ok(())
+    }
+    #[test]
+    fn test_system_mixed() -> Result<(), Box<dyn std::error::Error>> {
+        run_test_system(
+            RunningMode::Mixed,
+            MemoryBackendType::FixedLatency,
+            false,
+            None,
+        )?;
+        Ok(())
+    }
+    #[test]
+    fn test_system_input_requests() -> Result<(), Box<dyn std::error::Error>> {
+        let (graph, node_features) = build_test_inputs()?;
+        let acc_settings =
+            build_test_settings(RunningMode::Dense, MemoryBackendType::Ideal, false, None);
+        let stats_name = Local::now()
+            .format("output/%Y-%m-%d_%H-%M-%S%.6f-test.txt")
+            .to_string();
+        let mut system = System::new(&graph, &node_features, acc_settings.clone(), &stats_name)?;
+        // the ideal memory returns every read in the same cycle, so no read is merged
+        let requests = Rc::new(RefCell::new(vec![]));
+        system.mem_interface = MemInterface::new(
+            64,
+            64,
+            Box::new(RecordingMemory {
+                memory: Box::new(IdealMemory::new()),
+                requests: requests.clone(),
+            }),
+        );
+        system.run()?;
+        assert!(system.finished());
+
+        // the dense rows of the layers are 24 and 8 bytes, packed from the start of their regions
+        let regions = system.memory_layout.report().regions;
+        let mut expected = vec![];
+        let mut num_windows = 0;
+        for (layer, (features, row_bytes)) in node_features.iter().zip([24, 8]).enumerate() {
+            let base = regions
+                .iter()
+                .find(|x| x.layer == layer && x.kind == RegionKind::DenseFeatures)
+                .unwrap()
+                .start;
+            let window_iter_settings = WindowIterSettings {
+                agg_buffer_size: acc_settings.agg_buffer_size,
+                input_buffer_size: acc_settings.input_buffer_size,
+                layer,
+                gcn_hidden_size: acc_settings.gcn_hidden_size.clone(),
+                final_layer: layer == node_features.len() - 1,
+                running_mode: RunningMode::Dense,
+                mixed_settings: acc_settings.mixed_settings.clone(),
+            };
+            for window in
+                OutputWindowIterator::new(&graph, features, window_iter_settings)?.flatten()
+            {
+                let start = base + window.start_input_index as u64 * row_bytes;
+                let end = base + window.end_input_index as u64 * row_bytes;
+                // the interface sends the requests of a window from the last one
+                let mut window_requests = (start / 64 * 64..end).step_by(64).collect::<Vec<_>>();
+                window_requests.reverse();
+                expected.extend(window_requests);
+                num_windows += 1;
+            }
+        }
+        // the windows are loaded by both slots of the input buffer in turn
+        assert!(num_windows > 2);
+        let weights = |addr: u64| {
+            regions
+                .iter()
+                .any(|x| x.kind == RegionKind::Weights && (x.start..x.end).contains(&addr))
+        };
+        let reads = requests
+            .borrow()
+            .iter()
+            .filter(|&&(addr, is_write)| !is_write && !weights(addr))
+            .map(|&(addr, _)| addr)
+            .collect::<Vec<_>>();
+        assert_eq!(reads, expected);
+        Ok(())
+    }
+    #[test]
+    fn test_system_mixed_writeback() -> Result<(), Box<dyn std::error::Error>> {
+        let (graph, mut node_features) = build_test_inputs()?;
+        // the windows of layer 1 aggregate the density 6/10, 8/12 and 2/4
+        node_features[1] = NodeFeatures::from_dense(&[
+            vec![1., 1.],
+            vec![1., 1.],
+            vec![1., 1.],
+            vec![0., 0.],
+            vec![0., 0.],
+        ]);
+        // all dense, the first two windows dense and the last sparse, all sparse
+        for dense_threshold in [0.4, 0.6, 0.7] {
+            let mut acc_settings = build_test_settings(
+                RunningMode::Mixed,
+                MemoryBackendType::FixedLatency,
+                false,
+                None,
+            );
+            acc_settings.mixed_settings = MixedSettings {
+                dense_threshold,
+                granularity: MixedGranularity::Window,
+            };
+            let stats_name = Local::now()
+                .format("output/%Y-%m-%d_%H-%M-%S%.6f-test.txt")
+                .to_string();
+            let mut system = System::new(&graph, &node_features, acc_settings, &stats_name)?;
+            let requests = Rc::new(RefCell::new(vec![]));
+            system.mem_interface = MemInterface::new(
+                64,
+                64,
+                Box::new(RecordingMemory {
+                    memory: Box::new(FixedLatencyMemory::new(20, 1)),
+                    requests: requests.clone(),
+                }),
+            );
+            system.run()?;
+            assert!(system.finished());
+
+            // the features of layer 1 are read from the same addresses that layer 0 writes
+            let regions = system.memory_layout.report().regions;
+            let is_layer1 = |addr: u64, kind: RegionKind| {
+                regions
+                    .iter()
+                    .any(|x| x.layer == 1 && x.kind == kind && (x.start..x.end).contains(&addr))
+            };
+            let addrs = |is_write: bool, kind: RegionKind| {
+                requests
+                    .borrow()
+                    .iter()
+                    .filter(|&&(addr, x)| x == is_write && is_layer1(addr, kind))
+                    .map(|&(addr, _)| addr)
+                    .collect::<BTreeSet<_>>()
+            };
+            for kind in [RegionKind::Features, RegionKind::DenseFeatures] {
+                assert_eq!(addrs(false, kind), addrs(true, kind));
+            }
+            // with 0.6 the nodes 2..5 are read by the windows of both dataflows, so they are written in both
+            assert_eq!(
+                addrs(true, RegionKind::Features).is_empty(),
+                dense_threshold == 0.4
+            );
+            assert_eq!(
+                addrs(true, RegionKind::DenseFeatures).is_empty(),
+                dense_threshold == 0.7
+            );
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_system_fixed_latency() -> Result<(), Box<dyn std::error::Error>> {
+        run_test_system(
+            RunningMode::Sparse,
+            MemoryBackendType::FixedLatency,
+            false,
+            None,
+        )?;
+        Ok(())
+    }
+    #[test]
+    fn test_system_ideal() -> Result<(), Box<dyn std::error::Error>> {
+        run_test_system(RunningMode::Dense, MemoryBackendType::Ideal, false, None)?;
+        Ok(())
+    }
+    #[test]
+    fn test_system_fast_forward() -> Result<(), Box<dyn std::error::Error>> {
+        for (running_mode, backend) in [
+            (RunningMode::Sparse, MemoryBackendType::FixedLatency),
+            (RunningMode::Dense, MemoryBackendType::FixedLatency),
+            (RunningMode::Mixed, MemoryBackendType::Ideal),
+        ] {
+            let (stat, layers, skipped) =
+                run_test_system(running_mode.clone(), backend, false, None)?;
+            assert_eq!(skipped, 0);
+            let (fast_stat, fast_layers, skipped) =
+                run_test_system(running_mode, backend, true, None)?;
+            assert_eq!(stat.cycle, fast_stat.cycle);
+            assert_eq!(stat.components, fast_stat.components);
+            assert_eq!(layers, fast_layers);
+            assert_eq!(stat.memory, fast_stat.memory);
+            assert!(skipped > 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_trace() -> Result<(), Box<dyn std::error::Error>> {
+        let trace_path = std::env::temp_dir().join("gcn_agg_test_system_trace.json");
+        let trace_path = trace_path.to_string_lossy().to_string();
+        let trace_path = trace_path.as_str();
+        let (stat, layers, _) = run_test_system(
+            RunningMode::Sparse,
+            MemoryBackendType::FixedLatency,
+            true,
+            Some(trace_path),
+        )?;
+        assert_eq!(stat.trace_file.as_deref(), Some(trace_path));
+        let trace: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(trace_path)?)?;
+        let events = trace["traceEvents"].as_array().expect("no trace events");
+        for cat in [
+            "aggregator",
+            "mlp",
+            "sparsifier",
+            "memory_load",
+            "writeback",
+        ] {
+            assert!(events.iter().any(|x| x["cat"] == cat), "no {} event", cat);
+        }
+        // every span is finished before the end of the simulation
+        for x in events.iter().filter(|x| x["ph"] == "X") {
+            assert!(x["ts"].as_u64().unwrap() + x["dur"].as_u64().unwrap() <= stat.cycle);
+        }
+        let aggregations = events.iter().filter(|x| x["cat"] == "aggregator").count() as u64;
+        assert_eq!(
+            aggregations,
+            layers.iter().map(|x| x.num_input_windows).sum::<u64>()
+        );
+        // layer 0 ends with its last issued write
+        let last_write = events
+            .iter()
+            .filter(|x| x["cat"] == "writeback" && x["ph"] == "e" && x["args"]["layer_id"] == 0)
+            .map(|x| x["ts"].as_u64().unwrap())
+            .max();
+        assert_eq!(last_write, Some(layers[0].end_cycle));
+        std::fs::remove_file(trace_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_deadlock() -> Result<(), Box<dyn std::error::Error>> {
+        let (graph, node_features) = build_test_inputs()?;
+        let snapshot_path = std::env::temp_dir().join("gcn_agg_test_system_deadlock.txt");
+        let snapshot_path = snapshot_path.to_string_lossy().to_string();
+        let snapshot_path = snapshot_path.as_str();
+        // waiting for a slow memory within the threshold is not a deadlock
+        let mut acc_settings = build_test_settings(
+            RunningMode::Sparse,
+            MemoryBackendType::FixedLatency,
+            false,
+            None,
+        );
+        acc_settings.memory_settings.latency = 500;
+        acc_settings.deadlock_settings.threshold = 600;
+        System::new(&graph, &node_features, acc_settings.clone(), "")?.run()?;
+        // neither is it when the memory never tells the next return
+        let mut system = System::new(&graph, &node_features, acc_settings.clone(), "")?;
+        system.mem_interface = MemInterface::new(
+            64,
+            64,
+            Box::new(LossyMemory::new(500, usize::MAX).with_unknown_returns()),
+        );
+        system.run()?;
+
+        // a lost read is found whatever the memory tells about the next return
+        for memory in [
+            LossyMemory::new(500, 0),
+            LossyMemory::new(500, 0).with_unknown_returns(),
+        ] {
+            let mut system = System::new(&graph, &node_features, acc_settings.clone(), "")?;
+            system.mem_interface = MemInterface::new(64, 64, Box::new(memory));
+            assert!(matches!(
+                system.run(),
+                Err(GcnAggError::Deadlock {
+                    stalled_cycles: 600,
+                    ..
+                })
+            ));
+        }
+
+        // the memory loses all the reads, the first window waits forever
+        acc_settings.deadlock_settings = DeadlockSettings {
+            threshold: 100,
+            snapshot_path: Some(snapshot_path.into()),
+        };
+        let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+        system.mem_interface = MemInterface::new(64, 64, Box::new(LossyMemory::new(500, 0)));
+        match system.run() {
+            Err(GcnAggError::Deadlock {
+                stalled_cycles,
+                waits,
+                snapshot,
+                ..
+            }) => {
+                assert_eq!(stalled_cycles, 100);
+                assert!(waits.contains(&WaitFor::new(
+                    ComponentName::InputBuffer,
+                    ComponentName::MemInterface,
+                    Some(WindowId::new(0, 0, 0))
+                )));
+                assert!(waits.contains(&WaitFor::new(
+                    ComponentName::MemInterface,
+                    ComponentName::Memory,
+                    None
+                )));
+                assert_eq!(snapshot.as_deref(), Some(snapshot_path));
+                let snapshot = std::fs::read_to_string(snapshot_path)?;
+                assert!(snapshot
+                    .contains("input_buffer(layer 0 output 0 input 0) waits for mem_interface"));
+                std::fs::remove_file(snapshot_path)?;
+            }
+            result => panic!("expect a deadlock, got {:?}", result),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_fast_forward_deadlock() -> Result<(), Box<dyn std::error::Error>> {
+        let (graph, node_features) = build_test_inputs()?;
+        let run =
+            |fast_forward, latency| -> Result<(GcnAggError, u64), Box<dyn std::error::Error>> {
+                let mut acc_settings = build_test_settings(
+                    RunningMode::Sparse,
+                    MemoryBackendType::FixedLatency,
+                    fast_forward,
+                    None,
+                );
+                acc_settings.deadlock_settings.threshold = 100;
+                let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+                system.mem_interface =
+                    MemInterface::new(64, 64, Box::new(LossyMemory::new(latency, 8)));
+                let error = system.run().expect_err("expect a deadlock");
+                Ok((error, system.get_skipped_cycles()))
+            };
+        // 50: some windows are finished before the memory starts to lose the reads,
+        // each read is skipped over in one jump
+        // 200: the first read is longer than the threshold, the jump stops before the deadlock
+        for latency in [50, 200] {
+            let (error, skipped) = run(false, latency)?;
+            assert_eq!(skipped, 0);
+            assert!(matches!(
+                error,
+                GcnAggError::Deadlock {
+                    stalled_cycles: 100,
+                    ..
+                }
+            ));
+            // the same cycle and the same wait-for graph
+            let (fast_error, skipped) = run(true, latency)?;
+            assert!(skipped > 0);
+            assert_eq!(error, fast_error);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_empty_window() -> Result<(), Box<dyn std::error::Error>> {
+        let (_, node_features) = build_test_inputs()?;
+        // node 0 and node 3 have no input node, each output window of layer 0 has only one node
+        let graph = Graph::from_rows(&[vec![], vec![2, 3], vec![0, 1], vec![], vec![1, 2]], 6)?;
+        for running_mode in [RunningMode::Sparse, RunningMode::Dense] {
+            let acc_settings =
+                build_test_settings(running_mode, MemoryBackendType::FixedLatency, false, None);
+            let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+            system.run()?;
+            assert!(system.finished());
+            let layers = system.get_layer_stats();
+            assert_eq!(layers[0].num_output_windows, 5);
+            assert_eq!(layers[1].num_output_windows, 3);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_functional() -> Result<(), Box<dyn std::error::Error>> {
+        for running_mode in [RunningMode::Sparse, RunningMode::Dense, RunningMode::Mixed] {
+            let (graph, node_features) = build_test_inputs()?;
+            let mut acc_settings =
+                build_test_settings(running_mode, MemoryBackendType::FixedLatency, true, None);
+            acc_settings.functional_settings = Some(FunctionalSettings {
+                weights_paths: vec![],
+                output_dim: 3,
+                seed: 1,
+                tolerance: 1e-5,
+            });
+            let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+            let stat = system.run()?;
+            let functional = stat.functional.expect("no functional result");
+            assert_eq!(functional.len(), 2);
+            assert_eq!(functional[0].output_dim, 2);
+            assert_eq!(functional[1].output_dim, 3);
+        }
+
+        // the weights should match the dimension of each layer
+        let (graph, node_features) = build_test_inputs()?;
+        let weights_path = std::env::temp_dir().join("gcn_agg_weights_system_functional.txt");
+        std::fs::write(&weights_path, "1 2\n3 4\n")?;
+        let weights_path = weights_path.to_string_lossy().to_string();
+        let mut acc_settings = build_test_settings(
+            RunningMode::Sparse,
+            MemoryBackendType::FixedLatency,
+            true,
+            None,
+        );
+        acc_settings.functional_settings = Some(FunctionalSettings {
+            weights_paths: vec![weights_path.clone(), weights_path.clone()],
+            output_dim: 2,
+            seed: 0,
+            tolerance: 1e-5,
+        });
+        assert!(matches!(
+            System::new(&graph, &node_features, acc_settings, ""),
+            Err(GcnAggError::InputFormat { .. })
+        ));
+        std::fs::remove_file(weights_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_normalized() -> Result<(), Box<dyn std::error::Error>> {
+        let (graph, node_features) = build_test_inputs()?;
+        let mut aggregation_cycles = vec![];
+        for normalization in [Normalization::None, Normalization::Symmetric] {
+            // D^-1/2·(A+I)·D^-1/2
+            let preprocess_settings = PreprocessSettings {
+                self_loops: true,
+                symmetrize: true,
+                dedup: true,
+                normalization,
+            };
+            let graph = preprocess::apply(&preprocess_settings, graph.clone())?;
+            for running_mode in [RunningMode::Sparse, RunningMode::Dense] {
+                let mut acc_settings = build_test_settings(
+                    running_mode.clone(),
+                    MemoryBackendType::FixedLatency,
+                    true,
+                    None,
+                );
+                acc_settings.functional_settings = Some(FunctionalSettings {
+                    weights_paths: vec![],
+                    output_dim: 2,
+                    seed: 1,
+                    tolerance: 1e-5,
+                });
+                let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+                let stat = system.run()?;
+                assert!(stat.functional.is_some());
+                aggregation_cycles.push(stat.components.aggregator.busy_cycles);
+            }
+        }
+        // the scaling multiplies cost more cycles in both dataflows
+        assert!(aggregation_cycles[2] > aggregation_cycles[0]);
+        assert!(aggregation_cycles[3] > aggregation_cycles[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_weight_buffer() -> Result<(), Box<dyn std::error::Error>> {
+        let graph = Graph::from_rows(
+            &[
+                vec![1, 2],
+                vec![2, 3, 4],
+                vec![0, 1, 4],
+                vec![0, 2, 4],
+                vec![2, 4],
+            ],
+            64,
+        )?;
+        let sparse_rows = |dim: usize| -> Vec<Vec<f32>> {
+            (0..5)
+                .map(|i| (0..dim).map(|j| (j % 16 == i) as u8 as f32).collect())
+                .collect()
+        };
+        let node_features = vec![
+            NodeFeatures::from_dense(&sparse_rows(64)),
+            NodeFeatures::from_dense(&sparse_rows(32)),
+        ];
+        let run =
+            |weight_buffer_size| -> Result<(GcnStatistics, Vec<LayerStatistics>), GcnAggError> {
+                let mut acc_settings = build_test_settings(
+                    RunningMode::Sparse,
+                    MemoryBackendType::FixedLatency,
+                    false,
+                    None,
+                );
+                acc_settings.gcn_hidden_size = vec![32];
+                acc_settings.agg_buffer_size = 1024;
+                acc_settings.input_buffer_size = 1024;
+                acc_settings.weight_buffer_size = weight_buffer_size;
+                let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+                let stat = system.run()?;
+                check_component_stats(&stat, system.get_layer_stats());
+                Ok((stat, system.get_layer_stats().to_vec()))
+            };
+        // the 64 x 32 weights of layer 0 are 8192 bytes
+        let (resident, resident_layers) = run(8192)?;
+        assert_eq!(resident_layers[0].weight_fetches, 1);
+        assert_eq!(resident_layers[0].weight_bytes_read, 8192);
+        assert_eq!(resident_layers[1].weight_bytes_read, 128);
+
+        // streamed in tiles of 512 bytes for every output window
+        let (streamed, streamed_layers) = run(1024)?;
+        let windows = streamed_layers[0].num_output_windows;
+        assert!(windows > 1);
+        assert_eq!(streamed_layers[0].weight_fetches, windows);
+        assert_eq!(streamed_layers[0].weight_bytes_read, 8192 * windows);
+        // the 32 x 1 weights of layer 1 still fit
+        assert_eq!(streamed_layers[1].weight_fetches, 1);
+        // the streamed tiles overlap with each other and with the mlp, but the buffer still waits for the memory
+        assert!(
+            streamed.components.weight_buffer.stall_on_memory
+                > resident.components.weight_buffer.stall_on_memory
+        );
+
+        assert!(matches!(run(64), Err(GcnAggError::BufferTooSmall { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_sparse_encoding() -> Result<(), Box<dyn std::error::Error>> {
+        let mut stats = vec![];
+        for sparse_encoding in [
+            SparseEncoding::Csr,
+            SparseEncoding::Bitmap,
+            SparseEncoding::RunLength,
+        ] {
+            let (graph, node_features) = build_test_inputs()?;
+            let encoding = EncodingSettings {
+                sparse_encoding,
+                ..Default::default()
+            };
+            let node_features: Vec<_> = node_features
+                .into_iter()
+                .zip([6, 2])
+                .map(|(x, dim)| x.with_encoding(encoding, dim))
+                .try_collect()?;
+            let mut acc_settings = build_test_settings(
+                RunningMode::Sparse,
+                MemoryBackendType::FixedLatency,
+                false,
+                None,
+            );
+            acc_settings.encoding_settings = encoding;
+            let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+            let stat = system.run()?;
+            check_component_stats(&stat, system.get_layer_stats());
+            stats.push((stat, system.get_layer_stats().to_vec()));
+        }
+        // the 2 nonzeros of each node of layer 1: 16 bytes of csr, 9 bytes of bitmap
+        assert!(stats[1].1[0].bytes_written <= stats[0].1[0].bytes_written);
+        // no row pointer to emit
+        assert!(
+            stats[2].0.components.sparsifier.busy_cycles
+                < stats[0].0.components.sparsifier.busy_cycles
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_windows() -> Result<(), Box<dyn std::error::Error>> {
+        simple_logger::init_with_level(log::Level::Info).unwrap_or_default();
+        let graph = Graph::from_rows(
+            &[vec![1, 4], vec![0], vec![5], vec![], vec![2], vec![0, 2]],
+            4,
+        )?;
+        // one nonzero(index and value) is 8 bytes for each node of layer 0, so 4 nodes fill the half of the input buffer
+        let node_features = vec![
+            NodeFeatures::from_dense(&vec![vec![1., 0., 0., 0.]; 6]),
+            NodeFeatures::from_dense(&vec![vec![1., 0.]; 6]),
+        ];
+        let acc_settings =
+            build_test_settings(RunningMode::Sparse, MemoryBackendType::Ideal, false, None);
+        let plan = plan_windows(&graph, &node_features, &acc_settings)?;
+
+        // 2 output nodes each window
+        let layer = &plan.layers[0];
+        let ranges: Vec<_> = layer
+            .windows
+            .iter()
+            .map(|x| (x.output_range, x.input_range))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                ((0, 2), (0, 2)),
+                ((0, 2), (4, 5)),
+                ((2, 4), (5, 6)),
+                ((4, 6), (0, 3))
+            ]
+        );
+        let summary = &layer.summary;
+        assert_eq!(summary.output_windows, 3);
+        assert_eq!(summary.edges, 7);
+        assert_eq!(summary.input_bytes, 56);
+        assert_eq!(summary.average_fill, 56. / 32. / 4.);
+        assert_eq!(summary.skipped_rows, 7);
+        assert_eq!(summary.shrunk_rows, 4);
+        // node 1 in the last window
+        assert_eq!(summary.unused_bytes, 8);
+        // node 0 and 1 are loaded again by the last window
+        assert_eq!(summary.reloaded_bytes, 16);
+        assert_eq!(layer.windows.iter().filter(|x| x.is_last_row).count(), 3);
+
+        // the same windows as the simulation
+        let mut system = System::new(&graph, &node_features, acc_settings, "")?;
+        system.run()?;
+        for (layer, stat) in plan.layers.iter().zip(system.get_layer_stats()) {
+            assert_eq!(layer.summary.output_windows as u64, stat.num_output_windows);
+            assert_eq!(layer.summary.input_windows as u64, stat.num_input_windows);
+        }
+        assert_eq!(
+            plan.summary.input_windows,
+            plan.layers.iter().map(|x| x.windows.len()).sum::<usize>()
+        );
+        Ok(())
+    }
+
+    /// a fixed latency memory that returns the first `reads` read requests and loses the others
+    /// - `unknown_returns`: never tell the cycles to the next return, like the ramulator
+    #[derive(Debug)]
+    struct LossyMemory {
+        memory: FixedLatencyMemory,
+        reads: usize,
+        unknown_returns: bool,
+    }
+
+    impl LossyMemory {
+        fn new(latency: u64, reads: usize) -> Self {
+            LossyMemory {
+                memory: FixedLatencyMemory::new(latency, 1),
+                reads,
+                unknown_returns: false,
+            }
+        }
+        fn with_unknown_returns(self) -> Self {
+            LossyMemory {
+                unknown_returns: true,
+                ..self
+            }
+        }
+    }
+
+    impl MemoryBackend for LossyMemory {
+        fn available(&mut self, addr: u64, is_write: bool) -> bool {
+            self.memory.available(addr, is_write)
+        }
+        fn send(&mut self, addr: u64, is_write: bool) {
+            if is_write {
+                self.memory.send(addr, is_write);
+            } else if self.reads > 0 {
+                self.reads -= 1;
+                self.memory.send(addr, is_write);
+            }
+        }
+        fn ret_available(&mut self) -> bool {
+            self.memory.ret_available()
+        }
+        fn pop(&mut self) -> u64 {
+            self.memory.pop()
+        }
+        fn cycle(&mut self) {
+            self.memory.cycle()
+        }
+        fn get_cycles_to_next_return(&self) -> Option<u64> {
+            match self.unknown_returns {
+                true => None,
+                false => self.memory.get_cycles_to_next_return(),
+            }
+        }
+        fn fast_forward(&mut self, cycles: u64) {
+            self.memory.fast_forward(cycles)
+        }
+    }
+
+    /// the fixed latency memory that records every request
+    #[derive(Debug)]
+    struct RecordingMemory {
+        memory: Box<dyn MemoryBackend>,
+        requests: Rc<RefCell<Vec<(u64, bool)>>>,
+    }
+
+    impl MemoryBackend for RecordingMemory {
+        fn available(&mut self, addr: u64, is_write: bool) -> bool {
+            self.memory.available(addr, is_write)
+        }
+        fn send(&mut self, addr: u64, is_write: bool) {
+            self.requests.borrow_mut().push((addr, is_write));
+            self.memory.send(addr, is_write)
+        }
+        fn ret_available(&mut self) -> bool {
+            self.memory.ret_available()
+        }
+        fn pop(&mut self) -> u64 {
+            self.memory.pop()
+        }
+        fn cycle(&mut self) {
+            self.memory.cycle()
+        }
+    }
+
+    /// return the statistics and the skipped cycles
+    fn run_test_system(
+        running_mode: RunningMode,
+        backend: MemoryBackendType,
+        fast_forward: bool,
+        trace_path: Option<&str>,
+    ) -> Result<(GcnStatistics, Vec<LayerStatistics>, u64), Box<dyn std::error::Error>> {
+        let (graph, node_features) = build_test_inputs()?;
+        let acc_settings = build_test_settings(running_mode, backend, fast_forward, trace_path);
+        let stats_name = Local::now()
+            .format("output/%Y-%m-%d_%H-%M-%S%.6f-test.txt")
+            .to_string();
+        let mut system = System::new(&graph, &node_features, acc_settings, &stats_name)?;
+        let stat = system.run()?;
+        assert!(system.finished());
+        check_component_stats(&stat, system.get_layer_stats());
+        Ok((
+            stat,
+            system.get_layer_stats().to_vec(),
+            system.get_skipped_cycles(),
+        ))
+    }
+
+    /// the graph of 5 nodes and the features of 2 layers
+    fn build_test_inputs() -> Result<(Graph, Vec<NodeFeatures>), Box<dyn std::error::Error>> {
         std::fs::create_dir_all("output")?;
 
         simple_logger::init_with_level(log::Level::Info).unwrap_or_default();
 
-        let graph_name = "test_data/graph_system.txt";
-        let graph_data = "f 6\n1 2\n2 3 4\n0 1 4\n0 2 4\n2 4\nend\n";
-        let mut file = File::create(graph_name).unwrap();
-        file.write_all(graph_data.as_bytes()).unwrap();
-        let feature1 = "1 1 0 0 1 1\n1 0 0 1 1 1\n1 1 1 0 0 1\n1 1 1 0 0 1\n1 1 1 0 0 1\n";
-        let featrue1_name = "test_data/feature1_system.txt";
-        let mut file = File::create(featrue1_name).unwrap();
-        file.write_all(feature1.as_bytes()).unwrap();
-        let feature2 = "1 1\n1 1 \n1 1\n1 1\n1 1\n";
-        let featrue2_name = "test_data/feature2_system.txt";
-        let mut file = File::create(featrue2_name).unwrap();
-        file.write_all(feature2.as_bytes()).unwrap();
-
-        debug!("graph:\n{}", graph_data);
-        debug!("feature1:\n{}", feature1);
-        debug!("feature2:\n{}", feature2);
-
-        let graph = Graph::new(graph_name)?;
+        let graph = Graph::from_rows(
+            &[
+                vec![1, 2],
+                vec![2, 3, 4],
+                vec![0, 1, 4],
+                vec![0, 2, 4],
+                vec![2, 4],
+            ],
+            6,
+        )?;
         debug!("graph:\n{:?}", graph);
-        let node_features1 = NodeFeatures::new(featrue1_name)?;
-        let node_features2 = NodeFeatures::new(featrue2_name)?;
+        let node_features1 = NodeFeatures::from_dense(&[
+            vec![1., 1., 0., 0., 1., 1.],
+            vec![1., 0., 0., 1., 1., 1.],
+            vec![1., 1., 1., 0., 0., 1.],
+            vec![1., 1., 1., 0., 0., 1.],
+            vec![1., 1., 1., 0., 0., 1.],
+        ]);
+        let node_features2 = NodeFeatures::from_dense(&vec![vec![1., 1.]; 5]);
+        Ok((graph, vec![node_features1, node_features2]))
+    }
+
+    fn build_test_settings(
+        running_mode: RunningMode,
+        backend: MemoryBackendType,
+        fast_forward: bool,
+        trace_path: Option<&str>,
+    ) -> AcceleratorSettings {
         let gcn_hidden_size = vec![2];
-        let node_features = vec![node_features1, node_features2];
-        let acc_settings = AcceleratorSettings {
+        AcceleratorSettings {
             agg_buffer_size: 64,
             input_buffer_size: 64,
-            running_mode: RunningMode::Sparse,
+            weight_buffer_size: 4096,
+            running_mode,
+            mixed_settings: MixedSettings {
+                dense_threshold: 0.7,
+                granularity: MixedGranularity::Window,
+            },
+            encoding_settings: EncodingSettings::default(),
+            layout_settings: LayoutSettings {
+                alignment: 4096,
+                row_padding: false,
+                channels: 1,
+                interleave_bytes: 256,
+            },
             gcn_hidden_size,
+            memory_settings: MemorySettings {
+                backend,
+                latency: 20,
+                bandwidth: 1,
+            },
             mem_config_name: "HBM-config.cfg".into(),
+            fast_forward,
+            trace_path: trace_path.map(|x| x.to_string()),
             aggregator_settings: AggregatorSettings {
                 dense_cores: 1,
                 dense_width: 1,
@@ -855,14 +2429,80 @@ mod test {
             sparsifier_settings: SparsifierSettings {
                 sparsifier_cores: 2,
             },
-        };
-        let stats_name = Local::now()
-            .format("output/%Y-%m-%d_%H-%M-%S%.6f-test.txt")
-            .to_string();
-        let mut system = System::new(&graph, &node_features, acc_settings, &stats_name);
-        system.run()?;
-        assert!(system.finished());
-        Ok(())
+            deadlock_settings: DeadlockSettings {
+                threshold: 2000000,
+                snapshot_path: None,
+            },
+            functional_settings: None,
+        }
+    }
+
+    /// every cycle of each component should be classified exactly once
+    fn check_component_stats(stat: &GcnStatistics, layers: &[LayerStatistics]) {
+        let components = &stat.components;
+        for component in [
+            &components.aggregator,
+            &components.mlp,
+            &components.sparsifier,
+            &components.input_buffer,
+            &components.agg_buffer,
+            &components.sparsify_buffer,
+            &components.output_buffer,
+            &components.weight_buffer,
+            &components.mem_interface,
+        ] {
+            assert_eq!(
+                component.busy_cycles + component.idle_cycles + component.stall_cycles,
+                stat.cycle
+            );
+            assert_eq!(
+                component.stall_on_memory
+                    + component.stall_on_upstream
+                    + component.stall_on_downstream_full,
+                component.stall_cycles
+            );
+        }
+        assert!(components.aggregator.busy_cycles > 0);
+        assert!(components.mlp.busy_cycles > 0);
+        assert!(components.mem_interface.busy_cycles > 0);
+
+        assert_eq!(layers.len(), 2);
+        assert_eq!(
+            layers.iter().map(|l| l.aggregation_cycles).sum::<u64>(),
+            components.aggregator.busy_cycles
+        );
+        assert_eq!(
+            layers.iter().map(|l| l.mlp_cycles).sum::<u64>(),
+            components.mlp.busy_cycles
+        );
+        for layer in layers {
+            assert!(layer.num_input_windows >= layer.num_output_windows);
+            assert!(layer.num_output_windows > 0);
+            assert!(layer.bytes_read > 0);
+            assert!(layer.weight_fetches > 0);
+            assert!(layer.start_cycle <= layer.end_cycle);
+        }
+        // the next layer can only start after the previous layer is finished
+        assert!(layers[1].start_cycle >= layers[0].end_cycle);
+        assert!(layers[0].bytes_written > 0);
+        // the final layer is not written back
+        assert_eq!(layers[1].bytes_written, 0);
+
+        let memory = &stat.memory;
+        assert_eq!(memory.cycles, stat.cycle);
+        assert_eq!(memory.send_queue_occupancy.iter().sum::<u64>(), stat.cycle);
+        assert_eq!(
+            memory.total.write_bytes,
+            layers.iter().map(|l| l.bytes_written).sum::<u64>()
+        );
+        // the requests to the same addr may be merged
+        assert_eq!(
+            memory.total.read_bytes + memory.total.merged_requests * 64,
+            layers
+                .iter()
+                .map(|l| l.bytes_read + l.weight_bytes_read)
+                .sum::<u64>()
+        );
     }
     #[test]
     fn window_iter_test() {
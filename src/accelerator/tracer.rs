@@ -0,0 +1,220 @@
+//! # Description
+//! - record the span of each unit of work in the pipeline, and write them as chrome trace-event json
+//! - the result can be opened by `chrome://tracing` or perfetto(https://ui.perfetto.dev)
+//! - one cycle is written as one microsecond
+//!
+//! # Tracks
+//! - `Aggregator`, `Mlp` and `Sparsifier` never overlap with themselves, they are written as complete events
+//! - `MemoryLoad` and `Writeback` can overlap(the double buffer), they are written as async events
+//!
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::window_id::WindowId;
+use crate::error::GcnAggError;
+
+/// # Description
+/// - the component that a span belongs to, each track is a thread in the trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceTrack {
+    Aggregator,
+    Mlp,
+    Sparsifier,
+    MemoryLoad,
+    Writeback,
+}
+
+impl TraceTrack {
+    fn name(&self) -> &'static str {
+        match self {
+            TraceTrack::Aggregator => "aggregator",
+            TraceTrack::Mlp => "mlp",
+            TraceTrack::Sparsifier => "sparsifier",
+            TraceTrack::MemoryLoad => "memory_load",
+            TraceTrack::Writeback => "writeback",
+        }
+    }
+    fn tid(&self) -> usize {
+        *self as usize
+    }
+    fn is_async(&self) -> bool {
+        matches!(self, TraceTrack::MemoryLoad | TraceTrack::Writeback)
+    }
+    fn all() -> [TraceTrack; 5] {
+        [
+            TraceTrack::Aggregator,
+            TraceTrack::Mlp,
+            TraceTrack::Sparsifier,
+            TraceTrack::MemoryLoad,
+            TraceTrack::Writeback,
+        ]
+    }
+}
+
+/// # Description
+/// - one event in the chrome trace-event format
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    ts: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    pid: usize,
+    tid: usize,
+    args: Value,
+}
+
+/// # Description
+/// - the tracer of the system, collect the spans and write them to `path` in the end
+/// # Fields
+/// - `path`: the output json file
+/// - `open_spans`: the start cycle of the spans not finished yet
+/// - `events`: the finished events
+/// - `next_async_id`: the id of the next async span
+#[derive(Debug)]
+pub struct Tracer {
+    path: String,
+    open_spans: HashMap<(TraceTrack, WindowId), u64>,
+    events: Vec<TraceEvent>,
+    next_async_id: u64,
+}
+
+impl Tracer {
+    pub fn new(path: &str) -> Self {
+        Tracer {
+            path: path.to_string(),
+            open_spans: HashMap::new(),
+            events: Vec::new(),
+            next_async_id: 0,
+        }
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    /// # Description
+    /// - start a span of `window` on `track` at `cycle`
+    pub fn begin(&mut self, track: TraceTrack, window: &WindowId, cycle: u64) {
+        self.open_spans.insert((track, window.clone()), cycle);
+    }
+
+    /// # Description
+    /// - finish the span of `window` on `track` at `cycle`, ignored if the span is not started
+    pub fn end(&mut self, track: TraceTrack, window: &WindowId, cycle: u64) {
+        let start = match self.open_spans.remove(&(track, window.clone())) {
+            Some(start) => start,
+            None => return,
+        };
+        let name = format!(
+            "layer {} output {} input {}",
+            window.layer_id, window.output_id, window.input_id
+        );
+        let args = json!({
+            "layer_id": window.layer_id,
+            "output_id": window.output_id,
+            "input_id": window.input_id,
+        });
+        if track.is_async() {
+            let id = self.next_async_id;
+            self.next_async_id += 1;
+            for (ph, ts) in [("b", start), ("e", cycle)] {
+                self.events.push(TraceEvent {
+                    name: name.clone(),
+                    cat: track.name(),
+                    ph,
+                    ts,
+                    dur: None,
+                    id: Some(id),
+                    pid: 0,
+                    tid: track.tid(),
+                    args: args.clone(),
+                });
+            }
+        } else {
+            self.events.push(TraceEvent {
+                name,
+                cat: track.name(),
+                ph: "X",
+                ts: start,
+                dur: Some(cycle - start),
+                id: None,
+                pid: 0,
+                tid: track.tid(),
+                args,
+            });
+        }
+    }
+
+    /// # Description
+    /// - the trace-event json, the unfinished spans are dropped
+    pub fn to_json(&self) -> Value {
+        let metadata = TraceTrack::all().into_iter().map(|track| {
+            json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 0,
+                "tid": track.tid(),
+                "args": {"name": track.name()},
+            })
+        });
+        let events = metadata
+            .chain(self.events.iter().map(|x| json!(x)))
+            .collect::<Vec<_>>();
+        json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ns",
+            "otherData": {"time_unit": "1 cycle per microsecond"},
+        })
+    }
+
+    /// # Description
+    /// - write the trace to `path`
+    pub fn write(&self) -> Result<(), GcnAggError> {
+        let json =
+            serde_json::to_string(&self.to_json()).map_err(|e| GcnAggError::io(&self.path, e))?;
+        std::fs::write(&self.path, json).map_err(|e| GcnAggError::io(&self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tracer() {
+        let mut tracer = Tracer::new("output/test_tracer.json");
+        let window = WindowId::new(1, 2, 0);
+        tracer.begin(TraceTrack::Aggregator, &window, 3);
+        tracer.begin(TraceTrack::MemoryLoad, &window, 1);
+        tracer.end(TraceTrack::Aggregator, &window, 10);
+        tracer.end(TraceTrack::MemoryLoad, &window, 4);
+        // never started
+        tracer.end(TraceTrack::Mlp, &window, 12);
+
+        let json = tracer.to_json();
+        let events = json["traceEvents"].as_array().unwrap();
+        // 5 thread names, 1 complete event and 2 async events
+        assert_eq!(events.len(), 8);
+        let complete = events.iter().find(|x| x["ph"] == "X").unwrap();
+        assert_eq!(complete["ts"], 3);
+        assert_eq!(complete["dur"], 7);
+        assert_eq!(complete["args"]["output_id"], 1);
+        assert_eq!(complete["args"]["input_id"], 2);
+        let async_events = events
+            .iter()
+            .filter(|x| x["cat"] == "memory_load")
+            .collect::<Vec<_>>();
+        assert_eq!(async_events.len(), 2);
+        assert_eq!(async_events[0]["ph"], "b");
+        assert_eq!(async_events[1]["ph"], "e");
+        assert_eq!(async_events[0]["id"], async_events[1]["id"]);
+    }
+}
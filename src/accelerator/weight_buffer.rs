@@ -0,0 +1,234 @@
+//! # Description
+//! - the weight buffer holds the weights `W` of the layer that the mlp is working on
+//! - when the whole `input_dim × output_dim` matrix fits into the buffer, it's loaded once and reused by every
+//!   output window of the layer
+//! - otherwise the buffer is a double buffer of two tiles of half the size, the tiles are streamed for every output
+//!   window: each half has one tile on flight, so the next tile is loaded while the other one is on flight or on chip,
+//!   the mlp starts when the first tile is on chip, and finishes only after the last tile is loaded
+//!
+
+use log::debug;
+
+use super::{memory_layout::REQUEST_BYTES, window_id::WindowId};
+use crate::error::GcnAggError;
+
+/// one tile on flight for each half of the buffer
+const TILES_ON_FLIGHT: usize = 2;
+
+/// # Description
+/// - `Empty`: no weights in the buffer
+/// - `WaitingToLoad`: the next tile should be sent to the memory
+/// - `Loading`: both halves have a tile on flight, or the last tiles are on flight
+/// - `Ready`: all tiles of the current fetch are loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferStatus {
+    Empty,
+    WaitingToLoad,
+    Loading,
+    Ready,
+}
+
+/// # Description
+/// - `layer`: the layer of the weights in the buffer
+/// - `resident`: the whole matrix fits, the weights stay until the next layer
+/// - `tiles`: the requests of each tile of the current fetch
+/// - `sent_tiles` and `loaded_tiles`: the progress of the current fetch
+/// - `sent_requests`: the number of tiles sent so far, used as the id of the requests
+#[derive(Debug)]
+pub struct WeightBuffer {
+    size: usize,
+    state: BufferStatus,
+    layer: Option<usize>,
+    resident: bool,
+    tiles: Vec<Vec<u64>>,
+    sent_tiles: usize,
+    loaded_tiles: usize,
+    sent_requests: usize,
+}
+
+impl WeightBuffer {
+    pub fn new(size: usize) -> Self {
+        WeightBuffer {
+            size,
+            state: BufferStatus::Empty,
+            layer: None,
+            resident: false,
+            tiles: vec![],
+            sent_tiles: 0,
+            loaded_tiles: 0,
+            sent_requests: 0,
+        }
+    }
+
+    pub fn get_state(&self) -> BufferStatus {
+        self.state
+    }
+
+    /// # Description
+    /// - the buffer holds the weights of `layer`, or is fetching them
+    pub fn holds(&self, layer: usize) -> bool {
+        self.state != BufferStatus::Empty && self.layer == Some(layer)
+    }
+
+    /// # Description
+    /// - the mlp of `layer` can start: at least the first tile is on chip
+    pub fn is_ready(&self, layer: usize) -> bool {
+        self.holds(layer) && self.loaded_tiles > 0
+    }
+
+    /// # Description
+    /// - all tiles of the current fetch are loaded, the mlp can finish
+    pub fn all_loaded(&self) -> bool {
+        self.state == BufferStatus::Ready
+    }
+
+    /// # Description
+    /// - the number of requests in each tile of a matrix of `requests` requests
+    /// # Errors
+    /// - `BufferTooSmall` if the matrix does not fit and half of the buffer cannot hold one request
+    pub fn requests_per_tile(&self, layer: usize, requests: usize) -> Result<usize, GcnAggError> {
+        let request_bytes = REQUEST_BYTES as usize;
+        if requests * request_bytes <= self.size {
+            return Ok(requests.max(1));
+        }
+        match self.size / 2 / request_bytes {
+            0 => Err(GcnAggError::BufferTooSmall {
+                buffer: "weight_buffer".into(),
+                size: self.size,
+                required: 2 * request_bytes,
+                layer,
+            }),
+            x => Ok(x),
+        }
+    }
+
+    /// # Description
+    /// - start to fetch the weights of `layer`, `requests` is the whole matrix, see `MemoryLayout::weight_requests`
+    /// - the weights of the previous layer are dropped
+    /// # Errors
+    /// - `BufferTooSmall` if the tiles cannot fit, see `requests_per_tile`
+    pub fn start_load(&mut self, layer: usize, requests: Vec<u64>) -> Result<(), GcnAggError> {
+        let requests_per_tile = self.requests_per_tile(layer, requests.len())?;
+        self.resident = requests.len() * REQUEST_BYTES as usize <= self.size;
+        self.tiles = requests
+            .chunks(requests_per_tile)
+            .map(|x| x.to_vec())
+            .collect();
+        debug!(
+            "load the weights of layer {}: {} requests in {} tiles",
+            layer,
+            requests.len(),
+            self.tiles.len()
+        );
+        self.layer = Some(layer);
+        self.sent_tiles = 0;
+        self.loaded_tiles = 0;
+        self.update_state();
+        Ok(())
+    }
+
+    /// # Description
+    /// - the id and the requests of the next tile to send, and mark it on flight
+    /// - each tile has its own id, so the two tiles on flight return separately, the id is never used by the
+    ///   input windows
+    pub fn send_next_tile(&mut self) -> Option<(WindowId, Vec<u64>)> {
+        if self.state != BufferStatus::WaitingToLoad {
+            return None;
+        }
+        let tile = self.tiles[self.sent_tiles].clone();
+        self.sent_tiles += 1;
+        self.sent_requests += 1;
+        self.update_state();
+        let id = WindowId::weights(self.layer.unwrap_or_default(), self.sent_requests);
+        Some((id, tile))
+    }
+
+    /// # Description
+    /// - a tile on flight is returned, the next one can be loaded into its half of the buffer
+    /// # Errors
+    /// - `InvalidState` if no tile is on flight
+    pub fn receive(&mut self) -> Result<(), GcnAggError> {
+        if self.state == BufferStatus::Empty || self.loaded_tiles == self.sent_tiles {
+            return Err(GcnAggError::InvalidState(format!(
+                "the weight buffer receives a tile in state {:?}",
+                self.state
+            )));
+        }
+        self.loaded_tiles += 1;
+        self.update_state();
+        Ok(())
+    }
+
+    /// # Description
+    /// - the mlp of an output window is finished, the streamed tiles are dropped and fetched again by the next window
+    pub fn finished_mlp(&mut self) {
+        if !self.resident {
+            self.state = BufferStatus::Empty;
+            self.layer = None;
+        }
+    }
+
+    fn update_state(&mut self) {
+        self.state = if self.loaded_tiles == self.tiles.len() {
+            BufferStatus::Ready
+        } else if self.sent_tiles < self.tiles.len()
+            && self.sent_tiles - self.loaded_tiles < TILES_ON_FLIGHT
+        {
+            BufferStatus::WaitingToLoad
+        } else {
+            BufferStatus::Loading
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_weight_buffer() -> Result<(), GcnAggError> {
+        // 4 requests fit into 256 bytes
+        let mut buffer = WeightBuffer::new(256);
+        buffer.start_load(0, vec![0, 64, 128, 192])?;
+        assert!(buffer.holds(0) && !buffer.is_ready(0));
+        let (id, tile) = buffer.send_next_tile().unwrap();
+        assert!(id.is_weights());
+        assert_eq!(tile.len(), 4);
+        assert!(buffer.send_next_tile().is_none());
+        buffer.receive()?;
+        assert!(buffer.is_ready(0) && buffer.all_loaded());
+        // the resident weights are reused
+        buffer.finished_mlp();
+        assert!(buffer.is_ready(0));
+
+        // 5 requests are streamed in tiles of 2 requests, one tile on flight for each half
+        buffer.start_load(1, (0..5).map(|x| x * 64).collect())?;
+        assert!(!buffer.holds(0));
+        let (first, tile) = buffer.send_next_tile().unwrap();
+        assert_eq!(tile.len(), 2);
+        let (second, tile) = buffer.send_next_tile().unwrap();
+        assert_eq!(tile.len(), 2);
+        assert_ne!(first, second);
+        assert_eq!(buffer.get_state(), BufferStatus::Loading);
+        assert!(buffer.send_next_tile().is_none() && !buffer.is_ready(1));
+        buffer.receive()?;
+        assert!(buffer.is_ready(1));
+        let (_, tile) = buffer.send_next_tile().unwrap();
+        assert_eq!(tile.len(), 1);
+        assert!(buffer.send_next_tile().is_none());
+        buffer.receive()?;
+        assert!(!buffer.all_loaded());
+        buffer.receive()?;
+        assert!(buffer.all_loaded());
+        buffer.finished_mlp();
+        assert!(!buffer.holds(1));
+        assert!(buffer.receive().is_err());
+
+        let mut buffer = WeightBuffer::new(64);
+        assert!(matches!(
+            buffer.start_load(0, vec![0, 64]),
+            Err(GcnAggError::BufferTooSmall { .. })
+        ));
+        Ok(())
+    }
+}
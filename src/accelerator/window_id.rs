@@ -1,6 +1,11 @@
+use serde::Serialize;
+
+/// the input id of the weight fetches
+const WEIGHTS_INPUT_ID: usize = usize::MAX;
+
 /// # Description
 /// - struct Req define a window
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
 pub struct WindowId {
     pub output_id: usize,
     pub input_id: usize,
@@ -15,4 +20,20 @@ impl WindowId {
             layer_id,
         }
     }
+
+    /// # Description
+    /// - the id of the `tile`th weight tile sent for `layer_id`, the input id is never used by a window
+    pub fn weights(layer_id: usize, tile: usize) -> Self {
+        WindowId {
+            output_id: tile,
+            input_id: WEIGHTS_INPUT_ID,
+            layer_id,
+        }
+    }
+
+    /// # Description
+    /// - the id is a weight fetch, not a window
+    pub fn is_weights(&self) -> bool {
+        self.input_id == WEIGHTS_INPUT_ID
+    }
 }
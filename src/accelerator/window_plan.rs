@@ -0,0 +1,174 @@
+//! # Description
+//! - the dry run: build all windows of every layer by the window iterators without ticking the system
+//! - each input window is recorded with its ranges, edges and bytes, and how the tiling wastes the input buffer,
+//!   so the buffer sizes can be tuned without the simulation
+//!
+
+use super::sliding_window::{InputWindow, OutputWindowIterator, WindowIterSettings};
+use crate::{
+    error::GcnAggError,
+    gcn_result::{LayerPlan, PlanSummary, WindowPlan, WindowRecord},
+    graph::Graph,
+    node_features::NodeFeatures,
+    settings::{AcceleratorSettings, RunningMode},
+};
+
+/// # Description
+/// - plan the windows of all layers like `System::new` and `System::run`
+/// # Errors
+/// - `Config` if the number of layers does not match
+/// - `BufferTooSmall` if any layer cannot fit into the buffers
+pub fn plan_windows(
+    graph: &Graph,
+    node_features: &[NodeFeatures],
+    acc_settings: &AcceleratorSettings,
+) -> Result<WindowPlan, GcnAggError> {
+    let gcn_hidden_size = &acc_settings.gcn_hidden_size;
+    if node_features.len() != gcn_hidden_size.len() + 1 {
+        return Err(GcnAggError::Config(format!(
+            "there are {} layers of node features, but {} gcn hidden sizes",
+            node_features.len(),
+            gcn_hidden_size.len()
+        )));
+    }
+    let num_layers = node_features.len();
+    let mut layers = vec![];
+    for (layer, features) in node_features.iter().enumerate() {
+        let window_iter_settings = WindowIterSettings {
+            agg_buffer_size: acc_settings.agg_buffer_size,
+            input_buffer_size: acc_settings.input_buffer_size,
+            layer,
+            gcn_hidden_size: gcn_hidden_size.clone(),
+            final_layer: layer == num_layers - 1,
+            running_mode: acc_settings.running_mode.clone(),
+            mixed_settings: acc_settings.mixed_settings.clone(),
+        };
+        let output_iter = OutputWindowIterator::new(graph, features, window_iter_settings)?;
+        layers.push(plan_layer(
+            layer,
+            graph,
+            features,
+            output_iter,
+            acc_settings.input_buffer_size / 2,
+        ));
+    }
+    let summary = PlanSummary::merge(layers.iter().map(|x| &x.summary));
+    Ok(WindowPlan { layers, summary })
+}
+
+fn plan_layer(
+    layer: usize,
+    graph: &Graph,
+    features: &NodeFeatures,
+    output_iter: OutputWindowIterator,
+    buffer_size: usize,
+) -> LayerPlan {
+    let mut windows = vec![];
+    let mut summary = PlanSummary::default();
+    // the input nodes loaded by any window of this layer
+    let mut loaded = vec![false; graph.get_num_node()];
+    for input_iter in output_iter {
+        summary.output_windows += 1;
+        let mut previous_end = 0;
+        for window in input_iter {
+            let record = record_window(graph, features, &window, previous_end, buffer_size);
+            previous_end = window.end_input_index;
+            let input_range = window.start_input_index..window.end_input_index;
+            for (i, loaded) in input_range.clone().zip(&mut loaded[input_range]) {
+                let bytes = node_bytes(features, &window, i);
+                if graph.is_row_range_empty(i, window.start_output_index, window.end_output_index) {
+                    summary.unused_bytes += bytes;
+                }
+                if *loaded {
+                    summary.reloaded_bytes += bytes;
+                }
+                *loaded = true;
+            }
+            summary.add(&record);
+            windows.push(record);
+        }
+    }
+    summary.finish();
+    LayerPlan {
+        layer,
+        summary,
+        windows,
+    }
+}
+
+fn record_window(
+    graph: &Graph,
+    features: &NodeFeatures,
+    window: &InputWindow,
+    previous_end: usize,
+    buffer_size: usize,
+) -> WindowRecord {
+    let input_bytes: u64 = (window.start_input_index..window.end_input_index)
+        .map(|i| node_bytes(features, window, i))
+        .sum();
+    WindowRecord {
+        id: window.get_task_id().clone(),
+        running_mode: window.get_output_window().get_running_mode().clone(),
+        input_range: (window.start_input_index, window.end_input_index),
+        output_range: (window.start_output_index, window.end_output_index),
+        edges: window.get_tasks().iter().map(|x| x.len()).sum(),
+        input_bytes,
+        fill: input_bytes as f64 / buffer_size as f64,
+        is_last_row: window.is_last_row,
+        skipped_rows: window.start_input_index.saturating_sub(previous_end),
+        shrunk_rows: window.shrunk_rows,
+        unused_rows: (window.start_input_index..window.end_input_index)
+            .filter(|&i| {
+                graph.is_row_range_empty(i, window.start_output_index, window.end_output_index)
+            })
+            .count(),
+    }
+}
+
+/// the bytes of input node `i` in the input buffer
+fn node_bytes(features: &NodeFeatures, window: &InputWindow, i: usize) -> u64 {
+    match window.get_output_window().get_running_mode() {
+        RunningMode::Dense => {
+            (window.get_output_window().get_input_dim() * features.get_encoding().element_bytes())
+                as u64
+        }
+        _ => features.get_node_bytes(i) as u64,
+    }
+}
+
+impl PlanSummary {
+    fn add(&mut self, record: &WindowRecord) {
+        self.input_windows += 1;
+        self.edges += record.edges;
+        self.input_bytes += record.input_bytes;
+        // the sum of the fill until `finish`
+        self.average_fill += record.fill;
+        self.skipped_rows += record.skipped_rows;
+        self.shrunk_rows += record.shrunk_rows;
+    }
+
+    fn finish(&mut self) {
+        if self.input_windows != 0 {
+            self.average_fill /= self.input_windows as f64;
+        }
+    }
+
+    /// # Description
+    /// - the summary of all layers, the fill is averaged over all input windows
+    fn merge<'a>(layers: impl Iterator<Item = &'a PlanSummary>) -> PlanSummary {
+        let mut total = PlanSummary::default();
+        for layer in layers {
+            total.output_windows += layer.output_windows;
+            total.input_windows += layer.input_windows;
+            total.edges += layer.edges;
+            total.input_bytes += layer.input_bytes;
+            total.average_fill += layer.average_fill * layer.input_windows as f64;
+            total.reloaded_bytes += layer.reloaded_bytes;
+            total.unused_bytes += layer.unused_bytes;
+            total.skipped_rows += layer.skipped_rows;
+            total.shrunk_rows += layer.shrunk_rows;
+        }
+        total.finish();
+        total
+    }
+}
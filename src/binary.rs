@@ -0,0 +1,246 @@
+//! # Description
+//! - the compact binary files of the graph and the node features, all numbers are little endian
+//! - a file is the 8 bytes magic, the version(u32), the payload and the checksum(u64) of all the bytes before it
+//! - the checksum and the cache key are the 64 bits FNV-1a hash
+//! - `load_cached` reads the binary file in the cache dir when the source file is not changed,
+//!   otherwise it parses the source file and writes the binary file for the next run
+//!
+
+use std::path::Path;
+
+use log::{info, warn};
+
+use crate::error::GcnAggError;
+
+/// the version of the binary files, bump it when the layout of any payload changes
+pub const BINARY_VERSION: u32 = 1;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// # Description
+/// - continue the FNV-1a hash `hash` with `data`, start from `fnv1a(None, ...)`
+pub fn fnv1a(hash: Option<u64>, data: &[u8]) -> u64 {
+    data.iter().fold(hash.unwrap_or(FNV_OFFSET), |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// # Description
+/// - build the bytes of a binary file
+#[derive(Debug)]
+pub struct BinaryWriter {
+    data: Vec<u8>,
+}
+
+impl BinaryWriter {
+    pub fn new(magic: &[u8; 8]) -> Self {
+        let mut writer = BinaryWriter {
+            data: magic.to_vec(),
+        };
+        writer.write_u32(BINARY_VERSION);
+        writer
+    }
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn write_f32(&mut self, value: f32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// # Description
+    /// - append the checksum and write the file
+    pub fn write(mut self, path: &str) -> Result<(), GcnAggError> {
+        let checksum = fnv1a(None, &self.data);
+        self.write_u64(checksum);
+        std::fs::write(path, &self.data).map_err(|e| GcnAggError::io(path, e))
+    }
+}
+
+/// # Description
+/// - read the payload of a binary file, the header and the checksum are checked in `open`
+#[derive(Debug)]
+pub struct BinaryReader {
+    path: String,
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl BinaryReader {
+    /// # Description
+    /// - read `path` and check the magic, the version and the checksum
+    pub fn open(path: &str, magic: &[u8; 8]) -> Result<Self, GcnAggError> {
+        let mut data = std::fs::read(path).map_err(|e| GcnAggError::io(path, e))?;
+        if data.len() < 20 || &data[..8] != magic {
+            return Err(GcnAggError::input_format(
+                path,
+                None,
+                format!(
+                    "not a binary file of {}",
+                    String::from_utf8_lossy(magic).trim()
+                ),
+            ));
+        }
+        let checksum_pos = data.len() - 8;
+        let checksum = u64::from_le_bytes(data[checksum_pos..].try_into().unwrap());
+        if fnv1a(None, &data[..checksum_pos]) != checksum {
+            return Err(GcnAggError::input_format(path, None, "checksum mismatch"));
+        }
+        data.truncate(checksum_pos);
+        let mut reader = BinaryReader {
+            path: path.to_string(),
+            data,
+            pos: 8,
+        };
+        let version = reader.read_u32()?;
+        if version != BINARY_VERSION {
+            return Err(GcnAggError::input_format(
+                path,
+                None,
+                format!(
+                    "the version is {}, but {} is supported",
+                    version, BINARY_VERSION
+                ),
+            ));
+        }
+        Ok(reader)
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], GcnAggError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| GcnAggError::input_format(&self.path, None, "unexpected end of file"))?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+    pub fn read_u32(&mut self) -> Result<u32, GcnAggError> {
+        Ok(u32::from_le_bytes(self.read_bytes()?))
+    }
+    pub fn read_u64(&mut self) -> Result<u64, GcnAggError> {
+        Ok(u64::from_le_bytes(self.read_bytes()?))
+    }
+    pub fn read_f32(&mut self) -> Result<f32, GcnAggError> {
+        Ok(f32::from_le_bytes(self.read_bytes()?))
+    }
+    /// # Description
+    /// - read a u64 that is a length or an offset
+    pub fn read_len(&mut self) -> Result<usize, GcnAggError> {
+        let value = self.read_u64()?;
+        usize::try_from(value).map_err(|e| self.error(e))
+    }
+
+    /// # Description
+    /// - the offsets of `num_rows` rows, they should start from 0 and never decrease
+    pub fn read_offsets(&mut self, num_rows: usize) -> Result<Vec<usize>, GcnAggError> {
+        let offsets: Vec<usize> = (0..=num_rows)
+            .map(|_| self.read_len())
+            .collect::<Result<_, _>>()?;
+        if offsets[0] != 0 || offsets.windows(2).any(|x| x[0] > x[1]) {
+            return Err(self.error("the offsets are not sorted"));
+        }
+        Ok(offsets)
+    }
+
+    /// # Description
+    /// - the payload should be fully read
+    pub fn finish(self) -> Result<(), GcnAggError> {
+        match self.pos == self.data.len() {
+            true => Ok(()),
+            false => Err(self.error(format!(
+                "{} bytes left after the payload",
+                self.data.len() - self.pos
+            ))),
+        }
+    }
+
+    pub fn error(&self, message: impl std::fmt::Display) -> GcnAggError {
+        GcnAggError::input_format(&self.path, None, message)
+    }
+}
+
+/// # Description
+/// - the cache file of `source` in `cache_dir`, named by the file name of `source` and the hash of its content and `key`
+/// - `key` is anything else that changes the parsed result, e.g. the format
+pub fn cache_path(cache_dir: &str, source: &str, key: &str) -> Result<String, GcnAggError> {
+    let contents = std::fs::read(source).map_err(|e| GcnAggError::io(source, e))?;
+    let hash = fnv1a(Some(fnv1a(None, &contents)), key.as_bytes());
+    let file_name = Path::new(source)
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(Path::new(cache_dir)
+        .join(format!("{}.{:016x}.bin", file_name, hash))
+        .to_string_lossy()
+        .to_string())
+}
+
+/// # Description
+/// - load `source` through the binary cache in `cache_dir`
+/// - `load` reads the cache file, `parse` reads the source file, and `save` writes the cache file
+/// - a broken cache file is parsed again and replaced, failing to write the cache is only a warning
+pub fn load_cached<T>(
+    cache_dir: &str,
+    source: &str,
+    key: &str,
+    load: impl FnOnce(&str) -> Result<T, GcnAggError>,
+    parse: impl FnOnce(&str) -> Result<T, GcnAggError>,
+    save: impl FnOnce(&T, &str) -> Result<(), GcnAggError>,
+) -> Result<T, GcnAggError> {
+    let path = cache_path(cache_dir, source, key)?;
+    if Path::new(&path).exists() {
+        match load(&path) {
+            Ok(result) => {
+                info!("{} is loaded from the cache {}", source, path);
+                return Ok(result);
+            }
+            Err(e) => warn!("the cache of {} is broken, parse it again: {}", source, e),
+        }
+    }
+    let result = parse(source)?;
+    if let Err(e) = std::fs::create_dir_all(cache_dir)
+        .map_err(|e| GcnAggError::io(cache_dir, e))
+        .and_then(|_| save(&result, &path))
+    {
+        warn!("failed to write the cache of {}: {}", source, e);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binary_file() -> Result<(), GcnAggError> {
+        let path = "test_data/binary_file.bin";
+        let mut writer = BinaryWriter::new(b"GCNTEST ");
+        writer.write_u64(2);
+        writer.write_u32(7);
+        writer.write_f32(0.5);
+        writer.write(path)?;
+
+        let mut reader = BinaryReader::open(path, b"GCNTEST ")?;
+        assert_eq!(reader.read_u64()?, 2);
+        assert_eq!(reader.read_u32()?, 7);
+        assert_eq!(reader.read_f32()?, 0.5);
+        assert!(reader.read_u32().is_err());
+        reader.finish()?;
+
+        // the wrong magic
+        assert!(BinaryReader::open(path, b"GCNOTHER").is_err());
+        // flip one bit of the payload
+        let mut data = std::fs::read(path).unwrap();
+        data[13] ^= 1;
+        std::fs::write(path, data).unwrap();
+        assert!(matches!(
+            BinaryReader::open(path, b"GCNTEST "),
+            Err(GcnAggError::InputFormat { message, .. }) if message == "checksum mismatch"
+        ));
+        std::fs::remove_file(path).unwrap();
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, ValueHint};
+use clap::{Parser, Subcommand, ValueHint};
 use clap_complete::Shell;
 
 #[derive(Debug, Parser)]
@@ -8,7 +8,24 @@ pub struct Args {
     #[clap(long = "generate", short = 'g', arg_enum)]
     pub generator: Option<Shell>,
 
+    /// only build the windows of every layer and report them, without running the simulation
+    #[clap(long)]
+    pub dry_run: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Commands>,
+
     /// the paths of config files
     #[clap(value_hint=ValueHint::FilePath)]
     pub config_names: Vec<String>,
 }
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// load the graph and the node features, report their statistics as json without the simulation
+    GraphStats {
+        /// the paths of config files(`.toml`), or a graph file to replace the graph of the configs
+        #[clap(value_hint=ValueHint::FilePath, required = true)]
+        paths: Vec<String>,
+    },
+}
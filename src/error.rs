@@ -0,0 +1,175 @@
+//! # Description
+//! - the error type of the simulator, all the loaders and `System::run` return it
+//! - a bad config or input only fails the current simulation, it will not abort the process
+//!
+
+use std::{error::Error, fmt::Display};
+
+use itertools::Itertools;
+
+use crate::accelerator::WaitFor;
+
+/// # Description
+/// - the errors of the simulator
+/// - `Config`: the settings are invalid or cannot be read
+/// - `Io`: the file cannot be read or written
+/// - `InputFormat`: the graph or node features file is not in the expected format
+/// - `BufferTooSmall`: a buffer cannot hold even one unit of work
+/// - `Deadlock`: no component makes progress for `stalled_cycles` cycles, `waits` is the wait-for graph at `cycle`,
+///   `snapshot` is the file of the system snapshot if enabled
+/// - `InvalidState`: a component is asked to do something that is not allowed in its current state
+/// - `FunctionalMismatch`: the output of `node` in `layer` differs from the reference in the functional simulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GcnAggError {
+    Config(String),
+    Io {
+        path: String,
+        message: String,
+    },
+    InputFormat {
+        path: String,
+        line: Option<usize>,
+        message: String,
+    },
+    BufferTooSmall {
+        buffer: String,
+        size: usize,
+        required: usize,
+        layer: usize,
+    },
+    Deadlock {
+        cycle: u64,
+        stalled_cycles: u64,
+        waits: Vec<WaitFor>,
+        snapshot: Option<String>,
+    },
+    InvalidState(String),
+    FunctionalMismatch {
+        layer: usize,
+        node: usize,
+        message: String,
+    },
+}
+
+impl GcnAggError {
+    /// # Description
+    /// - the error when reading or writing `path`
+    pub fn io(path: &str, error: impl Display) -> Self {
+        GcnAggError::Io {
+            path: path.to_string(),
+            message: error.to_string(),
+        }
+    }
+    /// # Description
+    /// - the format error in `path`, `line` starts from 1
+    pub fn input_format(path: &str, line: Option<usize>, message: impl Display) -> Self {
+        GcnAggError::InputFormat {
+            path: path.to_string(),
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for GcnAggError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GcnAggError::Config(message) => write!(f, "config error: {}", message),
+            GcnAggError::Io { path, message } => write!(f, "io error: {}: {}", path, message),
+            GcnAggError::InputFormat {
+                path,
+                line: Some(line),
+                message,
+            } => write!(f, "input format error: {}:{}: {}", path, line, message),
+            GcnAggError::InputFormat {
+                path,
+                line: None,
+                message,
+            } => write!(f, "input format error: {}: {}", path, message),
+            GcnAggError::BufferTooSmall {
+                buffer,
+                size,
+                required,
+                layer,
+            } => write!(
+                f,
+                "buffer too small: {} of {} bytes cannot hold {} bytes in layer {}",
+                buffer, size, required, layer
+            ),
+            GcnAggError::Deadlock {
+                cycle,
+                stalled_cycles,
+                waits,
+                snapshot,
+            } => {
+                write!(
+                    f,
+                    "deadlock at cycle {}, no progress for {} cycles: ",
+                    cycle, stalled_cycles
+                )?;
+                match waits.is_empty() {
+                    true => write!(f, "no component is waiting")?,
+                    false => write!(f, "{}", waits.iter().join(", "))?,
+                }
+                match snapshot {
+                    Some(path) => write!(f, ", snapshot: {}", path),
+                    None => Ok(()),
+                }
+            }
+            GcnAggError::InvalidState(message) => write!(f, "invalid state: {}", message),
+            GcnAggError::FunctionalMismatch {
+                layer,
+                node,
+                message,
+            } => write!(
+                f,
+                "functional mismatch: layer {} node {}: {}",
+                layer, node, message
+            ),
+        }
+    }
+}
+
+impl Error for GcnAggError {}
+
+impl From<config::ConfigError> for GcnAggError {
+    fn from(error: config::ConfigError) -> Self {
+        GcnAggError::Config(error.to_string())
+    }
+}
+
+impl From<glob::PatternError> for GcnAggError {
+    fn from(error: glob::PatternError) -> Self {
+        GcnAggError::Config(error.to_string())
+    }
+}
+
+impl From<glob::GlobError> for GcnAggError {
+    fn from(error: glob::GlobError) -> Self {
+        GcnAggError::Config(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let error = GcnAggError::input_format("a.graph", Some(3), "not a number");
+        assert_eq!(
+            error.to_string(),
+            "input format error: a.graph:3: not a number"
+        );
+        let error = GcnAggError::BufferTooSmall {
+            buffer: "input_buffer".into(),
+            size: 16,
+            required: 24,
+            layer: 1,
+        };
+        assert_eq!(
+            error.to_string(),
+            "buffer too small: input_buffer of 16 bytes cannot hold 24 bytes in layer 1"
+        );
+    }
+}
@@ -1,6 +1,9 @@
 //! # mod gcn result
 //! - this mod contains structs for recording the result of gcn simulation.
-use crate::settings::Settings;
+use crate::{
+    accelerator::WindowId,
+    settings::{RunningMode, Settings},
+};
 use serde::Serialize;
 ///
 /// # Description
@@ -8,10 +11,15 @@ use serde::Serialize;
 /// # Fields
 /// - settings: the settings of gcn simulation.`gcn_agg::settings::Settings`
 /// - stats: the statistics
+/// - window_plan: the windows planned by `--dry-run`, `None` if the system is simulated
+/// - layers: the statistics of each gcn layer, `None` if the system is not simulated
 #[derive(Debug, Serialize, Default)]
 pub struct GcnAggResult {
     pub settings: Option<Settings>,
     pub stats: Option<GcnStatistics>,
+    pub layers: Option<Vec<LayerStatistics>>,
+    pub window_plan: Option<WindowPlan>,
+    pub memory_layout: Option<LayoutReport>,
 }
 
 impl GcnAggResult {
@@ -24,10 +32,18 @@ impl GcnAggResult {
 /// # Fields
 /// - simulation_time: the simulation time
 /// - cycle: the number of cycles
+/// - components: the utilization of each component
+/// - memory: the memory traffic from the mem interface
+/// - trace_file: the chrome trace-event json of the pipeline, `None` if the tracer is disabled
+/// - functional: the errors of the functional simulation, `None` if it's disabled
 #[derive(Debug, Serialize, Default)]
 pub struct GcnStatistics {
     pub cycle: u64,
     pub simulation_time: String,
+    pub components: ComponentsStatistics,
+    pub memory: MemoryStatistics,
+    pub trace_file: Option<String>,
+    pub functional: Option<Vec<FunctionalStatistics>>,
 }
 
 impl GcnStatistics {
@@ -35,6 +51,351 @@ impl GcnStatistics {
         GcnStatistics {
             cycle: 0,
             simulation_time: String::new(),
+            components: ComponentsStatistics::default(),
+            memory: MemoryStatistics::default(),
+            trace_file: None,
+            functional: None,
         }
     }
 }
+
+/// # Description
+/// - the result of the functional simulation of one layer, compared with the reference
+/// # Fields
+/// - layer: the gcn layer
+/// - output_dim: the dimension of the output features
+/// - max_error: the max absolute error of all output features
+#[derive(Debug, Serialize, Default, Clone, PartialEq)]
+pub struct FunctionalStatistics {
+    pub layer: usize,
+    pub output_dim: usize,
+    pub max_error: f32,
+}
+
+/// # Description
+/// - the memory traffic of the whole run or of one layer, all requests are 64 bytes
+/// # Fields
+/// - read_requests: the read requests sent to the memory
+/// - write_requests: the write requests sent to the memory
+/// - merged_requests: the read requests merged to an in flight request with the same addr
+/// - returned_reads: the read requests returned from the memory
+/// - total_read_latency: the sum of the latency of all returned reads
+/// - average_read_latency: `total_read_latency / returned_reads`
+#[derive(Debug, Serialize, Default, Clone, PartialEq)]
+pub struct MemoryTraffic {
+    pub read_requests: u64,
+    pub write_requests: u64,
+    pub merged_requests: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub returned_reads: u64,
+    pub total_read_latency: u64,
+    pub average_read_latency: f64,
+}
+
+impl MemoryTraffic {
+    fn update_average(&mut self) {
+        self.average_read_latency = match self.returned_reads {
+            0 => 0.,
+            n => self.total_read_latency as f64 / n as f64,
+        };
+    }
+}
+
+/// # Description
+/// - the memory statistics collected by the mem interface
+/// # Fields
+/// - cycles: the cycles the mem interface runs
+/// - total: the traffic of the whole run
+/// - layers: the traffic of each layer
+/// - send_queue_occupancy: `send_queue_occupancy[n]` is the number of cycles that the send queue holds `n` requests
+/// - average_bandwidth: the bytes transferred per cycle
+/// - bandwidth_utilization: `average_bandwidth` over the peak bandwidth, `None` if the backend has no fixed peak
+/// - stats_file: the statistics file of ramulator, `None` for other backends
+#[derive(Debug, Serialize, Default, Clone, PartialEq)]
+pub struct MemoryStatistics {
+    pub cycles: u64,
+    pub total: MemoryTraffic,
+    pub layers: Vec<MemoryTraffic>,
+    pub send_queue_occupancy: Vec<u64>,
+    pub average_bandwidth: f64,
+    pub bandwidth_utilization: Option<f64>,
+    pub stats_file: Option<String>,
+}
+
+impl MemoryStatistics {
+    /// # Description
+    /// - apply `f` to both the total traffic and the traffic of `layer`
+    pub fn record(&mut self, layer: usize, f: impl Fn(&mut MemoryTraffic)) {
+        if self.layers.len() <= layer {
+            self.layers.resize(layer + 1, MemoryTraffic::default());
+        }
+        f(&mut self.total);
+        f(&mut self.layers[layer]);
+    }
+    /// # Description
+    /// - record the occupancy of the send queue for `cycles` cycles
+    pub fn record_occupancy(&mut self, occupancy: usize, cycles: u64) {
+        if self.send_queue_occupancy.len() <= occupancy {
+            self.send_queue_occupancy.resize(occupancy + 1, 0);
+        }
+        self.send_queue_occupancy[occupancy] += cycles;
+    }
+    /// # Description
+    /// - fill the averages, `peak_bandwidth` is the max bytes per cycle of the memory
+    pub fn update_averages(&mut self, peak_bandwidth: Option<f64>) {
+        self.total.update_average();
+        self.layers.iter_mut().for_each(|x| x.update_average());
+        self.average_bandwidth = match self.cycles {
+            0 => 0.,
+            cycles => (self.total.read_bytes + self.total.write_bytes) as f64 / cycles as f64,
+        };
+        self.bandwidth_utilization = peak_bandwidth.map(|peak| self.average_bandwidth / peak);
+    }
+}
+
+/// # Description
+/// - the statistics of one gcn layer
+/// # Fields
+/// - start_cycle: the cycle that the first window of this layer is added to the input buffer
+/// - end_cycle: the cycle that the last write of this layer is issued, or the last output window of the final layer is finished
+/// - num_output_windows: the number of output windows
+/// - num_input_windows: the number of input windows
+/// - bytes_read: the bytes of the input features read from memory
+/// - bytes_written: the bytes of the output features written to memory
+/// - weight_bytes_read: the bytes of the weights read from memory, including the tiles fetched again
+/// - weight_fetches: the times the weights are fetched, 1 if they fit into the weight buffer
+/// - aggregation_cycles: the busy cycles of the aggregator
+/// - mlp_cycles: the busy cycles of the mlp
+#[derive(Debug, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct LayerStatistics {
+    pub layer: usize,
+    pub start_cycle: u64,
+    pub end_cycle: u64,
+    pub num_output_windows: u64,
+    pub num_input_windows: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub weight_bytes_read: u64,
+    pub weight_fetches: u64,
+    pub aggregation_cycles: u64,
+    pub mlp_cycles: u64,
+}
+
+impl LayerStatistics {
+    pub fn new(layer: usize) -> Self {
+        LayerStatistics {
+            layer,
+            ..Default::default()
+        }
+    }
+    /// # Description
+    /// - the total cycles of this layer, including the overlap with the neighbor layers
+    pub fn get_cycles(&self) -> u64 {
+        self.end_cycle.saturating_sub(self.start_cycle)
+    }
+}
+
+/// # Description
+/// - the reason why a component is stalled
+/// - `Memory`: waiting for the memory
+/// - `Upstream`: waiting for the previous component to provide the data
+/// - `DownstreamFull`: the result cannot be moved because the next buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallCause {
+    Memory,
+    Upstream,
+    DownstreamFull,
+}
+
+/// # Description
+/// - the status of a component in one cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentStatus {
+    Busy,
+    Idle,
+    Stalled(StallCause),
+}
+
+/// # Description
+/// - the number of busy, idle and stalled cycles of a component
+/// - `stall_cycles` is the sum of all `stall_on_*`
+#[derive(Debug, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct ComponentStatistics {
+    pub busy_cycles: u64,
+    pub idle_cycles: u64,
+    pub stall_cycles: u64,
+    pub stall_on_memory: u64,
+    pub stall_on_upstream: u64,
+    pub stall_on_downstream_full: u64,
+}
+
+impl ComponentStatistics {
+    /// # Description
+    /// - record the status for `cycles` cycles
+    pub fn record(&mut self, status: ComponentStatus, cycles: u64) {
+        match status {
+            ComponentStatus::Busy => self.busy_cycles += cycles,
+            ComponentStatus::Idle => self.idle_cycles += cycles,
+            ComponentStatus::Stalled(cause) => {
+                self.stall_cycles += cycles;
+                match cause {
+                    StallCause::Memory => self.stall_on_memory += cycles,
+                    StallCause::Upstream => self.stall_on_upstream += cycles,
+                    StallCause::DownstreamFull => self.stall_on_downstream_full += cycles,
+                }
+            }
+        }
+    }
+    /// # Description
+    /// - the ratio of busy cycles
+    pub fn get_utilization(&self) -> f64 {
+        match self.busy_cycles + self.idle_cycles + self.stall_cycles {
+            0 => 0.,
+            total => self.busy_cycles as f64 / total as f64,
+        }
+    }
+}
+
+/// # Description
+/// - the utilization statistics of all components in the accelerator
+#[derive(Debug, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct ComponentsStatistics {
+    pub aggregator: ComponentStatistics,
+    pub mlp: ComponentStatistics,
+    pub sparsifier: ComponentStatistics,
+    pub input_buffer: ComponentStatistics,
+    pub agg_buffer: ComponentStatistics,
+    pub sparsify_buffer: ComponentStatistics,
+    pub output_buffer: ComponentStatistics,
+    pub weight_buffer: ComponentStatistics,
+    pub mem_interface: ComponentStatistics,
+}
+
+/// # Description
+/// - the windows of every layer built by the window iterators, without running the system
+/// # Fields
+/// - layers: the windows of each layer
+/// - summary: the summary of all layers
+#[derive(Debug, Serialize, Default)]
+pub struct WindowPlan {
+    pub layers: Vec<LayerPlan>,
+    pub summary: PlanSummary,
+}
+
+/// # Description
+/// - the windows of one layer
+#[derive(Debug, Serialize, Default)]
+pub struct LayerPlan {
+    pub layer: usize,
+    pub summary: PlanSummary,
+    pub windows: Vec<WindowRecord>,
+}
+
+/// # Description
+/// - one input window
+/// # Fields
+/// - input_range, output_range: the input nodes and the output nodes of the window, `start..end`
+/// - edges: the edges aggregated by this window
+/// - input_bytes: the bytes of the input nodes, the index and the value of each nonzero(sparse) or each element(dense)
+/// - fill: `input_bytes` over the half of `input_buffer_size`, the other half is loading the next window
+/// - skipped_rows: the empty rows between the previous window of the same output window and this one
+/// - shrunk_rows: the empty rows cut from the end of this window
+/// - unused_rows: the rows inside the window without any edge to the output window, loaded for nothing
+#[derive(Debug, Serialize)]
+pub struct WindowRecord {
+    pub id: WindowId,
+    pub running_mode: RunningMode,
+    pub input_range: (usize, usize),
+    pub output_range: (usize, usize),
+    pub edges: usize,
+    pub input_bytes: u64,
+    pub fill: f64,
+    pub is_last_row: bool,
+    pub skipped_rows: usize,
+    pub shrunk_rows: usize,
+    pub unused_rows: usize,
+}
+
+/// # Description
+/// - the summary of the windows of one layer or all layers
+/// # Fields
+/// - average_fill: the average `fill` of the input windows
+/// - reloaded_bytes: the bytes of the input nodes already loaded by a previous output window of the same layer
+/// - unused_bytes: the bytes of the `unused_rows`
+#[derive(Debug, Serialize, Default)]
+pub struct PlanSummary {
+    pub output_windows: usize,
+    pub input_windows: usize,
+    pub edges: usize,
+    pub input_bytes: u64,
+    pub average_fill: f64,
+    pub reloaded_bytes: u64,
+    pub unused_bytes: u64,
+    pub skipped_rows: usize,
+    pub shrunk_rows: usize,
+}
+
+/// # Description
+/// - the tensors placed by the memory layout
+/// - `Features`: the sparse input features of a layer, written back by the previous layer
+/// - `DenseFeatures`: the dense input features of a layer, read by the dense windows
+/// - `Weights`: the weight matrix of a layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum RegionKind {
+    Features,
+    DenseFeatures,
+    Weights,
+}
+
+/// # Description
+/// - the regions of all tensors in the memory, see `accelerator::MemoryLayout`
+/// # Fields
+/// - channel_stride: the address space of each channel when `channels` is more than 1
+/// - total_bytes: the end of the last region, before the interleaving
+/// - regions: the regions in the address order, the addresses are before the interleaving
+#[derive(Debug, Serialize, Default)]
+pub struct LayoutReport {
+    pub alignment: u64,
+    pub row_padding: bool,
+    pub channels: u64,
+    pub interleave_bytes: u64,
+    pub channel_stride: u64,
+    pub total_bytes: u64,
+    pub regions: Vec<RegionRecord>,
+}
+
+/// # Description
+/// - one region of the memory layout
+/// # Fields
+/// - start, end: the address range of the region, `end` is aligned
+/// - data_bytes: the bytes of the tensor without the row padding and the alignment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RegionRecord {
+    pub layer: usize,
+    pub kind: RegionKind,
+    pub start: u64,
+    pub end: u64,
+    pub data_bytes: u64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_component_statistics() {
+        let mut stat = ComponentStatistics::default();
+        stat.record(ComponentStatus::Busy, 3);
+        stat.record(ComponentStatus::Idle, 1);
+        stat.record(ComponentStatus::Stalled(StallCause::Memory), 2);
+        stat.record(ComponentStatus::Stalled(StallCause::DownstreamFull), 2);
+        assert_eq!(stat.busy_cycles, 3);
+        assert_eq!(stat.idle_cycles, 1);
+        assert_eq!(stat.stall_cycles, 4);
+        assert_eq!(stat.stall_on_memory, 2);
+        assert_eq!(stat.stall_on_upstream, 0);
+        assert_eq!(stat.stall_on_downstream_full, 2);
+        assert_eq!(stat.get_utilization(), 3. / 8.);
+    }
+}
@@ -0,0 +1,394 @@
+//! # Description
+//! - the synthetic graphs built in memory, every generator is deterministic for the same seed
+//! - all graphs are undirected: each edge is added in both directions, there is no self loop
+//! - `from_uri` builds a graph from `graph_path = "gen:<name>?<key>=<value>&..."`:
+//!   - `er`: Erdős–Rényi `G(n, p)`, `n`, `p`(or the average degree `degree`), `seed`
+//!   - `rmat`: R-MAT/Kronecker of `2^scale` nodes and `ef * 2^scale` edges, `scale`, `ef`, `a`, `b`, `c`, `d`, `seed`
+//!   - `ba`: Barabási–Albert, `n` nodes, each new node connects `m` nodes, `seed`
+//!   - `grid`: the 2D mesh of `rows * cols` nodes, each node connects its 4 neighbors
+//!   - `sbm`: the stochastic block model of `blocks` blocks of `size` nodes, `p_in`, `p_out`, `seed`
+//!
+
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::{Edges, Graph};
+use crate::error::GcnAggError;
+
+/// # Description
+/// - the parsed `gen:<name>?<key>=<value>&...` uri, used by the synthetic graphs and node features
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenUri {
+    pub name: String,
+    params: HashMap<String, String>,
+}
+
+impl GenUri {
+    /// # Description
+    /// - parse `uri`, the `gen:` prefix is optional
+    pub fn parse(uri: &str) -> Result<Self, GcnAggError> {
+        let uri = uri.strip_prefix("gen:").unwrap_or(uri);
+        let (name, query) = uri.split_once('?').unwrap_or((uri, ""));
+        if name.is_empty() {
+            return Err(GcnAggError::Config(format!(
+                "no generator name in gen:{}",
+                uri
+            )));
+        }
+        let params = query
+            .split('&')
+            .filter(|x| !x.is_empty())
+            .map(|x| match x.split_once('=') {
+                Some((key, value)) => Ok((key.to_string(), value.to_string())),
+                None => Err(GcnAggError::Config(format!(
+                    "the parameter {} of gen:{} should be like key=value",
+                    x, uri
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(GenUri {
+            name: name.to_string(),
+            params,
+        })
+    }
+
+    /// # Description
+    /// - the parameter `key`, or `default` if not provided
+    pub fn get_or<T>(&self, key: &str, default: T) -> Result<T, GcnAggError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.params.contains_key(key) {
+            true => self.get(key),
+            false => Ok(default),
+        }
+    }
+
+    /// # Description
+    /// - the required parameter `key`
+    pub fn get<T>(&self, key: &str) -> Result<T, GcnAggError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.params.get(key) {
+            Some(value) => value.parse().map_err(|e| {
+                GcnAggError::Config(format!(
+                    "the parameter {}={} of gen:{}: {}",
+                    key, value, self.name, e
+                ))
+            }),
+            None => Err(GcnAggError::Config(format!(
+                "gen:{} needs the parameter {}",
+                self.name, key
+            ))),
+        }
+    }
+
+    /// # Description
+    /// - the seed of the generator, 0 if not provided
+    pub fn get_seed(&self) -> Result<u64, GcnAggError> {
+        self.get_or("seed", 0)
+    }
+}
+
+/// # Description
+/// - build the graph described by `uri`, e.g. `gen:rmat?scale=20&ef=16&seed=1`, see the module doc
+pub fn from_uri(uri: &str, feature_size: usize) -> Result<Graph, GcnAggError> {
+    let uri = GenUri::parse(uri)?;
+    match uri.name.as_str() {
+        "er" | "erdos_renyi" => {
+            let n: usize = uri.get("n")?;
+            let p = match uri.get_or("degree", -1.)? {
+                degree if degree >= 0. => degree / n.max(2).saturating_sub(1) as f64,
+                _ => uri.get("p")?,
+            };
+            erdos_renyi(n, p, uri.get_seed()?, feature_size)
+        }
+        "rmat" | "kronecker" => {
+            let a = uri.get_or("a", 0.57)?;
+            let b = uri.get_or("b", 0.19)?;
+            let c = uri.get_or("c", 0.19)?;
+            let d = uri.get_or("d", 1. - a - b - c)?;
+            rmat(
+                uri.get("scale")?,
+                uri.get_or("ef", 16)?,
+                [a, b, c, d],
+                uri.get_seed()?,
+                feature_size,
+            )
+        }
+        "ba" | "barabasi_albert" => {
+            barabasi_albert(uri.get("n")?, uri.get("m")?, uri.get_seed()?, feature_size)
+        }
+        "grid" | "mesh" => grid(uri.get("rows")?, uri.get("cols")?, feature_size),
+        "sbm" => stochastic_block_model(
+            &vec![uri.get("size")?; uri.get("blocks")?],
+            uri.get("p_in")?,
+            uri.get("p_out")?,
+            uri.get_seed()?,
+            feature_size,
+        ),
+        name => Err(GcnAggError::Config(format!(
+            "unknown graph generator {}",
+            name
+        ))),
+    }
+}
+
+/// # Description
+/// - the probability should be in [0, 1]
+fn check_probability(name: &str, p: f64) -> Result<(), GcnAggError> {
+    match (0. ..=1.).contains(&p) {
+        true => Ok(()),
+        false => Err(GcnAggError::Config(format!(
+            "the probability {} should be in [0, 1], got {}",
+            name, p
+        ))),
+    }
+}
+
+/// # Description
+/// - call `f` with each index in `0..len` that is chosen with the probability `p`
+/// - the gaps between the chosen indices are geometric, so the time is linear to the chosen ones
+fn sample_indices(rng: &mut impl Rng, len: usize, p: f64, mut f: impl FnMut(usize)) {
+    if p <= 0. {
+        return;
+    }
+    if p >= 1. {
+        (0..len).for_each(f);
+        return;
+    }
+    let log_q = (1. - p).ln();
+    let mut index = 0usize;
+    loop {
+        let r: f64 = rng.gen();
+        let skip = ((1. - r).ln() / log_q).floor();
+        if skip >= (len - index) as f64 {
+            return;
+        }
+        index += skip as usize;
+        f(index);
+        index += 1;
+    }
+}
+
+fn add_undirected(edges: &mut Edges, u: usize, v: usize) {
+    if u != v {
+        edges.add(u as u32, v as u32);
+        edges.add(v as u32, u as u32);
+    }
+}
+
+/// # Description
+/// - the Erdős–Rényi graph: each pair of the `n` nodes is connected with the probability `p`
+pub fn erdos_renyi(n: usize, p: f64, seed: u64, feature_size: usize) -> Result<Graph, GcnAggError> {
+    check_probability("p", p)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut edges = Edges::default();
+    for v in 1..n {
+        sample_indices(&mut rng, v, p, |u| add_undirected(&mut edges, u, v));
+    }
+    edges.build("gen:er", Some(n), feature_size, true)
+}
+
+/// # Description
+/// - the R-MAT graph of `2^scale` nodes, `edge_factor * 2^scale` edges are drawn
+/// - each edge picks one quadrant of the adjacency matrix by `probabilities`(a, b, c, d) at each of the `scale` levels
+/// - the self loops and the duplicated edges are dropped, so there are fewer edges than drawn
+pub fn rmat(
+    scale: u32,
+    edge_factor: usize,
+    probabilities: [f64; 4],
+    seed: u64,
+    feature_size: usize,
+) -> Result<Graph, GcnAggError> {
+    for (name, p) in ["a", "b", "c", "d"].iter().zip(probabilities) {
+        check_probability(name, p)?;
+    }
+    if (probabilities.iter().sum::<f64>() - 1.).abs() > 1e-6 {
+        return Err(GcnAggError::Config(format!(
+            "a + b + c + d should be 1, got {:?}",
+            probabilities
+        )));
+    }
+    if scale >= 32 {
+        return Err(GcnAggError::Config(format!(
+            "the scale {} is too large, at most 31",
+            scale
+        )));
+    }
+    let [a, b, c, _] = probabilities;
+    let n = 1usize << scale;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut edges = Edges::default();
+    for _ in 0..edge_factor * n {
+        let (mut row, mut col) = (0, 0);
+        for level in (0..scale).rev() {
+            let r: f64 = rng.gen();
+            let (down, right) = match r {
+                r if r < a => (0, 0),
+                r if r < a + b => (0, 1),
+                r if r < a + b + c => (1, 0),
+                _ => (1, 1),
+            };
+            row |= down << level;
+            col |= right << level;
+        }
+        add_undirected(&mut edges, row, col);
+    }
+    edges.build("gen:rmat", Some(n), feature_size, true)
+}
+
+/// # Description
+/// - the Barabási–Albert graph: start from `m` nodes, each new node connects `m` distinct nodes,
+///   chosen with the probability proportional to their degrees
+pub fn barabasi_albert(
+    n: usize,
+    m: usize,
+    seed: u64,
+    feature_size: usize,
+) -> Result<Graph, GcnAggError> {
+    if m == 0 || m >= n {
+        return Err(GcnAggError::Config(format!(
+            "the barabasi albert graph needs 0 < m < n, got n = {}, m = {}",
+            n, m
+        )));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut edges = Edges::default();
+    // each node appears once for each of its edges, so a uniform pick is proportional to the degree
+    let mut ends: Vec<usize> = vec![];
+    let mut targets: Vec<usize> = (0..m).collect();
+    for v in m..n {
+        for &u in &targets {
+            add_undirected(&mut edges, u, v);
+            ends.push(u);
+            ends.push(v);
+        }
+        targets.clear();
+        while targets.len() < m {
+            let u = ends[rng.gen_range(0..ends.len())];
+            if !targets.contains(&u) {
+                targets.push(u);
+            }
+        }
+    }
+    edges.build("gen:ba", Some(n), feature_size, true)
+}
+
+/// # Description
+/// - the 2D mesh of `rows * cols` nodes, node `r * cols + c` connects its up, down, left and right neighbors
+pub fn grid(rows: usize, cols: usize, feature_size: usize) -> Result<Graph, GcnAggError> {
+    let mut edges = Edges::default();
+    for r in 0..rows {
+        for c in 0..cols {
+            let v = r * cols + c;
+            if c + 1 < cols {
+                add_undirected(&mut edges, v, v + 1);
+            }
+            if r + 1 < rows {
+                add_undirected(&mut edges, v, v + cols);
+            }
+        }
+    }
+    edges.build("gen:grid", Some(rows * cols), feature_size, true)
+}
+
+/// # Description
+/// - the stochastic block model, the nodes are split into consecutive blocks of `block_sizes`
+/// - two nodes are connected with the probability `p_in` in the same block, or `p_out` otherwise
+pub fn stochastic_block_model(
+    block_sizes: &[usize],
+    p_in: f64,
+    p_out: f64,
+    seed: u64,
+    feature_size: usize,
+) -> Result<Graph, GcnAggError> {
+    check_probability("p_in", p_in)?;
+    check_probability("p_out", p_out)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut edges = Edges::default();
+    let mut block_start = 0;
+    for &size in block_sizes {
+        for v in block_start..block_start + size {
+            // the nodes before this block, then the nodes before v in this block
+            sample_indices(&mut rng, block_start, p_out, |u| {
+                add_undirected(&mut edges, u, v)
+            });
+            sample_indices(&mut rng, v - block_start, p_in, |u| {
+                add_undirected(&mut edges, block_start + u, v)
+            });
+        }
+        block_start += size;
+    }
+    edges.build("gen:sbm", Some(block_start), feature_size, true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn is_symmetric(graph: &Graph) -> bool {
+        (0..graph.get_num_node()).all(|i| graph.get_csc_row(i) == graph.get_csr_row(i))
+    }
+
+    #[test]
+    fn test_gen_uri() -> Result<(), GcnAggError> {
+        let uri = GenUri::parse("gen:rmat?scale=20&ef=16&seed=1")?;
+        assert_eq!(uri.name, "rmat");
+        assert_eq!(uri.get::<u32>("scale")?, 20);
+        assert_eq!(uri.get_or("a", 0.5)?, 0.5);
+        assert_eq!(uri.get_seed()?, 1);
+        assert!(uri.get::<usize>("n").is_err());
+        assert!(GenUri::parse("gen:rmat?scale").is_err());
+        assert!(from_uri("gen:rmat?scale=x", 4).is_err());
+        assert!(from_uri("gen:unknown", 4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generators() -> Result<(), GcnAggError> {
+        let graph = from_uri("gen:er?n=200&p=0.05&seed=3", 8)?;
+        assert_eq!(graph.get_num_node(), 200);
+        assert_eq!(graph.get_feature_size(), 8);
+        assert!(is_symmetric(&graph));
+        // about p * n * (n - 1) directed edges
+        let expected = 0.05 * 200. * 199.;
+        assert!((graph.get_num_edges() as f64 - expected).abs() < expected * 0.2);
+        // the same seed builds the same graph
+        assert_eq!(graph, from_uri("gen:er?n=200&p=0.05&seed=3", 8)?);
+        assert_ne!(graph, from_uri("gen:er?n=200&p=0.05&seed=4", 8)?);
+        assert_eq!(from_uri("gen:er?n=10&p=1", 8)?.get_num_edges(), 90);
+
+        let graph = from_uri("gen:rmat?scale=8&ef=4&seed=1", 8)?;
+        assert_eq!(graph.get_num_node(), 256);
+        assert!(is_symmetric(&graph));
+        assert!(graph.get_num_edges() <= 2 * 4 * 256);
+        // the skewed quadrants make a skewed degree
+        let max_degree = (0..256).map(|i| graph.get_csc_row(i).len()).max().unwrap();
+        assert!(max_degree > 4 * graph.get_num_edges() / 256);
+        assert!(from_uri("gen:rmat?scale=4&a=0.5&b=0.5&c=0.5", 8).is_err());
+
+        let graph = barabasi_albert(100, 3, 1, 8)?;
+        assert!(is_symmetric(&graph));
+        assert_eq!(graph.get_num_edges(), 2 * 3 * 97);
+        assert!((3..100).all(|i| graph.get_csc_row(i).len() >= 3));
+
+        let graph = grid(3, 4, 8)?;
+        assert_eq!(graph.get_num_node(), 12);
+        assert_eq!(graph.get_csc_row(0), &[1, 4]);
+        assert_eq!(graph.get_csc_row(5), &[1, 4, 6, 9]);
+        // 3 * 3 horizontal and 2 * 4 vertical edges
+        assert_eq!(graph.get_num_edges(), 2 * (9 + 8));
+
+        let graph = from_uri("gen:sbm?blocks=4&size=25&p_in=1&p_out=0&seed=2", 8)?;
+        assert_eq!(graph.get_num_node(), 100);
+        assert!(is_symmetric(&graph));
+        assert_eq!(graph.get_num_edges(), 4 * 25 * 24);
+        assert!(graph.get_csc_row(30).iter().all(|&x| (25..50).contains(&x)));
+        Ok(())
+    }
+}
@@ -0,0 +1,832 @@
+pub mod generators;
+pub mod preprocess;
+pub mod reorder;
+
+use log::info;
+
+use self::preprocess::DegreeData;
+use crate::{
+    binary::{self, BinaryReader, BinaryWriter},
+    error::GcnAggError,
+    settings::{GraphFormat, Settings},
+};
+
+// build the structure of the graph
+// the input nodes of node `i` are `csc_indices[csc_offsets[i]..csc_offsets[i + 1]]`,
+// the output nodes of node `i` are `csr_indices[csr_offsets[i]..csr_offsets[i + 1]]`, both are sorted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph {
+    csc_offsets: Vec<u32>,
+    csc_indices: Vec<u32>,
+    csr_offsets: Vec<u32>,
+    csr_indices: Vec<u32>,
+    // the feature size
+    feature_size: usize,
+    // the number of nodes
+    pub total_nodes: usize,
+    // the degree of each node, attached by `preprocess::apply` for the normalization
+    degree_data: Option<DegreeData>,
+}
+impl Graph {
+    /// read the graph from the file
+    /// # Arguments
+    /// * `file_name` - the path of the file
+    /// # Return
+    /// * `Graph` - the graph
+    ///
+    /// the file format is:
+    /// f feature_size
+    /// 0 1 2
+    /// 1 2 0
+    /// 2 0 1
+    /// end
+    ///
+    /// the first line is the feature size
+    /// the following lines are the edges
+    /// the last line is end or END
+    ///
+    /// the duplicate edges are kept, they are merged by `preprocess::apply`
+    /// # Examples
+    /// ```ignore
+    /// use gcn_agg::graph::Graph;
+    /// use std::{fs::File, io::{Read,Write}};
+    /// let file_name="test_data/graph.txt";
+    /// // write the graph to the file
+    /// let data="f 3\n0 1 2\n1 2 0\n2 0 1\nend\n";
+    /// let mut f = File::create(file_name).expect("file not found");
+    /// f.write_all(data.as_bytes())
+    ///     .expect("something went wrong writing the file");
+    /// // read the graph from the file
+    /// let graph = Graph::from("test_data/graph.txt");
+    /// assert_eq!(graph.get_feature_size(), 3);
+    /// assert_eq!(graph.get_csc_row(0), &[0, 1, 2]);
+    /// assert_eq!(graph.get_csc_row(1), &[0, 1, 2]);
+    /// assert_eq!(graph.get_csc_row(2), &[0, 1, 2]);
+    /// // delete the file
+    /// std::fs::remove_file(file_name).expect("failed to delete the file");
+    /// ```
+    ///
+    pub fn new(file_name: &str) -> Result<Self, GcnAggError> {
+        let contents =
+            std::fs::read_to_string(file_name).map_err(|e| GcnAggError::io(file_name, e))?;
+        let mut lines = contents.lines();
+        // the first line should be like "f {feature_size}"
+        let first_line = lines
+            .next()
+            .ok_or_else(|| GcnAggError::input_format(file_name, None, "empty graph file"))?;
+        let mut iter = first_line.split_whitespace();
+        let feature_size = match (iter.next(), iter.next()) {
+            (Some("f"), Some(size)) => size
+                .parse::<usize>()
+                .map_err(|e| GcnAggError::input_format(file_name, Some(1), e))?,
+            _ => {
+                return Err(GcnAggError::input_format(
+                    file_name,
+                    Some(1),
+                    "the first line should be like \"f feature_size\"",
+                ))
+            }
+        };
+
+        // the remaining lines should be like list of edges in csc format
+        // from next line to the second last row, will contain the row index of the edges
+        let mut edges = Edges::default();
+        let mut num_nodes = 0;
+        for (line_number, line) in lines.enumerate() {
+            // test if the line start with END or end
+            if line.starts_with("END") || line.starts_with("end") {
+                break;
+            }
+            // each number of line `num_nodes` is an input node of node `num_nodes`
+            for i in line.split_whitespace() {
+                let src = i
+                    .parse::<u32>()
+                    .map_err(|e| GcnAggError::input_format(file_name, Some(line_number + 2), e))?;
+                edges.add(src, num_nodes);
+            }
+            num_nodes += 1;
+        }
+        edges.build(file_name, Some(num_nodes as usize), feature_size, false)
+    }
+
+    /// # Description
+    /// - read the graph of `settings.graph_path` in the format of `settings.get_graph_format()`
+    /// - the parsed graph is cached in `settings.cache_dir` if set
+    /// - the graph path `gen:<name>?<params>` builds a synthetic graph, see `generators`
+    /// - the loaded graph is changed by `settings.preprocess`, see `preprocess`
+    pub fn load(settings: &Settings) -> Result<Self, GcnAggError> {
+        let graph = Graph::load_raw(settings)?;
+        preprocess::apply(&settings.preprocess, graph)
+    }
+
+    /// # Description
+    /// - the graph in the file or generated, before the preprocessing
+    fn load_raw(settings: &Settings) -> Result<Self, GcnAggError> {
+        if settings.graph_path.starts_with("gen:") {
+            let feature_size = settings.feature_size.ok_or_else(|| {
+                GcnAggError::Config("the generated graph needs the feature_size".into())
+            })?;
+            return generators::from_uri(&settings.graph_path, feature_size);
+        }
+        let format = settings.get_graph_format();
+        let parse = |path: &str| Graph::from_file(path, format, settings.feature_size);
+        match &settings.cache_dir {
+            Some(cache_dir) => binary::load_cached(
+                cache_dir,
+                &settings.graph_path,
+                &format!(
+                    "graph {:?} {:?} with duplicates",
+                    format, settings.feature_size
+                ),
+                Graph::load_binary,
+                parse,
+                Graph::save_binary,
+            ),
+            None => parse(&settings.graph_path),
+        }
+    }
+
+    /// # Description
+    /// - write the graph as the binary file, see `crate::binary`
+    /// - the payload is the feature size(u64), the number of nodes(u64), the number of edges(u64),
+    ///   the offsets of the input nodes of each node(u64 * (nodes + 1)) and the input nodes(u32 * edges)
+    pub fn save_binary(&self, file_name: &str) -> Result<(), GcnAggError> {
+        let mut writer = BinaryWriter::new(GRAPH_MAGIC);
+        writer.write_u64(self.feature_size as u64);
+        writer.write_u64(self.get_num_node() as u64);
+        writer.write_u64(self.get_num_edges() as u64);
+        for &offset in &self.csc_offsets {
+            writer.write_u64(offset as u64);
+        }
+        for &index in &self.csc_indices {
+            writer.write_u32(index);
+        }
+        writer.write(file_name)
+    }
+
+    /// # Description
+    /// - read the graph from the binary file written by `save_binary`
+    pub fn load_binary(file_name: &str) -> Result<Self, GcnAggError> {
+        let mut reader = BinaryReader::open(file_name, GRAPH_MAGIC)?;
+        let feature_size = reader.read_len()?;
+        let num_nodes = reader.read_len()?;
+        let num_edges = reader.read_len()?;
+        let offsets = reader.read_offsets(num_nodes)?;
+        if offsets[num_nodes] != num_edges {
+            return Err(reader.error("the last offset is not the number of edges"));
+        }
+        let offsets = offsets
+            .into_iter()
+            .map(|x| u32::try_from(x).map_err(|e| reader.error(e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let indices = (0..num_edges)
+            .map(|_| reader.read_u32())
+            .collect::<Result<Vec<_>, _>>()?;
+        reader.finish()?;
+        Graph::from_csc(file_name, offsets, indices, feature_size)
+    }
+
+    /// # Description
+    /// - read the graph in `format` from `file_name`
+    /// - `feature_size` is required by all formats but `Native`, `Native` reads it from the file
+    /// - the duplicate edges are kept, they are merged by `preprocess::apply`
+    pub fn from_file(
+        file_name: &str,
+        format: GraphFormat,
+        feature_size: Option<usize>,
+    ) -> Result<Self, GcnAggError> {
+        if format == GraphFormat::Native {
+            return Graph::new(file_name);
+        }
+        let feature_size = feature_size.ok_or_else(|| {
+            GcnAggError::Config(format!(
+                "the {:?} graph {} needs the feature_size",
+                format, file_name
+            ))
+        })?;
+        let contents =
+            std::fs::read_to_string(file_name).map_err(|e| GcnAggError::io(file_name, e))?;
+        let (edges, num_nodes) = match format {
+            GraphFormat::EdgeList => (parse_edge_list(file_name, &contents)?, None),
+            GraphFormat::MatrixMarket => {
+                let (edges, num_nodes) = parse_matrix_market(file_name, &contents)?;
+                (edges, Some(num_nodes))
+            }
+            GraphFormat::Metis => {
+                let (edges, num_nodes) = parse_metis(file_name, &contents)?;
+                (edges, Some(num_nodes))
+            }
+            GraphFormat::Native => unreachable!(),
+        };
+        edges.build(file_name, num_nodes, feature_size, false)
+    }
+
+    /// # Description
+    /// - build the graph from the input nodes of each node, `rows[i]` is the same as the line of node `i` in the
+    ///   native graph file
+    pub fn from_rows(rows: &[Vec<u32>], feature_size: usize) -> Result<Self, GcnAggError> {
+        let mut edges = Edges::default();
+        for (dst, row) in rows.iter().enumerate() {
+            for &src in row {
+                edges.add(src, dst as u32);
+            }
+        }
+        edges.build("rows", Some(rows.len()), feature_size, false)
+    }
+
+    /// # Description
+    /// - build the graph from the input nodes of each node, the rows should be sorted
+    /// - `file_name` is only used in the error message
+    fn from_csc(
+        file_name: &str,
+        csc_offsets: Vec<u32>,
+        csc_indices: Vec<u32>,
+        feature_size: usize,
+    ) -> Result<Self, GcnAggError> {
+        let num_nodes = csc_offsets.len() - 1;
+        for (node, row) in csc_offsets.windows(2).enumerate() {
+            let row = &csc_indices[row[0] as usize..row[1] as usize];
+            if row.windows(2).any(|x| x[0] > x[1]) {
+                return Err(GcnAggError::input_format(
+                    file_name,
+                    None,
+                    format!("the input nodes of node {} are not sorted", node),
+                ));
+            }
+            if let Some(&max_index) = row.last().filter(|&&x| x as usize >= num_nodes) {
+                return Err(GcnAggError::input_format(
+                    file_name,
+                    None,
+                    format!(
+                        "the node index {} is out of range, there are {} nodes",
+                        max_index, num_nodes
+                    ),
+                ));
+            }
+        }
+
+        // build csr from csc, the rows of csc are visited in order, so each csr row is sorted
+        let mut csr_offsets = vec![0u32; num_nodes + 1];
+        for &src in &csc_indices {
+            csr_offsets[src as usize + 1] += 1;
+        }
+        for i in 0..num_nodes {
+            csr_offsets[i + 1] += csr_offsets[i];
+        }
+        let mut next = csr_offsets.clone();
+        let mut csr_indices = vec![0u32; csc_indices.len()];
+        for (dst, row) in csc_offsets.windows(2).enumerate() {
+            for &src in &csc_indices[row[0] as usize..row[1] as usize] {
+                csr_indices[next[src as usize] as usize] = dst as u32;
+                next[src as usize] += 1;
+            }
+        }
+
+        Ok(Graph {
+            csc_offsets,
+            csc_indices,
+            csr_offsets,
+            csr_indices,
+            feature_size,
+            total_nodes: num_nodes,
+            degree_data: None,
+        })
+    }
+}
+
+/// # Description
+/// - the edges read by the parsers, `(dst, src)` means node `dst` aggregates node `src`
+#[derive(Debug, Default)]
+struct Edges {
+    edges: Vec<(u32, u32)>,
+    max_node: Option<u32>,
+}
+
+impl Edges {
+    fn add(&mut self, src: u32, dst: u32) {
+        self.edges.push((dst, src));
+        self.max_node = self.max_node.max(Some(src.max(dst)));
+    }
+
+    /// # Description
+    /// - sort the edges into the graph of `num_nodes` nodes, or the max node id plus 1 if `None`
+    /// - the duplicate edges are merged if `dedup`
+    fn build(
+        mut self,
+        file_name: &str,
+        num_nodes: Option<usize>,
+        feature_size: usize,
+        dedup: bool,
+    ) -> Result<Graph, GcnAggError> {
+        let num_nodes = num_nodes.unwrap_or_else(|| self.max_node.map_or(0, |x| x as usize + 1));
+        if let Some(max_node) = self.max_node.filter(|&x| x as usize >= num_nodes) {
+            return Err(GcnAggError::input_format(
+                file_name,
+                None,
+                format!(
+                    "the node index {} is out of range, there are {} nodes",
+                    max_node, num_nodes
+                ),
+            ));
+        }
+        self.edges.sort_unstable();
+        let num_edges = self.edges.len();
+        if dedup {
+            self.edges.dedup();
+        }
+        if self.edges.len() != num_edges {
+            info!(
+                "{}: {} duplicate edges are merged",
+                file_name,
+                num_edges - self.edges.len()
+            );
+        }
+        if num_nodes >= u32::MAX as usize || self.edges.len() > u32::MAX as usize {
+            return Err(GcnAggError::input_format(
+                file_name,
+                None,
+                format!(
+                    "{} nodes and {} edges cannot be indexed by u32",
+                    num_nodes,
+                    self.edges.len()
+                ),
+            ));
+        }
+        let mut csc_offsets = vec![0u32; num_nodes + 1];
+        for &(dst, _) in &self.edges {
+            csc_offsets[dst as usize + 1] += 1;
+        }
+        for i in 0..num_nodes {
+            csc_offsets[i + 1] += csc_offsets[i];
+        }
+        let csc_indices = self.edges.into_iter().map(|(_, src)| src).collect();
+        Graph::from_csc(file_name, csc_offsets, csc_indices, feature_size)
+    }
+}
+
+/// the magic of the binary graph file
+const GRAPH_MAGIC: &[u8; 8] = b"GCNGRAPH";
+
+/// # Description
+/// - parse `token` in line `line_number`(starts from 1) of `file_name` as a node index
+fn parse_index(file_name: &str, line_number: usize, token: &str) -> Result<usize, GcnAggError> {
+    token.parse::<usize>().map_err(|e| {
+        GcnAggError::input_format(file_name, Some(line_number), format!("{}: {}", token, e))
+    })
+}
+
+/// # Description
+/// - parse `token` in line `line_number`(starts from 1) of `file_name` as a node id
+fn parse_node(file_name: &str, line_number: usize, token: &str) -> Result<u32, GcnAggError> {
+    token.parse::<u32>().map_err(|e| {
+        GcnAggError::input_format(file_name, Some(line_number), format!("{}: {}", token, e))
+    })
+}
+
+/// # Description
+/// - the edge list: each line is `src dst`, separated by spaces, tabs or a comma, the following columns are ignored
+/// - the empty lines and the lines start with `#` or `%` are skipped
+/// - the number of nodes is the max node id plus 1
+fn parse_edge_list(file_name: &str, contents: &str) -> Result<Edges, GcnAggError> {
+    let mut edges = Edges::default();
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('%') {
+            continue;
+        }
+        let mut iter = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|x| !x.is_empty());
+        match (iter.next(), iter.next()) {
+            (Some(src), Some(dst)) => {
+                let src = parse_node(file_name, line_number + 1, src)?;
+                let dst = parse_node(file_name, line_number + 1, dst)?;
+                edges.add(src, dst);
+            }
+            _ => {
+                return Err(GcnAggError::input_format(
+                    file_name,
+                    Some(line_number + 1),
+                    "the edge should be like \"src dst\"",
+                ))
+            }
+        }
+    }
+    Ok(edges)
+}
+
+/// # Description
+/// - the matrix market coordinate file: the header, the comments start with `%`, the size line `rows cols entries`,
+///   then one `row col [value]` entry each line, the ids start from 1 and the values are ignored
+/// - the entry `row col` is the edge from `row` to `col`, the symmetric matrices also have the edge from `col` to `row`
+/// - return the edges and the number of nodes, the max of `rows` and `cols`
+fn parse_matrix_market(file_name: &str, contents: &str) -> Result<(Edges, usize), GcnAggError> {
+    let mut lines = contents.lines().enumerate();
+    let header = lines
+        .next()
+        .map(|(_, line)| line.to_lowercase())
+        .ok_or_else(|| GcnAggError::input_format(file_name, None, "empty matrix market file"))?;
+    let header = header.split_whitespace().collect::<Vec<_>>();
+    let symmetric = match header[..] {
+        ["%%matrixmarket", "matrix", "coordinate", _, "general"] => false,
+        ["%%matrixmarket", "matrix", "coordinate", _, "symmetric" | "skew-symmetric" | "hermitian"] => {
+            true
+        }
+        _ => {
+            return Err(GcnAggError::input_format(
+                file_name,
+                Some(1),
+                "the header should be like \"%%MatrixMarket matrix coordinate <field> <symmetry>\"",
+            ))
+        }
+    };
+    let mut lines = lines.filter(|(_, line)| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with('%')
+    });
+
+    let (line_number, size_line) = lines
+        .next()
+        .ok_or_else(|| GcnAggError::input_format(file_name, None, "no size line"))?;
+    let sizes = size_line
+        .split_whitespace()
+        .map(|x| parse_index(file_name, line_number + 1, x))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (rows, cols, entries) = match sizes[..] {
+        [rows, cols, entries] => (rows, cols, entries),
+        _ => {
+            return Err(GcnAggError::input_format(
+                file_name,
+                Some(line_number + 1),
+                "the size line should be like \"rows cols entries\"",
+            ))
+        }
+    };
+
+    let mut edges = Edges::default();
+    let mut num_entries = 0;
+    for (line_number, line) in lines {
+        let mut iter = line.split_whitespace();
+        let (row, col) = match (iter.next(), iter.next()) {
+            (Some(row), Some(col)) => (
+                parse_node(file_name, line_number + 1, row)?,
+                parse_node(file_name, line_number + 1, col)?,
+            ),
+            _ => {
+                return Err(GcnAggError::input_format(
+                    file_name,
+                    Some(line_number + 1),
+                    "the entry should be like \"row col [value]\"",
+                ))
+            }
+        };
+        if row == 0 || row as usize > rows || col == 0 || col as usize > cols {
+            return Err(GcnAggError::input_format(
+                file_name,
+                Some(line_number + 1),
+                format!(
+                    "the entry ({}, {}) is out of the {}x{} matrix",
+                    row, col, rows, cols
+                ),
+            ));
+        }
+        edges.add(row - 1, col - 1);
+        if symmetric {
+            edges.add(col - 1, row - 1);
+        }
+        num_entries += 1;
+    }
+    if num_entries != entries {
+        return Err(GcnAggError::input_format(
+            file_name,
+            None,
+            format!("expect {} entries, found {}", entries, num_entries),
+        ));
+    }
+    Ok((edges, rows.max(cols)))
+}
+
+/// # Description
+/// - the METIS graph file: the comments start with `%`, the header `nodes edges [fmt [ncon]]`,
+///   then line `i` lists the neighbors of node `i`(an empty line is a node without neighbors), the ids start from 1
+/// - `fmt` is 3 flags: the node sizes, the node weights(`ncon` of them) and the edge weights, the weights are ignored
+/// - each undirected edge is listed by both of its nodes, so there should be `2 * edges` neighbors in total
+/// - return the edges and the number of nodes
+fn parse_metis(file_name: &str, contents: &str) -> Result<(Edges, usize), GcnAggError> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim_start().starts_with('%'));
+    let (line_number, header) = lines
+        .next()
+        .ok_or_else(|| GcnAggError::input_format(file_name, None, "empty metis file"))?;
+    let header = header.split_whitespace().collect::<Vec<_>>();
+    let (num_nodes, num_edges, fmt, ncon) = match header[..] {
+        [nodes, edges] => (nodes, edges, "0", "1"),
+        [nodes, edges, fmt] => (nodes, edges, fmt, "1"),
+        [nodes, edges, fmt, ncon] => (nodes, edges, fmt, ncon),
+        _ => {
+            return Err(GcnAggError::input_format(
+                file_name,
+                Some(line_number + 1),
+                "the header should be like \"nodes edges [fmt [ncon]]\"",
+            ))
+        }
+    };
+    let num_nodes = parse_index(file_name, line_number + 1, num_nodes)?;
+    let num_edges = parse_index(file_name, line_number + 1, num_edges)?;
+    let ncon = parse_index(file_name, line_number + 1, ncon)?;
+    let flags = format!("{:0>3}", fmt);
+    if flags.len() != 3 || flags.chars().any(|x| x != '0' && x != '1') {
+        return Err(GcnAggError::input_format(
+            file_name,
+            Some(line_number + 1),
+            format!("unknown fmt {}", fmt),
+        ));
+    }
+    let flags = flags.as_bytes();
+    // the values before the neighbors and after each neighbor
+    let skipped = (flags[0] == b'1') as usize + (flags[1] == b'1') as usize * ncon;
+    let step = 1 + (flags[2] == b'1') as usize;
+
+    let mut edges = Edges::default();
+    let mut num_neighbors = 0;
+    for (node, (line_number, line)) in lines.enumerate() {
+        if node >= num_nodes {
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Err(GcnAggError::input_format(
+                file_name,
+                Some(line_number + 1),
+                format!("there are more than {} nodes", num_nodes),
+            ));
+        }
+        for neighbor in line.split_whitespace().skip(skipped).step_by(step) {
+            let neighbor = parse_node(file_name, line_number + 1, neighbor)?;
+            if neighbor == 0 || neighbor as usize > num_nodes {
+                return Err(GcnAggError::input_format(
+                    file_name,
+                    Some(line_number + 1),
+                    format!("the node {} is out of 1..={}", neighbor, num_nodes),
+                ));
+            }
+            edges.add(neighbor - 1, node as u32);
+            num_neighbors += 1;
+        }
+    }
+    if num_neighbors != 2 * num_edges {
+        return Err(GcnAggError::input_format(
+            file_name,
+            None,
+            format!(
+                "expect {} neighbors for {} edges, found {}",
+                2 * num_edges,
+                num_edges,
+                num_neighbors
+            ),
+        ));
+    }
+    Ok((edges, num_nodes))
+}
+
+impl Graph {
+    pub fn get_feature_size(&self) -> usize {
+        self.feature_size
+    }
+    /// # Description
+    /// - the input nodes of node `i`, sorted
+    pub fn get_csc_row(&self, i: usize) -> &[u32] {
+        &self.csc_indices[self.csc_offsets[i] as usize..self.csc_offsets[i + 1] as usize]
+    }
+    /// # Description
+    /// - the output nodes of node `i`, sorted
+    pub fn get_csr_row(&self, i: usize) -> &[u32] {
+        &self.csr_indices[self.csr_offsets[i] as usize..self.csr_offsets[i + 1] as usize]
+    }
+    /// # Description
+    /// - the input nodes of node `i` in `start..end`
+    pub fn get_csc_row_range(&self, i: usize, start: usize, end: usize) -> &[u32] {
+        slice_range(self.get_csc_row(i), start, end)
+    }
+    /// # Description
+    /// test if a row is empty from col start to col end, for index i
+    pub fn is_row_range_empty(&self, i: usize, start: usize, end: usize) -> bool {
+        slice_range(self.get_csr_row(i), start, end).is_empty()
+    }
+    pub fn get_num_node(&self) -> usize {
+        self.csc_offsets.len() - 1
+    }
+    pub fn get_num_edges(&self) -> usize {
+        self.csc_indices.len()
+    }
+    /// # Description
+    /// - the degree data for the normalization, `None` if the aggregation is not normalized
+    pub fn get_degree_data(&self) -> Option<&DegreeData> {
+        self.degree_data.as_ref()
+    }
+}
+
+impl Graph {
+    /// # Description
+    /// - renumber the nodes, the new node `i` is the old node `order[i]`, see `reorder`
+    pub fn permute(&self, order: &[u32]) -> Graph {
+        let new_ids = reorder::inverse(order);
+        let mut csc_offsets = Vec::with_capacity(order.len() + 1);
+        let mut csc_indices = Vec::with_capacity(self.csc_indices.len());
+        csc_offsets.push(0);
+        for &old in order {
+            let start = csc_indices.len();
+            csc_indices.extend(
+                self.get_csc_row(old as usize)
+                    .iter()
+                    .map(|&x| new_ids[x as usize]),
+            );
+            csc_indices[start..].sort_unstable();
+            csc_offsets.push(csc_indices.len() as u32);
+        }
+        let mut graph = Graph::from_csc("", csc_offsets, csc_indices, self.feature_size)
+            .expect("the permutation of a valid graph is valid");
+        graph.degree_data = self.degree_data.as_ref().map(|x| x.permute(order));
+        graph
+    }
+}
+
+/// # Description
+/// - the elements of the sorted `row` in `start..end`, found by binary search
+fn slice_range(row: &[u32], start: usize, end: usize) -> &[u32] {
+    let lower = row.partition_point(|&x| (x as usize) < start);
+    let upper = lower + row[lower..].partition_point(|&x| (x as usize) < end);
+    &row[lower..upper]
+}
+
+// create a mod for testing
+#[cfg(test)]
+mod graph_test {
+    use std::{error::Error, fs::File, io::Write};
+
+    use super::*;
+    #[test]
+    fn test_from_str() -> Result<(), Box<dyn Error>> {
+        let file_name = "test_data/graph_graph_str.txt";
+        // write the graph to the file
+        let data = "f 3\n0 1 2\n1 2 0\n2 0 1\nend\n";
+        let mut f = File::create(file_name).expect("file not found");
+        f.write_all(data.as_bytes())
+            .expect("something went wrong writing the file");
+        // read the graph from the file
+
+        let graph = Graph::new(file_name)?;
+        assert_eq!(graph.get_feature_size(), 3);
+        assert_eq!(graph.get_csc_row(0), &[0, 1, 2]);
+
+        assert_eq!(graph.get_csc_row(1), &[0, 1, 2]);
+        assert_eq!(graph.get_csc_row(2), &[0, 1, 2]);
+        assert_eq!(graph.get_num_edges(), 9);
+        // delete the file
+        std::fs::remove_file(file_name).expect("failed to delete the file");
+        Ok(())
+    }
+    #[test]
+    fn test_csr() -> Result<(), Box<dyn Error>> {
+        let file_name = "test_data/graph_csr.txt";
+        // write the graph to the file
+        let data = "f 3\n0 1\n1 2\n2 0\nend\n";
+        let mut f = File::create(file_name).expect("file not found");
+        f.write_all(data.as_bytes())
+            .expect("something went wrong writing the file");
+        // read the graph from the file
+
+        let graph = Graph::new(file_name)?;
+
+        assert_eq!(graph.get_csr_row(0), &[0, 2]);
+        assert_eq!(graph.get_csr_row(1), &[0, 1]);
+        assert_eq!(graph.get_csr_row(2), &[1, 2]);
+        assert_eq!(graph.get_num_node(), 3);
+
+        // the rows are searched in the range
+        assert_eq!(graph.get_csc_row_range(1, 0, 2), &[1]);
+        assert_eq!(graph.get_csc_row_range(1, 2, 3), &[2]);
+        assert!(graph.get_csc_row_range(2, 1, 2).is_empty());
+        assert!(graph.is_row_range_empty(0, 1, 2));
+        assert!(!graph.is_row_range_empty(0, 1, 3));
+        Ok(())
+    }
+    #[test]
+    fn test_bad_graph() -> Result<(), Box<dyn Error>> {
+        let file_name = "test_data/graph_bad.txt";
+        std::fs::write(file_name, "3\n0 1\nend\n")?;
+        assert!(matches!(
+            Graph::new(file_name),
+            Err(GcnAggError::InputFormat { line: Some(1), .. })
+        ));
+        std::fs::write(file_name, "f 3\n0 1\n1 x\nend\n")?;
+        assert!(matches!(
+            Graph::new(file_name),
+            Err(GcnAggError::InputFormat { line: Some(3), .. })
+        ));
+        // node 2 does not exist
+        std::fs::write(file_name, "f 3\n0 1\n2\nend\n")?;
+        assert!(matches!(
+            Graph::new(file_name),
+            Err(GcnAggError::InputFormat { line: None, .. })
+        ));
+        std::fs::remove_file(file_name)?;
+        assert!(matches!(Graph::new(file_name), Err(GcnAggError::Io { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_other_formats() -> Result<(), Box<dyn Error>> {
+        // node 0 aggregates 1 and 2, node 1 aggregates 2, node 2 aggregates 0
+        std::fs::write("test_data/graph_formats.txt", "f 4\n1 2\n2\n0\nend\n")?;
+        let native = Graph::new("test_data/graph_formats.txt")?;
+
+        let file_name = "test_data/graph_formats.edges";
+        std::fs::write(file_name, "# a comment\n1 0\n2\t0\n\n2,1\n0 2 0.5\n")?;
+        let graph = Graph::from_file(file_name, GraphFormat::EdgeList, Some(4))?;
+        assert_eq!(graph, native);
+
+        let file_name = "test_data/graph_formats.mtx";
+        std::fs::write(
+            file_name,
+            "%%MatrixMarket matrix coordinate pattern general\n% a comment\n3 3 4\n2 1\n3 1\n3 2\n1 3\n",
+        )?;
+        let graph = Graph::from_file(file_name, GraphFormat::MatrixMarket, Some(4))?;
+        assert_eq!(graph, native);
+
+        // the symmetric matrix only stores the lower triangle
+        std::fs::write(
+            file_name,
+            "%%MatrixMarket matrix coordinate real symmetric\n3 3 2\n2 1 1.0\n3 2 -1.0\n",
+        )?;
+        let graph = Graph::from_file(file_name, GraphFormat::MatrixMarket, Some(4))?;
+        assert_eq!(graph.get_csc_row(1), &[0, 2]);
+        assert_eq!(graph.get_csc_row(2), &[1]);
+
+        // 4 nodes, 3 edges: 1-2, 1-3, 2-3, node 4 is isolated, with node weights and edge weights
+        let file_name = "test_data/graph_formats.metis";
+        std::fs::write(
+            file_name,
+            "% a comment\n4 3 011\n5 2 1 3 1\n5 1 1 3 2\n5 1 1 2 2\n5\n",
+        )?;
+        let graph = Graph::from_file(file_name, GraphFormat::Metis, Some(4))?;
+        assert_eq!(graph.get_num_node(), 4);
+        assert_eq!(graph.get_csc_row(0), &[1, 2]);
+        assert_eq!(graph.get_csc_row(1), &[0, 2]);
+        assert_eq!(graph.get_csc_row(2), &[0, 1]);
+        assert!(graph.get_csc_row(3).is_empty());
+
+        // the bad files
+        std::fs::write("test_data/graph_formats.edges", "0 1\n2\n")?;
+        assert!(matches!(
+            Graph::from_file(
+                "test_data/graph_formats.edges",
+                GraphFormat::EdgeList,
+                Some(4)
+            ),
+            Err(GcnAggError::InputFormat { line: Some(2), .. })
+        ));
+        assert!(matches!(
+            Graph::from_file("test_data/graph_formats.edges", GraphFormat::EdgeList, None),
+            Err(GcnAggError::Config(_))
+        ));
+        std::fs::write(
+            "test_data/graph_formats.mtx",
+            "%%MatrixMarket matrix coordinate pattern general\n3 3 2\n4 1\n",
+        )?;
+        assert!(matches!(
+            Graph::from_file(
+                "test_data/graph_formats.mtx",
+                GraphFormat::MatrixMarket,
+                Some(4)
+            ),
+            Err(GcnAggError::InputFormat { line: Some(3), .. })
+        ));
+        std::fs::write("test_data/graph_formats.metis", "2 1\n2\n1\n2\n")?;
+        assert!(matches!(
+            Graph::from_file("test_data/graph_formats.metis", GraphFormat::Metis, Some(4)),
+            Err(GcnAggError::InputFormat { line: Some(4), .. })
+        ));
+        for name in ["txt", "edges", "mtx", "metis"] {
+            std::fs::remove_file(format!("test_data/graph_formats.{}", name))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary() -> Result<(), Box<dyn Error>> {
+        let file_name = "test_data/graph_binary.txt";
+        std::fs::write(file_name, "f 5\n1 2\n\n0 1 2\nend\n")?;
+        let graph = Graph::new(file_name)?;
+        graph.save_binary("test_data/graph_binary.bin")?;
+        let loaded = Graph::load_binary("test_data/graph_binary.bin")?;
+        assert_eq!(loaded.get_feature_size(), 5);
+        assert_eq!(loaded, graph);
+        // the native file is not a binary file
+        assert!(Graph::load_binary(file_name).is_err());
+        std::fs::remove_file(file_name)?;
+        std::fs::remove_file("test_data/graph_binary.bin")?;
+        Ok(())
+    }
+}
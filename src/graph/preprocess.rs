@@ -0,0 +1,225 @@
+//! # Description
+//! - the passes that change the loaded graph before the simulation, so the input files can be used as they are
+//! - `apply` adds the self loops and the reverse edges, merges the duplicate edges(including the ones added here),
+//!   then attaches the degree data for the normalization
+//! - the loaders keep the duplicate edges of the files, so `dedup = false` simulates the multigraph as it is
+//!
+
+use log::info;
+
+use super::{Edges, Graph};
+use crate::{
+    error::GcnAggError,
+    settings::{Normalization, PreprocessSettings},
+};
+
+/// # Description
+/// - the degree of each node after the preprocessing, attached to the graph for the normalized aggregation
+/// - the functional model scales the values by it, the aggregator counts the extra multiplies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DegreeData {
+    pub normalization: Normalization,
+    /// the number of input nodes of each node
+    pub in_degrees: Vec<u32>,
+    /// the number of output nodes of each node
+    pub out_degrees: Vec<u32>,
+}
+
+impl DegreeData {
+    pub fn new(graph: &Graph, normalization: Normalization) -> Self {
+        let num_nodes = graph.get_num_node();
+        DegreeData {
+            normalization,
+            in_degrees: (0..num_nodes)
+                .map(|i| graph.get_csc_row(i).len() as u32)
+                .collect(),
+            out_degrees: (0..num_nodes)
+                .map(|i| graph.get_csr_row(i).len() as u32)
+                .collect(),
+        }
+    }
+
+    /// # Description
+    /// - the scale of the input node `src` before it is aggregated
+    pub fn src_scale(&self, src: usize) -> f32 {
+        match self.normalization {
+            Normalization::Symmetric => inverse_sqrt(self.out_degrees[src]),
+            Normalization::Row | Normalization::None => 1.,
+        }
+    }
+
+    /// # Description
+    /// - the scale of the aggregated result of the output node `dst`
+    pub fn dst_scale(&self, dst: usize) -> f32 {
+        match self.normalization {
+            Normalization::Symmetric => inverse_sqrt(self.in_degrees[dst]),
+            Normalization::Row => match self.in_degrees[dst] {
+                0 => 0.,
+                degree => 1. / degree as f32,
+            },
+            Normalization::None => 1.,
+        }
+    }
+
+    /// # Description
+    /// - renumber the nodes like `Graph::permute`
+    pub fn permute(&self, order: &[u32]) -> Self {
+        DegreeData {
+            normalization: self.normalization,
+            in_degrees: order.iter().map(|&x| self.in_degrees[x as usize]).collect(),
+            out_degrees: order
+                .iter()
+                .map(|&x| self.out_degrees[x as usize])
+                .collect(),
+        }
+    }
+}
+
+/// the edges that repeat the previous input node of the same node
+fn count_duplicate_edges(graph: &Graph) -> usize {
+    (0..graph.get_num_node())
+        .map(|i| {
+            graph
+                .get_csc_row(i)
+                .windows(2)
+                .filter(|x| x[0] == x[1])
+                .count()
+        })
+        .sum()
+}
+
+/// `1/sqrt(degree)`, the node without any edge is never aggregated, so 0 is returned instead of inf
+fn inverse_sqrt(degree: u32) -> f32 {
+    match degree {
+        0 => 0.,
+        degree => 1. / (degree as f32).sqrt(),
+    }
+}
+
+/// # Description
+/// - change `graph` by `settings`, nothing changes for the default settings
+/// # Errors
+/// - `InputFormat` if the new edges cannot be indexed by u32
+pub fn apply(settings: &PreprocessSettings, graph: Graph) -> Result<Graph, GcnAggError> {
+    let has_duplicates = settings.dedup && count_duplicate_edges(&graph) > 0;
+    let mut graph = match settings.self_loops || settings.symmetrize || has_duplicates {
+        true => {
+            let mut edges = Edges::default();
+            for dst in 0..graph.get_num_node() as u32 {
+                for &src in graph.get_csc_row(dst as usize) {
+                    edges.add(src, dst);
+                    if settings.symmetrize {
+                        edges.add(dst, src);
+                    }
+                }
+                if settings.self_loops {
+                    edges.add(dst, dst);
+                }
+            }
+            let new_graph = edges.build(
+                "preprocessed graph",
+                Some(graph.get_num_node()),
+                graph.get_feature_size(),
+                settings.dedup,
+            )?;
+            info!(
+                "preprocess the graph, self loops: {}, symmetrize: {}, dedup: {}, edges: {} -> {}",
+                settings.self_loops,
+                settings.symmetrize,
+                settings.dedup,
+                graph.get_num_edges(),
+                new_graph.get_num_edges()
+            );
+            new_graph
+        }
+        false => graph,
+    };
+    if settings.normalization != Normalization::None {
+        graph.degree_data = Some(DegreeData::new(&graph, settings.normalization));
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_preprocess() -> Result<(), GcnAggError> {
+        // 0 -> 1, 1 -> 2, 2 -> 1
+        let mut edges = Edges::default();
+        edges.add(0, 1);
+        edges.add(1, 2);
+        edges.add(2, 1);
+        let graph = edges.build("test", Some(3), 4, false)?;
+
+        let mut settings = PreprocessSettings {
+            self_loops: false,
+            symmetrize: false,
+            dedup: true,
+            normalization: Normalization::None,
+        };
+        assert_eq!(apply(&settings, graph.clone())?, graph);
+
+        settings.self_loops = true;
+        let looped = apply(&settings, graph.clone())?;
+        assert_eq!(looped.get_csc_row(0), &[0]);
+        assert_eq!(looped.get_csc_row(1), &[0, 1, 2]);
+        assert_eq!(looped.get_csc_row(2), &[1, 2]);
+        assert!(looped.get_degree_data().is_none());
+
+        // the reverse of 1 -> 2 is already there, so it's merged
+        settings.symmetrize = true;
+        settings.normalization = Normalization::Symmetric;
+        let symmetric = apply(&settings, graph)?;
+        assert_eq!(symmetric.get_csc_row(0), &[0, 1]);
+        assert_eq!(symmetric.get_csc_row(1), &[0, 1, 2]);
+        assert_eq!(symmetric.get_num_edges(), 7);
+        let degree_data = symmetric.get_degree_data().unwrap();
+        assert_eq!(degree_data.in_degrees, vec![2, 3, 2]);
+        assert_eq!(degree_data.out_degrees, vec![2, 3, 2]);
+        assert_eq!(degree_data.dst_scale(0), 1. / 2f32.sqrt());
+        assert_eq!(degree_data.src_scale(1), 1. / 3f32.sqrt());
+
+        let row = DegreeData {
+            normalization: Normalization::Row,
+            ..degree_data.clone()
+        };
+        assert_eq!(row.src_scale(1), 1.);
+        assert_eq!(row.dst_scale(1), 1. / 3.);
+
+        // the degree data follows the nodes
+        let permuted = symmetric.permute(&[1, 2, 0]);
+        assert_eq!(
+            permuted.get_degree_data().unwrap().in_degrees,
+            vec![3, 2, 2]
+        );
+
+        // the edge 0 -> 1 of the file is duplicated
+        let mut edges = Edges::default();
+        edges.add(0, 1);
+        edges.add(0, 1);
+        edges.add(1, 2);
+        edges.add(2, 1);
+        let multigraph = edges.build("test", Some(3), 4, false)?;
+        assert_eq!(multigraph.get_csc_row(1), &[0, 0, 2]);
+        settings = PreprocessSettings {
+            self_loops: false,
+            symmetrize: false,
+            dedup: true,
+            normalization: Normalization::None,
+        };
+        let merged = apply(&settings, multigraph.clone())?;
+        assert_eq!(merged.get_csc_row(1), &[0, 2]);
+        assert_eq!(merged.get_num_edges(), 3);
+        // without dedup the graph is kept as it is, and the reverse edges are added even if they exist
+        settings.dedup = false;
+        assert_eq!(apply(&settings, multigraph.clone())?, multigraph);
+        settings.symmetrize = true;
+        let symmetric = apply(&settings, multigraph)?;
+        assert_eq!(symmetric.get_csc_row(0), &[1, 1]);
+        assert_eq!(symmetric.get_csc_row(1), &[0, 0, 2, 2]);
+        assert_eq!(symmetric.get_csc_row(2), &[1, 1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+//! # Description
+//! - the passes that renumber the nodes before the simulation, to improve the locality of the windows
+//! - each pass returns `order`, a permutation of the nodes: the new node `i` is the old node `order[i]`
+//! - `apply` renumbers the graph and the node features of every layer by the same permutation
+//!
+
+use std::collections::{HashMap, VecDeque};
+
+use log::info;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::Graph;
+use crate::{
+    node_features::NodeFeatures,
+    settings::{ReorderMethod, ReorderSettings},
+};
+
+/// # Description
+/// - the permutation of `method` for `graph`, `seed` is only used by `Random`
+pub fn order(graph: &Graph, method: ReorderMethod, seed: u64) -> Vec<u32> {
+    match method {
+        ReorderMethod::None => (0..graph.get_num_node() as u32).collect(),
+        ReorderMethod::Degree => degree_order(graph),
+        ReorderMethod::Bfs => bfs_order(graph),
+        ReorderMethod::Rcm => reverse_cuthill_mckee(graph),
+        ReorderMethod::Rabbit => rabbit_order(graph),
+        ReorderMethod::Random => random_order(graph, seed),
+    }
+}
+
+/// # Description
+/// - renumber `graph` and all layers of `node_features` by `settings`, nothing changes for `ReorderMethod::None`
+pub fn apply(
+    settings: &ReorderSettings,
+    graph: Graph,
+    node_features: Vec<NodeFeatures>,
+) -> (Graph, Vec<NodeFeatures>) {
+    if settings.method == ReorderMethod::None {
+        return (graph, node_features);
+    }
+    let order = order(&graph, settings.method, settings.seed);
+    info!(
+        "reorder the graph by {:?}, the average edge distance: {:.2} -> {:.2}",
+        settings.method,
+        average_edge_distance(&graph, None),
+        average_edge_distance(&graph, Some(&order))
+    );
+    let node_features = node_features.iter().map(|x| x.permute(&order)).collect();
+    (graph.permute(&order), node_features)
+}
+
+/// # Description
+/// - the average of `|i - j|` over the edges after renumbering by `order`, smaller means denser windows
+pub fn average_edge_distance(graph: &Graph, order: Option<&[u32]>) -> f64 {
+    if graph.get_num_edges() == 0 {
+        return 0.;
+    }
+    let new_ids = order.map(inverse);
+    let new_id = |x: usize| new_ids.as_ref().map_or(x, |ids| ids[x] as usize);
+    let total: usize = (0..graph.get_num_node())
+        .flat_map(|i| graph.get_csc_row(i).iter().map(move |&j| (i, j as usize)))
+        .map(|(i, j)| new_id(i).abs_diff(new_id(j)))
+        .sum();
+    total as f64 / graph.get_num_edges() as f64
+}
+
+/// # Description
+/// - the new id of each old node: `inverse(order)[order[i]] == i`
+pub fn inverse(order: &[u32]) -> Vec<u32> {
+    let mut new_ids = vec![0; order.len()];
+    for (new_id, &old_id) in order.iter().enumerate() {
+        new_ids[old_id as usize] = new_id as u32;
+    }
+    new_ids
+}
+
+/// # Description
+/// - the neighbors of node `i` in both directions, sorted without duplicates
+fn neighbors(graph: &Graph, i: usize) -> Vec<u32> {
+    let mut neighbors = [graph.get_csc_row(i), graph.get_csr_row(i)].concat();
+    neighbors.sort_unstable();
+    neighbors.dedup();
+    neighbors.retain(|&x| x as usize != i);
+    neighbors
+}
+
+/// # Description
+/// - the nodes with more input nodes first, the ties keep the old order
+fn degree_order(graph: &Graph) -> Vec<u32> {
+    let mut order: Vec<u32> = (0..graph.get_num_node() as u32).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(graph.get_csc_row(i as usize).len()));
+    order
+}
+
+/// # Description
+/// - the breadth first order of each connected component, from the smallest unvisited node
+fn bfs_order(graph: &Graph) -> Vec<u32> {
+    let num_nodes = graph.get_num_node();
+    let mut visited = vec![false; num_nodes];
+    let mut order = Vec::with_capacity(num_nodes);
+    for start in 0..num_nodes {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            order.push(node as u32);
+            for next in neighbors(graph, node) {
+                if !visited[next as usize] {
+                    visited[next as usize] = true;
+                    queue.push_back(next as usize);
+                }
+            }
+        }
+    }
+    order
+}
+
+/// # Description
+/// - the reverse Cuthill–McKee order: each component starts from its node of the min degree,
+///   the neighbors are visited from the min degree, then the whole order is reversed
+fn reverse_cuthill_mckee(graph: &Graph) -> Vec<u32> {
+    let num_nodes = graph.get_num_node();
+    let adjacency: Vec<Vec<u32>> = (0..num_nodes).map(|i| neighbors(graph, i)).collect();
+    let mut starts: Vec<usize> = (0..num_nodes).collect();
+    starts.sort_by_key(|&i| adjacency[i].len());
+
+    let mut visited = vec![false; num_nodes];
+    let mut order = Vec::with_capacity(num_nodes);
+    for start in starts {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            order.push(node as u32);
+            let mut next: Vec<usize> = adjacency[node]
+                .iter()
+                .map(|&x| x as usize)
+                .filter(|&x| !visited[x])
+                .collect();
+            next.sort_by_key(|&x| adjacency[x].len());
+            for x in next {
+                visited[x] = true;
+                queue.push_back(x);
+            }
+        }
+    }
+    order.reverse();
+    order
+}
+
+/// # Description
+/// - a simplified rabbit order: visit the nodes from the min degree, merge each node into the neighbor community
+///   of the max modularity gain if the gain is positive, then number the nodes by a depth first walk of the merges,
+///   so the nodes of one community are consecutive
+fn rabbit_order(graph: &Graph) -> Vec<u32> {
+    let num_nodes = graph.get_num_node();
+    // the weights to the neighbor communities, the keys may be merged nodes and are resolved by `find`
+    let mut adjacency: Vec<HashMap<usize, f64>> = (0..num_nodes)
+        .map(|i| {
+            neighbors(graph, i)
+                .into_iter()
+                .map(|x| (x as usize, 1.))
+                .collect()
+        })
+        .collect();
+    let mut degrees: Vec<f64> = adjacency.iter().map(|x| x.len() as f64).collect();
+    let total_degree: f64 = degrees.iter().sum();
+    let mut parents: Vec<usize> = (0..num_nodes).collect();
+    let mut children: Vec<Vec<usize>> = vec![vec![]; num_nodes];
+
+    fn find(parents: &mut [usize], mut x: usize) -> usize {
+        while parents[x] != x {
+            parents[x] = parents[parents[x]];
+            x = parents[x];
+        }
+        x
+    }
+
+    let mut visits: Vec<usize> = (0..num_nodes).collect();
+    visits.sort_by(|&a, &b| degrees[a].total_cmp(&degrees[b]));
+    for node in visits {
+        let mut weights: HashMap<usize, f64> = HashMap::new();
+        for (neighbor, weight) in std::mem::take(&mut adjacency[node]) {
+            let community = find(&mut parents, neighbor);
+            if community != node {
+                *weights.entry(community).or_default() += weight;
+            }
+        }
+        let best = weights
+            .iter()
+            .map(|(&community, &weight)| {
+                let gain = weight / total_degree
+                    - degrees[node] * degrees[community] / (total_degree * total_degree);
+                (community, gain)
+            })
+            .filter(|&(_, gain)| gain > 0.)
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+        match best {
+            Some((community, _)) => {
+                parents[node] = community;
+                children[community].push(node);
+                degrees[community] += degrees[node];
+                for (neighbor, weight) in weights {
+                    if neighbor != community {
+                        *adjacency[community].entry(neighbor).or_default() += weight;
+                    }
+                }
+            }
+            None => adjacency[node] = weights,
+        }
+    }
+
+    let mut order = Vec::with_capacity(num_nodes);
+    for root in (0..num_nodes).filter(|&x| parents[x] == x) {
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            order.push(node as u32);
+            stack.extend(children[node].iter().rev());
+        }
+    }
+    order
+}
+
+/// # Description
+/// - a random permutation from `seed`
+fn random_order(graph: &Graph, seed: u64) -> Vec<u32> {
+    let mut order: Vec<u32> = (0..graph.get_num_node() as u32).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+    order
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graph::generators;
+
+    fn is_permutation(order: &[u32], num_nodes: usize) -> bool {
+        let mut sorted = order.to_vec();
+        sorted.sort_unstable();
+        sorted == (0..num_nodes as u32).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn test_orders() -> Result<(), Box<dyn std::error::Error>> {
+        let graph =
+            generators::from_uri("gen:sbm?blocks=4&size=30&p_in=0.3&p_out=0.005&seed=1", 8)?;
+        let methods = [
+            ReorderMethod::None,
+            ReorderMethod::Degree,
+            ReorderMethod::Bfs,
+            ReorderMethod::Rcm,
+            ReorderMethod::Rabbit,
+            ReorderMethod::Random,
+        ];
+        for method in methods {
+            let order = order(&graph, method, 1);
+            assert!(is_permutation(&order, 120), "{:?}", method);
+        }
+
+        let degrees = degree_order(&graph);
+        assert!(degrees
+            .windows(2)
+            .all(|x| graph.get_csc_row(x[0] as usize).len()
+                >= graph.get_csc_row(x[1] as usize).len()));
+
+        // shuffle the communities, then the locality passes should bring them back
+        let shuffled = graph.permute(&random_order(&graph, 7));
+        let shuffled_distance = average_edge_distance(&shuffled, None);
+        for method in [
+            ReorderMethod::Rcm,
+            ReorderMethod::Rabbit,
+            ReorderMethod::Bfs,
+        ] {
+            let order = order(&shuffled, method, 0);
+            let distance = average_edge_distance(&shuffled, Some(&order));
+            assert!(
+                distance < shuffled_distance * 0.7,
+                "{:?}: {}",
+                method,
+                distance
+            );
+        }
+        // the rabbit order keeps most edges inside the blocks of 30 nodes
+        let reordered = shuffled.permute(&rabbit_order(&shuffled));
+        let close = (0..120)
+            .flat_map(|i| {
+                reordered
+                    .get_csc_row(i)
+                    .iter()
+                    .map(move |&j| i.abs_diff(j as usize))
+            })
+            .filter(|&x| x < 30)
+            .count();
+        assert!(close * 10 > reordered.get_num_edges() * 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply() -> Result<(), Box<dyn std::error::Error>> {
+        let graph = generators::grid(3, 3, 4)?;
+        let features = NodeFeatures::from_dense(
+            &(0..9)
+                .map(|i| vec![i as f32 + 1., 0., 1., 0.])
+                .collect::<Vec<_>>(),
+        );
+        let settings = ReorderSettings {
+            method: ReorderMethod::Random,
+            seed: 3,
+        };
+        let order = order(&graph, settings.method, settings.seed);
+        let new_ids = inverse(&order);
+        let (reordered, layers) = apply(&settings, graph.clone(), vec![features]);
+        for old in 0..9 {
+            let new = new_ids[old] as usize;
+            // the features move with the node
+            assert_eq!(layers[0].values[new][0], old as f32 + 1.);
+            // so do the edges
+            let mut expected: Vec<u32> = graph
+                .get_csc_row(old)
+                .iter()
+                .map(|&x| new_ids[x as usize])
+                .collect();
+            expected.sort_unstable();
+            assert_eq!(reordered.get_csc_row(new), expected.as_slice());
+        }
+        assert_eq!(layers[0].start_addrs.len(), 10);
+        Ok(())
+    }
+}
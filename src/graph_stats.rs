@@ -0,0 +1,183 @@
+//! # Description
+//! - the statistics of a dataset, reported by `gcn_agg graph-stats` to describe it before the simulation
+//! - the node `i` aggregates the nodes in row `i`(the input nodes), and is aggregated by the nodes in column `i`
+//!   (the output nodes), an empty row is never aggregated into and an empty column is never read by others
+//! - the ordering metrics are measured on the current node ids, so they show the effect of `reorder`
+//!
+
+use serde::{Deserialize, Serialize};
+
+use crate::{graph::reorder, Graph, NodeFeatures};
+
+/// # Description
+/// - the distribution of the degrees of all nodes, the percentiles are the nearest rank
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DegreeStatistics {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub p50: usize,
+    pub p90: usize,
+    pub p99: usize,
+}
+
+impl DegreeStatistics {
+    pub fn new(mut degrees: Vec<usize>) -> Self {
+        if degrees.is_empty() {
+            return Self::default();
+        }
+        degrees.sort_unstable();
+        let percentile = |p: usize| degrees[((p * degrees.len()).div_ceil(100)).max(1) - 1];
+        DegreeStatistics {
+            min: degrees[0],
+            max: degrees[degrees.len() - 1],
+            mean: degrees.iter().sum::<usize>() as f64 / degrees.len() as f64,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+        }
+    }
+}
+
+/// # Description
+/// - how far the edges are from the diagonal of the adjacency matrix, smaller means denser windows
+/// - `bandwidth`: the max `|i - j|` of the edges
+/// - `profile`: the sum of `i - min(row i)` of the rows whose first input node is before `i`
+/// - `average_edge_distance`: the average `|i - j|` of the edges
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderingStatistics {
+    pub bandwidth: usize,
+    pub profile: u64,
+    pub average_edge_distance: f64,
+}
+
+/// # Description
+/// - the node features of one layer
+/// - `start_addrs_bytes`: the size of the `start_addrs` table, one u64 for each node and the end
+/// - `features_bytes`: the bytes of the sparse features addressed by `start_addrs`, the index and the value of each nonzero
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeaturesStatistics {
+    pub layer: usize,
+    pub dim: usize,
+    pub nnz: usize,
+    pub density: f64,
+    pub start_addrs_bytes: u64,
+    pub features_bytes: u64,
+}
+
+/// # Description
+/// - the statistics of the graph and the node features of each layer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphStatistics {
+    pub graph_path: String,
+    pub num_nodes: usize,
+    pub num_edges: usize,
+    pub feature_size: usize,
+    pub in_degrees: DegreeStatistics,
+    pub out_degrees: DegreeStatistics,
+    pub empty_rows: usize,
+    pub empty_columns: usize,
+    pub ordering: OrderingStatistics,
+    pub layers: Vec<FeaturesStatistics>,
+}
+
+impl GraphStatistics {
+    /// # Description
+    /// - measure `graph` and `node_features`, `dims` is the dense dimension of each layer of `node_features`
+    pub fn new(
+        graph_path: &str,
+        graph: &Graph,
+        node_features: &[NodeFeatures],
+        dims: &[usize],
+    ) -> Self {
+        let num_nodes = graph.get_num_node();
+        let in_degrees: Vec<usize> = (0..num_nodes).map(|i| graph.get_csc_row(i).len()).collect();
+        let out_degrees: Vec<usize> = (0..num_nodes).map(|i| graph.get_csr_row(i).len()).collect();
+        let layers = node_features
+            .iter()
+            .zip(dims)
+            .enumerate()
+            .map(|(layer, (features, &dim))| FeaturesStatistics {
+                layer,
+                dim,
+                nnz: features.get_nnz(),
+                density: features.get_density(dim),
+                start_addrs_bytes: features.start_addrs.len() as u64 * 8,
+                features_bytes: features.start_addrs.last().copied().unwrap_or(0),
+            })
+            .collect();
+        GraphStatistics {
+            graph_path: graph_path.to_string(),
+            num_nodes,
+            num_edges: graph.get_num_edges(),
+            feature_size: graph.get_feature_size(),
+            empty_rows: in_degrees.iter().filter(|&&x| x == 0).count(),
+            empty_columns: out_degrees.iter().filter(|&&x| x == 0).count(),
+            in_degrees: DegreeStatistics::new(in_degrees),
+            out_degrees: DegreeStatistics::new(out_degrees),
+            ordering: ordering_statistics(graph),
+            layers,
+        }
+    }
+}
+
+fn ordering_statistics(graph: &Graph) -> OrderingStatistics {
+    let mut bandwidth = 0;
+    let mut profile = 0;
+    for i in 0..graph.get_num_node() {
+        let row = graph.get_csc_row(i);
+        // the rows are sorted
+        if let (Some(&first), Some(&last)) = (row.first(), row.last()) {
+            bandwidth = bandwidth
+                .max(i.abs_diff(first as usize))
+                .max(i.abs_diff(last as usize));
+            profile += i.saturating_sub(first as usize) as u64;
+        }
+    }
+    OrderingStatistics {
+        bandwidth,
+        profile,
+        average_edge_distance: reorder::average_edge_distance(graph, None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_graph_statistics() -> Result<(), Box<dyn std::error::Error>> {
+        let graph_name = "graph_stats";
+        // node 0 aggregates 1 and 3, node 2 aggregates 0, node 1 and 3 aggregate nothing
+        let graph = Graph::from_rows(&[vec![1, 3], vec![], vec![0], vec![]], 4)?;
+        let features = NodeFeatures::from_dense(&[
+            vec![1., 0., 0., 0.],
+            vec![1., 1., 0., 0.],
+            vec![0., 0., 0., 0.],
+            vec![1., 1., 1., 1.],
+        ]);
+        let stats = GraphStatistics::new(graph_name, &graph, &[features], &[4]);
+        assert_eq!(stats.num_nodes, 4);
+        assert_eq!(stats.num_edges, 3);
+        assert_eq!(stats.empty_rows, 2);
+        // node 2 is never read
+        assert_eq!(stats.empty_columns, 1);
+        assert_eq!(stats.in_degrees.max, 2);
+        assert_eq!(stats.in_degrees.p50, 0);
+        assert_eq!(stats.in_degrees.p90, 2);
+        assert_eq!(stats.out_degrees.mean, 0.75);
+        assert_eq!(
+            stats.ordering,
+            OrderingStatistics {
+                bandwidth: 3,
+                profile: 2,
+                average_edge_distance: 2.,
+            }
+        );
+        assert_eq!(stats.layers[0].nnz, 7);
+        assert_eq!(stats.layers[0].density, 7. / 16.);
+        assert_eq!(stats.layers[0].start_addrs_bytes, 40);
+        assert_eq!(stats.layers[0].features_bytes, 56);
+        Ok(())
+    }
+}
@@ -3,16 +3,19 @@
 //!
 //! - accelerator: the accelerator is a graph convolutional neural network accelerator.
 //! - graph: the data structure to represent the graph.
+//! - graph_stats: the statistics of the graph and the node features, without the simulation.
+//! - binary: the binary files and the cache of the graph and the node features.
 //! - node_features: the data structure to represent the node features.
+//! - npy: the reader of the numpy `.npy` and `.npz` files of the node features.
 //! - statics: the result statics to record the result.
+//! - error: the error type returned by the loaders and the simulator.
 //! # Examples
 //! ```
 //!     use chrono::Local;
 //!     use gcn_agg::{
-//!     accelerator::System, gcn_result::GcnAggResult, graph::Graph, node_features::NodeFeatures,
+//!     accelerator::System, gcn_result::GcnAggResult, graph::{reorder, Graph}, node_features::NodeFeatures,
 //!     settings::Settings,
 //!     };
-//!     use itertools::Itertools;
 //!
 //!     fn test_system() -> Result<(), Box<dyn std::error::Error>> {
 //!         std::fs::create_dir_all("output")?;
@@ -28,15 +31,10 @@
 //!         // create the folder for output
 //!         std::fs::create_dir_all("output")?;
 //!
-//!         let graph_name = &settings.graph_path;
-//!         let features_name = &settings.features_paths;
+//!         let graph = Graph::load(&settings)?;
 //!
-//!         let graph = Graph::new(graph_name.as_str())?;
-//!
-//!         let node_features: Vec<_> = features_name
-//!             .iter()
-//!             .map(|x| NodeFeatures::new(x.as_str()))
-//!             .try_collect()?;
+//!         let node_features = NodeFeatures::load_layers(&settings, &graph)?;
+//!         let (graph, node_features) = reorder::apply(&settings.reorder, graph, node_features);
 //!
 //!         let mem_stat_path = format!("output/{}_mem_stat.txt", current_time);
 //!         let mut system = System::new(
@@ -44,7 +42,7 @@
 //!             &node_features,
 //!             settings.accelerator_settings,
 //!             &mem_stat_path,
-//!         );
+//!         )?;
 //!
 //!         // run the system
 //!         let mut stat = system.run()?;
@@ -59,6 +57,7 @@
 //!         stat.simulation_time = time_str;
 //!
 //!         results.stats = Some(stat);
+//!         results.layers = Some(system.get_layer_stats().to_vec());
 //!         let output_path = format!("output/{}.json", current_time);
 //!
 //!         println!("{}", serde_json::to_string_pretty(&results)?);
@@ -74,13 +73,18 @@
 //!
 
 pub mod accelerator;
+pub mod binary;
 pub mod cmd_args;
+pub mod error;
 pub mod gcn_result;
 pub mod graph;
+pub mod graph_stats;
 pub mod node_features;
+pub mod npy;
 pub mod settings;
 // default re-export
 pub use accelerator::System;
+pub use error::GcnAggError;
 pub use gcn_result::{GcnAggResult, GcnStatistics};
 pub use graph::Graph;
 pub use node_features::NodeFeatures;
@@ -1,12 +1,65 @@
 use chrono::Local;
 use clap::{Command, CommandFactory, Parser};
 use clap_complete::{generate, Generator};
-use gcn_agg::{cmd_args::Args, settings::Settings, GcnAggResult, Graph, NodeFeatures, System};
-use itertools::Itertools;
+use gcn_agg::{
+    accelerator::{plan_windows, MemoryLayout},
+    cmd_args::{Args, Commands},
+    graph::reorder,
+    graph_stats::GraphStatistics,
+    settings::Settings,
+    GcnAggError, GcnAggResult, Graph, NodeFeatures, System,
+};
+use log::info;
 use std::io;
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut io::stdout());
 }
+
+/// # Description
+/// - the `graph-stats` command: the `.toml` paths are configs, the other path replaces the graph of the configs
+/// - the node features are only loaded for the graph of the configs
+fn graph_stats(
+    mut config_names: Vec<String>,
+    paths: Vec<String>,
+    current_time: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (configs, graphs): (Vec<_>, Vec<_>) = paths.into_iter().partition(|x| x.ends_with(".toml"));
+    if graphs.len() > 1 {
+        return Err(GcnAggError::Config(format!(
+            "only one graph can be measured, got {:?}",
+            graphs
+        ))
+        .into());
+    }
+    config_names.extend(configs);
+    let mut settings = Settings::new(config_names)?;
+    if let Some(graph_path) = graphs.first() {
+        settings.set_graph_path(graph_path)?;
+    }
+    std::fs::create_dir_all("output")?;
+
+    let graph = Graph::load(&settings)?;
+    let node_features = match graphs.is_empty() {
+        true => NodeFeatures::load_layers(&settings, &graph)?,
+        false => vec![],
+    };
+    let (graph, node_features) = reorder::apply(&settings.reorder, graph, node_features);
+    let dims: Vec<_> = std::iter::once(graph.get_feature_size())
+        .chain(
+            settings
+                .accelerator_settings
+                .gcn_hidden_size
+                .iter()
+                .cloned(),
+        )
+        .collect();
+    let stats = GraphStatistics::new(&settings.graph_path, &graph, &node_features, &dims);
+
+    let output_path = format!("output/{}_graph_stats.json", current_time);
+    println!("{}", serde_json::to_string_pretty(&stats)?);
+    std::fs::write(output_path, serde_json::to_string_pretty(&stats)?)?;
+    Ok(())
+}
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     simple_logger::init_with_level(log::Level::Info)?;
     let start_time = std::time::Instant::now();
@@ -21,6 +74,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
     println!("{:?}", args);
+    if let Some(Commands::GraphStats { paths }) = args.command {
+        return graph_stats(config_names, paths, &current_time);
+    }
     let margs = args.config_names;
 
     // config_names append args
@@ -35,22 +91,38 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // create the folder for output
     std::fs::create_dir_all("output")?;
 
-    let graph_name = &settings.graph_path;
-    let features_name = &settings.features_paths;
+    let graph = Graph::load(&settings)?;
 
-    let graph = Graph::new(graph_name.as_str())?;
-
-    let node_features: Vec<_> = features_name
-        .iter()
-        .map(|x| NodeFeatures::new(x.as_str()))
-        .try_collect()?;
+    let node_features = NodeFeatures::load_layers(&settings, &graph)?;
+    let (graph, node_features) = reorder::apply(&settings.reorder, graph, node_features);
+    if args.dry_run {
+        let plan = plan_windows(&graph, &node_features, &settings.accelerator_settings)?;
+        info!(
+            "dry run: {} output windows, {} input windows, the average fill: {:.2}",
+            plan.summary.output_windows, plan.summary.input_windows, plan.summary.average_fill
+        );
+        results.window_plan = Some(plan);
+        let acc_settings = &settings.accelerator_settings;
+        let layout = MemoryLayout::new(
+            &graph,
+            &node_features,
+            &acc_settings.gcn_hidden_size,
+            &acc_settings.running_mode,
+            &acc_settings.layout_settings,
+        )?;
+        results.memory_layout = Some(layout.report());
+        let output_path = format!("output/{}_window_plan.json", current_time);
+        std::fs::write(output_path, serde_json::to_string_pretty(&results)?)?;
+        return Ok(());
+    }
     let stats_name = format!("output/{}_mem_stat.txt", current_time);
     let mut system = System::new(
         &graph,
         &node_features,
         settings.accelerator_settings,
         &stats_name,
-    );
+    )?;
+    results.memory_layout = Some(system.get_memory_layout().report());
 
     // run the system
     let mut stat = system.run()?;
@@ -65,6 +137,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     stat.simulation_time = time_str;
 
     results.stats = Some(stat);
+    results.layers = Some(system.get_layer_stats().to_vec());
     let output_path = format!("output/{}.json", current_time);
 
     println!("{}", serde_json::to_string_pretty(&results)?);
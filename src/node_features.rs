@@ -1,13 +1,57 @@
-use std::error::Error;
-use std::fs::File;
-use std::io::Read;
-
 use itertools::Itertools;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    accelerator::reference_node_features,
+    binary::{self, BinaryReader, BinaryWriter},
+    error::GcnAggError,
+    graph::{generators::GenUri, Graph},
+    npy::{self, NpyArray, NpyData},
+    settings::{EncodingSettings, FeaturesFormat, FeaturesSource, Settings, SparseEncoding},
+};
+
+/// the magic of the binary node features file
+const FEATURES_MAGIC: &[u8; 8] = b"GCNFEATS";
+
+/// # Description
+/// - the distribution of the density of each node in the synthetic node features
+/// - `Constant`: every node has the same density
+/// - `Uniform`: the density is uniform in `[min, max]`
+/// - `Normal`: the density is normal of `mean` and `std`, clamped to [0, 1]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DensityDistribution {
+    Constant(f64),
+    Uniform { min: f64, max: f64 },
+    Normal { mean: f64, std: f64 },
+}
+
+impl DensityDistribution {
+    /// # Description
+    /// - draw the density of one node, always in [0, 1]
+    pub fn sample(&self, rng: &mut impl Rng) -> f64 {
+        let density = match *self {
+            DensityDistribution::Constant(density) => density,
+            DensityDistribution::Uniform { min, max } if min < max => rng.gen_range(min..=max),
+            DensityDistribution::Uniform { min, .. } => min,
+            DensityDistribution::Normal { mean, std } => {
+                // box-muller
+                let (u1, u2): (f64, f64) = (rng.gen(), rng.gen());
+                mean + std * (-2. * (1. - u1).ln()).sqrt() * (2. * std::f64::consts::PI * u2).cos()
+            }
+        };
+        density.clamp(0., 1.)
+    }
+}
 
 #[derive(Debug)]
 pub struct NodeFeatures {
     pub features: Vec<Vec<usize>>,
+    /// the values of the nonzero elements in `features`, only used by the functional simulation
+    pub values: Vec<Vec<f32>>,
+    /// the address of each node in the memory, the sparse nodes are stored one after another
     pub start_addrs: Vec<u64>,
+    /// how the features are stored, decides `start_addrs`
+    encoding: EncodingSettings,
 }
 
 impl NodeFeatures {
@@ -20,6 +64,7 @@ impl NodeFeatures {
     /// # Description
     /// Reads a file containing a list of features for each node.
     /// note each line of file contains a dense format of a node feature
+    /// the elements can be real numbers, the nonzero values are kept for the functional simulation
     /// example file format:
     /// 0 1 0
     /// 1 0 1
@@ -58,64 +103,647 @@ impl NodeFeatures {
     /// std::fs::remove_file(file_name)?;
     ///
     /// ```
-    pub fn new(file_name: &str) -> Result<Self, Box<dyn Error>> {
+    pub fn new(file_name: &str) -> Result<Self, GcnAggError> {
         // the file contains adjacency matrix
         // each line is a node
-        let mut file = File::open(file_name)?;
         // contnents contains all the file in 0 1 0 1\n 1 0 1 1\n 1 1 0 0\n format
-        let mut contents = String::new();
-
-        file.read_to_string(&mut contents)?;
+        let contents =
+            std::fs::read_to_string(file_name).map_err(|e| GcnAggError::io(file_name, e))?;
         let mut features = Vec::new();
+        let mut values = Vec::new();
 
-        for line in contents.lines() {
+        for (line_number, line) in contents.lines().enumerate() {
             // each line is a node in 0 1 0 1 format
             let line_vec: Vec<_> = line
                 .split_whitespace()
-                .map(|x| x.parse::<usize>())
-                .try_collect()?;
+                .map(|x| x.parse::<f32>())
+                .try_collect()
+                .map_err(|e| GcnAggError::input_format(file_name, Some(line_number + 1), e))?;
 
             // convert the line to csc format
             let mut csc_line = Vec::new();
+            let mut value_line = Vec::new();
             // build the csc format: 0 1 0 1 => 1,3
             for (i, &item) in line_vec.iter().enumerate() {
-                if item != 0 {
+                if item != 0. {
                     csc_line.push(i);
+                    value_line.push(item);
                 }
             }
 
             features.push(csc_line);
+            values.push(value_line);
         }
-        // build start addr from the node features
 
+        Ok(Self::from_rows(features, values))
+    }
+
+    /// # Description
+    /// - read the node features from `file_name` in `format`, see `FeaturesFormat`
+    /// - all formats build the same `features` and `start_addrs` as `new`, the explicit zeros are dropped
+    /// # Errors
+    /// - `InputFormat` if the file is not in `format`, or a node has the same index twice
+    pub fn from_file(file_name: &str, format: FeaturesFormat) -> Result<Self, GcnAggError> {
+        let read = || std::fs::read_to_string(file_name).map_err(|e| GcnAggError::io(file_name, e));
+        let rows = match format {
+            FeaturesFormat::Dense => return NodeFeatures::new(file_name),
+            FeaturesFormat::Libsvm => parse_libsvm(file_name, &read()?)?,
+            FeaturesFormat::IndexList => parse_index_list(file_name, &read()?)?,
+            FeaturesFormat::Coo => parse_coo(file_name, &read()?)?,
+            FeaturesFormat::Npy => dense_array_rows(file_name, &npy::read_npy(file_name)?)?,
+            FeaturesFormat::Npz => npz_rows(file_name)?,
+        };
+        let (features, values) = rows.into_iter().unzip();
+        Ok(Self::from_rows(features, values))
+    }
+
+    /// # Description
+    /// - read the node features from `file_name` by `from_file`, through the binary cache in `cache_dir` if set
+    /// - the numpy files are read directly, they are binary already
+    pub fn load(
+        file_name: &str,
+        format: FeaturesFormat,
+        cache_dir: Option<&str>,
+    ) -> Result<Self, GcnAggError> {
+        let parse = |path: &str| NodeFeatures::from_file(path, format);
+        match cache_dir {
+            Some(cache_dir) if !matches!(format, FeaturesFormat::Npy | FeaturesFormat::Npz) => {
+                binary::load_cached(
+                    cache_dir,
+                    file_name,
+                    &format!("node features {:?}", format),
+                    NodeFeatures::load_binary,
+                    parse,
+                    NodeFeatures::save_binary,
+                )
+            }
+            _ => parse(file_name),
+        }
+    }
+
+    /// # Description
+    /// - write the node features as the binary file, see `crate::binary`
+    /// - the payload is the number of nodes(u64), the number of nonzeros(u64), whether the values are saved(u32),
+    ///   the offsets of each node(u64 * (nodes + 1)), the indices(u32 * nonzeros) and the values(f32 * nonzeros)
+    /// - the values are not saved when they are all 1
+    pub fn save_binary(&self, file_name: &str) -> Result<(), GcnAggError> {
+        let has_values = self.values.iter().flatten().any(|&x| x != 1.);
+        let mut writer = BinaryWriter::new(FEATURES_MAGIC);
+        writer.write_u64(self.features.len() as u64);
+        writer.write_u64(self.get_nnz() as u64);
+        writer.write_u32(has_values as u32);
+        let mut offset = 0;
+        writer.write_u64(offset);
+        for row in &self.features {
+            offset += row.len() as u64;
+            writer.write_u64(offset);
+        }
+        for &index in self.features.iter().flatten() {
+            let index =
+                u32::try_from(index).map_err(|e| GcnAggError::InvalidState(e.to_string()))?;
+            writer.write_u32(index);
+        }
+        if has_values {
+            for &value in self.values.iter().flatten() {
+                writer.write_f32(value);
+            }
+        }
+        writer.write(file_name)
+    }
+
+    /// # Description
+    /// - read the node features from the binary file written by `save_binary`
+    pub fn load_binary(file_name: &str) -> Result<Self, GcnAggError> {
+        let mut reader = BinaryReader::open(file_name, FEATURES_MAGIC)?;
+        let num_nodes = reader.read_len()?;
+        let nnz = reader.read_len()?;
+        let has_values = match reader.read_u32()? {
+            0 => false,
+            1 => true,
+            x => return Err(reader.error(format!("bad values flag {}", x))),
+        };
+        let offsets = reader.read_offsets(num_nodes)?;
+        if offsets[num_nodes] != nnz {
+            return Err(reader.error("the last offset is not the number of nonzeros"));
+        }
+        let features: Vec<Vec<usize>> = offsets
+            .windows(2)
+            .map(|x| {
+                (x[0]..x[1])
+                    .map(|_| Ok::<_, GcnAggError>(reader.read_u32()? as usize))
+                    .try_collect()
+            })
+            .try_collect()?;
+        let values: Vec<Vec<f32>> = match has_values {
+            true => offsets
+                .windows(2)
+                .map(|x| (x[0]..x[1]).map(|_| reader.read_f32()).try_collect())
+                .try_collect()?,
+            false => features.iter().map(|x| vec![1.; x.len()]).collect(),
+        };
+        reader.finish()?;
+        Ok(Self::from_rows(features, values))
+    }
+
+    /// # Description
+    /// - build the node features from the indices and the values of the nonzeros of each node
+    /// - the features are encoded by the default `EncodingSettings`
+    pub fn from_rows(features: Vec<Vec<usize>>, values: Vec<Vec<f32>>) -> Self {
+        let encoding = EncodingSettings::default();
+        NodeFeatures {
+            // the default csr does not depend on the dim
+            start_addrs: Self::build_start_addrs(&features, &encoding, 0),
+            features,
+            values,
+            encoding,
+        }
+    }
+
+    fn build_start_addrs(
+        features: &[Vec<usize>],
+        encoding: &EncodingSettings,
+        dim: usize,
+    ) -> Vec<u64> {
+        Self::prefix_sum(
+            features
+                .iter()
+                .map(|x| encoding.sparse_node_bytes(x, dim) as u64),
+        )
+    }
+
+    /// the start of each node and the end, from the bytes of each node
+    fn prefix_sum(node_bytes: impl Iterator<Item = u64>) -> Vec<u64> {
         let mut start_addrs: Vec<u64> = vec![];
-        let last = features.iter().fold(0, |acc, x| {
+        let last = node_bytes.fold(0, |acc, x| {
             start_addrs.push(acc);
-            acc + (x.len() * 4) as u64
+            acc + x
         });
         start_addrs.push(last);
+        start_addrs
+    }
 
-        Ok(NodeFeatures {
-            features,
-            start_addrs,
+    /// # Description
+    /// - store the features of `dim` columns by `encoding`, the addresses of all nodes are rebuilt
+    /// # Errors
+    /// - `Config` if a column index cannot be encoded by `index_bytes` of `Csr`
+    pub fn with_encoding(
+        mut self,
+        encoding: EncodingSettings,
+        dim: usize,
+    ) -> Result<Self, GcnAggError> {
+        if let Some(&index) = self.features.iter().flatten().max() {
+            if encoding.sparse_encoding == SparseEncoding::Csr && index > encoding.max_index() {
+                return Err(GcnAggError::Config(format!(
+                    "the feature index {} cannot be encoded in {} bytes",
+                    index, encoding.index_bytes
+                )));
+            }
+        }
+        self.start_addrs = Self::build_start_addrs(&self.features, &encoding, dim);
+        self.encoding = encoding;
+        Ok(self)
+    }
+
+    /// # Description
+    /// - renumber the nodes, the new node `i` is the old node `order[i]`, see `graph::reorder`
+    pub fn permute(&self, order: &[u32]) -> Self {
+        let (features, values) = order
+            .iter()
+            .map(|&x| {
+                (
+                    self.features[x as usize].clone(),
+                    self.values[x as usize].clone(),
+                )
+            })
+            .unzip();
+        let mut permuted = Self::from_rows(features, values);
+        // the size of each node is kept
+        permuted.start_addrs = Self::prefix_sum(
+            order
+                .iter()
+                .map(|&x| self.get_node_bytes(x as usize) as u64),
+        );
+        permuted.encoding = self.encoding;
+        permuted
+    }
+
+    /// # Description
+    /// - keep the nonzeros of the dense features, each row is a node
+    pub fn from_dense(rows: &[Vec<f32>]) -> Self {
+        let (features, values) = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, &x)| x != 0.)
+                    .map(|(i, &x)| (i, x))
+                    .unzip()
+            })
+            .unzip();
+        Self::from_rows(features, values)
+    }
+
+    /// # Description
+    /// - the synthetic features of `num_nodes` nodes, each of the `dim` elements is 1 with the probability `density`
+    pub fn random(num_nodes: usize, dim: usize, density: f64, rng: &mut impl Rng) -> Self {
+        Self::synthetic(num_nodes, dim, &DensityDistribution::Constant(density), rng)
+    }
+
+    /// # Description
+    /// - the synthetic features of `num_nodes` nodes, the density of each node is drawn from `distribution`,
+    ///   then each of the `dim` elements is 1 with the probability of that density
+    pub fn synthetic(
+        num_nodes: usize,
+        dim: usize,
+        distribution: &DensityDistribution,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let features: Vec<Vec<usize>> = (0..num_nodes)
+            .map(|_| {
+                let density = distribution.sample(rng);
+                (0..dim).filter(|_| rng.gen_bool(density)).collect()
+            })
+            .collect();
+        let values = features.iter().map(|x| vec![1.; x.len()]).collect();
+        Self::from_rows(features, values)
+    }
+
+    /// # Description
+    /// - the synthetic features described by `uri` for `num_nodes` nodes, e.g. `gen:features?dist=uniform&min=0.1&max=0.5&seed=1`
+    /// - `dim` is the dimension of each node, `default_dim` if not provided
+    /// - `dist` is `constant`(with `density`), `uniform`(with `min` and `max`) or `normal`(with `mean` and `std`)
+    pub fn from_uri(uri: &str, num_nodes: usize, default_dim: usize) -> Result<Self, GcnAggError> {
+        let uri = GenUri::parse(uri)?;
+        if uri.name != "features" {
+            return Err(GcnAggError::Config(format!(
+                "unknown features generator {}",
+                uri.name
+            )));
+        }
+        let distribution = match uri.get_or("dist", "constant".to_string())?.as_str() {
+            "constant" => DensityDistribution::Constant(uri.get("density")?),
+            "uniform" => DensityDistribution::Uniform {
+                min: uri.get("min")?,
+                max: uri.get("max")?,
+            },
+            "normal" => DensityDistribution::Normal {
+                mean: uri.get("mean")?,
+                std: uri.get("std")?,
+            },
+            dist => {
+                return Err(GcnAggError::Config(format!(
+                    "unknown density distribution {}",
+                    dist
+                )))
+            }
+        };
+        let mut rng = StdRng::seed_from_u64(uri.get_seed()?);
+        Ok(Self::synthetic(
+            num_nodes,
+            uri.get_or("dim", default_dim)?,
+            &distribution,
+            &mut rng,
+        ))
+    }
+
+    /// # Description
+    /// - build the node features of all layers by `settings.features_settings`, encoded by the `encoding_settings`
+    /// - the first layer is always read from the first file of `features_paths`
+    /// - a path like `gen:features?...` builds the synthetic features, see `from_uri`
+    /// # Errors
+    /// - the errors of reading the files, or building the functional features
+    /// - `Config` if a feature index cannot be encoded by `index_bytes`
+    pub fn load_layers(settings: &Settings, graph: &Graph) -> Result<Vec<Self>, GcnAggError> {
+        let paths = &settings.features_paths;
+        let gcn_hidden_size = &settings.accelerator_settings.gcn_hidden_size;
+        let features_settings = &settings.features_settings;
+        let cache_dir = settings.cache_dir.as_deref();
+        let encoding = settings.accelerator_settings.encoding_settings;
+        let dims: Vec<_> = std::iter::once(graph.get_feature_size())
+            .chain(gcn_hidden_size.iter().cloned())
+            .collect();
+        let load = |layer: usize, path: &str| {
+            let features = match path.starts_with("gen:") {
+                true => NodeFeatures::from_uri(path, graph.get_num_node(), dims[layer]),
+                false => NodeFeatures::load(path, features_settings.get_format(path), cache_dir),
+            };
+            features?.with_encoding(encoding, dims[layer])
+        };
+        if features_settings.source == FeaturesSource::Files {
+            return paths
+                .iter()
+                .enumerate()
+                .map(|(layer, path)| load(layer, path))
+                .try_collect();
+        }
+
+        let first_path = paths
+            .first()
+            .ok_or_else(|| GcnAggError::Config("no features path".into()))?;
+        let first = load(0, first_path)?;
+        let later = match features_settings.source {
+            FeaturesSource::Functional => {
+                let functional_settings = settings
+                    .accelerator_settings
+                    .functional_settings
+                    .as_ref()
+                    .ok_or_else(|| {
+                        GcnAggError::Config("the functional_settings is not provided".into())
+                    })?;
+                reference_node_features(graph, &first, gcn_hidden_size, functional_settings)?
+            }
+            _ => {
+                let mut rng = StdRng::seed_from_u64(features_settings.seed);
+                features_settings
+                    .densities
+                    .iter()
+                    .zip(gcn_hidden_size)
+                    .map(|(&density, &dim)| {
+                        NodeFeatures::random(graph.get_num_node(), dim, density, &mut rng)
+                    })
+                    .collect()
+            }
+        };
+        std::iter::once(Ok(first))
+            .chain(
+                later
+                    .into_iter()
+                    .zip(&dims[1..])
+                    .map(|(x, &dim)| x.with_encoding(encoding, dim)),
+            )
+            .try_collect()
+    }
+}
+/// the nonzeros of one node
+type Row = (Vec<usize>, Vec<f32>);
+
+/// # Description
+/// - sort the `(index, value)` of one node and drop the zeros
+/// # Errors
+/// - `InputFormat` if an index appears twice
+fn build_row(
+    file_name: &str,
+    line_number: Option<usize>,
+    mut elements: Vec<(usize, f32)>,
+) -> Result<Row, GcnAggError> {
+    elements.sort_unstable_by_key(|&(index, _)| index);
+    if let Some(x) = elements.windows(2).find(|x| x[0].0 == x[1].0) {
+        return Err(GcnAggError::input_format(
+            file_name,
+            line_number,
+            format!("duplicate index {}", x[0].0),
+        ));
+    }
+    Ok(elements
+        .into_iter()
+        .filter(|&(_, value)| value != 0.)
+        .unzip())
+}
+
+fn parse_token<T: std::str::FromStr>(
+    file_name: &str,
+    line_number: usize,
+    token: &str,
+) -> Result<T, GcnAggError>
+where
+    T::Err: std::fmt::Display,
+{
+    token.parse().map_err(|e| {
+        GcnAggError::input_format(
+            file_name,
+            Some(line_number),
+            format!("bad number {}: {}", token, e),
+        )
+    })
+}
+
+/// one line per node, `index:value` of each nonzero, the first token without `:` is the label
+fn parse_libsvm(file_name: &str, contents: &str) -> Result<Vec<Row>, GcnAggError> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let line_number = i + 1;
+            let elements = line
+                .split_whitespace()
+                .enumerate()
+                .filter(|(j, token)| *j != 0 || token.contains(':'))
+                .map(|(_, token)| {
+                    let (index, value) = token.split_once(':').ok_or_else(|| {
+                        GcnAggError::input_format(
+                            file_name,
+                            Some(line_number),
+                            format!("expect index:value, found {}", token),
+                        )
+                    })?;
+                    Ok::<_, GcnAggError>((
+                        parse_token(file_name, line_number, index)?,
+                        parse_token(file_name, line_number, value)?,
+                    ))
+                })
+                .try_collect()?;
+            build_row(file_name, Some(line_number), elements)
+        })
+        .try_collect()
+}
+
+/// one line per node, the indices of the nonzeros
+fn parse_index_list(file_name: &str, contents: &str) -> Result<Vec<Row>, GcnAggError> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let elements = line
+                .split_whitespace()
+                .map(|token| Ok::<_, GcnAggError>((parse_token(file_name, i + 1, token)?, 1.)))
+                .try_collect()?;
+            build_row(file_name, Some(i + 1), elements)
         })
+        .try_collect()
+}
+
+/// one `node index value` each line, the optional first line `nodes dim`
+fn parse_coo(file_name: &str, contents: &str) -> Result<Vec<Row>, GcnAggError> {
+    let mut num_nodes = None;
+    let mut elements: Vec<Vec<(usize, f32)>> = vec![];
+    let lines = contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or("")))
+        .filter(|(_, line)| !line.trim().is_empty());
+    for (line_number, line) in lines {
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        match tokens[..] {
+            [nodes, _dim] if num_nodes.is_none() && elements.is_empty() => {
+                let nodes = parse_token(file_name, line_number, nodes)?;
+                elements.resize(nodes, vec![]);
+                num_nodes = Some(nodes);
+            }
+            [node, index, value] => {
+                let node: usize = parse_token(file_name, line_number, node)?;
+                match num_nodes {
+                    Some(nodes) if node >= nodes => {
+                        return Err(GcnAggError::input_format(
+                            file_name,
+                            Some(line_number),
+                            format!("node {} is out of the {} nodes", node, nodes),
+                        ))
+                    }
+                    _ if node >= elements.len() => elements.resize(node + 1, vec![]),
+                    _ => {}
+                }
+                elements[node].push((
+                    parse_token(file_name, line_number, index)?,
+                    parse_token(file_name, line_number, value)?,
+                ));
+            }
+            _ => {
+                return Err(GcnAggError::input_format(
+                    file_name,
+                    Some(line_number),
+                    format!("expect node index value, found {}", line.trim()),
+                ))
+            }
+        }
     }
+    elements
+        .into_iter()
+        .map(|x| build_row(file_name, None, x))
+        .try_collect()
 }
+
+/// the rows of a 2d numpy array
+fn dense_array_rows(file_name: &str, array: &NpyArray) -> Result<Vec<Row>, GcnAggError> {
+    let dim = match array.shape[..] {
+        [_, dim] => dim,
+        _ => {
+            return Err(GcnAggError::input_format(
+                file_name,
+                None,
+                format!("expect a 2d array, found the shape {:?}", array.shape),
+            ))
+        }
+    };
+    let numbers = array.numbers(file_name)?;
+    match dim {
+        0 => Ok(vec![(vec![], vec![]); array.shape[0]]),
+        _ => numbers
+            .chunks(dim)
+            .map(|row| {
+                build_row(
+                    file_name,
+                    None,
+                    row.iter().map(|&x| x as f32).enumerate().collect(),
+                )
+            })
+            .try_collect(),
+    }
+}
+
+/// the rows of a numpy archive, the scipy csr matrix or one 2d array
+fn npz_rows(file_name: &str) -> Result<Vec<Row>, GcnAggError> {
+    let error = |message: String| GcnAggError::input_format(file_name, None, message);
+    let arrays = npy::read_npz(file_name)?;
+    if let Some(format) = arrays.get("format") {
+        match &format.data {
+            NpyData::Strings(x) if x.len() == 1 && x[0] == "csr" => {}
+            x => {
+                return Err(error(format!(
+                    "only the csr matrix is supported, found {:?}",
+                    x
+                )))
+            }
+        }
+    }
+    let get = |name: &str| {
+        arrays
+            .get(name)
+            .ok_or_else(|| error(format!("no {} in the csr matrix", name)))?
+            .numbers(file_name)
+    };
+    match (arrays.len(), arrays.get("indptr")) {
+        (_, Some(_)) => {
+            let (indptr, indices, data) = (get("indptr")?, get("indices")?, get("data")?);
+            let shape = get("shape")?;
+            let (num_nodes, dim) = match shape {
+                &[nodes, dim] => (nodes as usize, dim as usize),
+                x => return Err(error(format!("bad shape {:?}", x))),
+            };
+            if indptr.len() != num_nodes + 1
+                || indices.len() != data.len()
+                || indptr.windows(2).any(|x| x[0] > x[1])
+                || indptr.last().map(|&x| x as usize) != Some(indices.len())
+            {
+                return Err(error("the indptr does not match the indices".into()));
+            }
+            indptr
+                .windows(2)
+                .map(|x| {
+                    let elements: Vec<_> = (x[0] as usize..x[1] as usize)
+                        .map(|j| (indices[j] as usize, data[j] as f32))
+                        .collect();
+                    if let Some(&(index, _)) = elements.iter().find(|(index, _)| *index >= dim) {
+                        return Err(error(format!("index {} is out of the dim {}", index, dim)));
+                    }
+                    build_row(file_name, None, elements)
+                })
+                .try_collect()
+        }
+        (1, None) => {
+            let array = arrays.values().next().expect("there is one array");
+            dense_array_rows(file_name, array)
+        }
+        _ => Err(error(format!(
+            "expect one array or a csr matrix, found {:?}",
+            arrays.keys().sorted().collect_vec()
+        ))),
+    }
+}
+
 impl NodeFeatures {
     pub fn get_features(&self, node_id: usize) -> &Vec<usize> {
         &self.features[node_id]
     }
+    pub fn get_values(&self, node_id: usize) -> &Vec<f32> {
+        &self.values[node_id]
+    }
+    /// # Description
+    /// - the bytes of the sparse node `node_id` in the memory and the input buffer
+    pub fn get_node_bytes(&self, node_id: usize) -> usize {
+        (self.start_addrs[node_id + 1] - self.start_addrs[node_id]) as usize
+    }
+    pub fn get_encoding(&self) -> &EncodingSettings {
+        &self.encoding
+    }
     pub fn len(&self) -> usize {
         self.features.len()
     }
     pub fn is_empty(&self) -> bool {
         self.features.is_empty()
     }
+    /// # Description
+    /// - the total number of nonzero elements of all nodes
+    pub fn get_nnz(&self) -> usize {
+        self.features.iter().map(|x| x.len()).sum()
+    }
+    /// # Description
+    /// - the measured density of the features, `dim` is the dense dimension of each node
+    pub fn get_density(&self, dim: usize) -> f64 {
+        if self.features.is_empty() || dim == 0 {
+            return 0.;
+        }
+        self.get_nnz() as f64 / (self.features.len() * dim) as f64
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{
+        npy::test::npy_bytes,
+        settings::{run_length_entries, ElementType},
+    };
+    use std::error::Error;
     use std::fs::File;
     use std::io::Write;
 
@@ -144,8 +772,347 @@ mod test {
         assert_eq!(node_features.get_features(2)[1], 1);
         assert_eq!(node_features.get_features(2)[2], 5);
 
+        assert_eq!(node_features.get_nnz(), 9);
+        assert_eq!(node_features.values[1], vec![1.; 4]);
+        assert_eq!(node_features.get_density(6), 0.5);
+
         // delete the file
         std::fs::remove_file(file_name)?;
         Ok(())
     }
+
+    #[test]
+    fn test_binary_cache() -> Result<(), Box<dyn Error>> {
+        let file_name = "test_data/features_binary.txt";
+        std::fs::write(file_name, "0 1 0 1\n0 0 0 0\n1 1 0 0\n")?;
+        let cache_dir = "test_data/features_cache";
+        let _ = std::fs::remove_dir_all(cache_dir);
+
+        // parse and write the cache, then read the cache
+        for _ in 0..2 {
+            let node_features =
+                NodeFeatures::load(file_name, FeaturesFormat::Dense, Some(cache_dir))?;
+            assert_eq!(node_features.features, vec![vec![1, 3], vec![], vec![0, 1]]);
+            assert_eq!(node_features.values[0], vec![1., 1.]);
+            assert_eq!(node_features.start_addrs, vec![0, 16, 16, 32]);
+            assert_eq!(std::fs::read_dir(cache_dir)?.count(), 1);
+        }
+        // a changed file has a new cache, the values are saved when they are not all 1
+        std::fs::write(file_name, "0 -2.5 0 1\n")?;
+        let node_features = NodeFeatures::load(file_name, FeaturesFormat::Dense, Some(cache_dir))?;
+        assert_eq!(std::fs::read_dir(cache_dir)?.count(), 2);
+        let cache_path = binary::cache_path(cache_dir, file_name, "node features Dense")?;
+        let loaded = NodeFeatures::load_binary(&cache_path)?;
+        assert_eq!(loaded.values, node_features.values);
+        assert_eq!(loaded.values[0], vec![-2.5, 1.]);
+
+        // a broken cache is replaced
+        std::fs::write(&cache_path, "broken")?;
+        assert_eq!(
+            NodeFeatures::load(file_name, FeaturesFormat::Dense, Some(cache_dir))?.values[0],
+            vec![-2.5, 1.]
+        );
+        assert!(NodeFeatures::load_binary(&cache_path).is_ok());
+
+        std::fs::remove_dir_all(cache_dir)?;
+        std::fs::remove_file(file_name)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_formats() -> Result<(), Box<dyn Error>> {
+        let dir = test_dir("sparse_formats")?;
+        let file = |name: &str| format!("{}/{}", dir.display(), name);
+        let dense = "0 1 0 2.5\n0 0 0 0\n-1 0 0 1\n0 0 0 0\n";
+        let expected = NodeFeatures::from_dense(&[
+            vec![0., 1., 0., 2.5],
+            vec![0.; 4],
+            vec![-1., 0., 0., 1.],
+            vec![0.; 4],
+        ]);
+        let npy_data: Vec<u8> = [
+            0f32, 1., 0., 2.5, 0., 0., 0., 0., -1., 0., 0., 1., 0., 0., 0., 0.,
+        ]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+        let npy = npy_bytes("<f4", false, &[4, 4], &npy_data);
+        // the scipy csr matrix, the indices of node 2 are not sorted
+        let csr = [
+            ("format.npy", npy_bytes("|S3", false, &[], b"csr")),
+            (
+                "shape.npy",
+                npy_bytes(
+                    "<i8",
+                    false,
+                    &[2],
+                    &[4u8, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0],
+                ),
+            ),
+            (
+                "indptr.npy",
+                npy_bytes(
+                    "<i4",
+                    false,
+                    &[5],
+                    &[0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 4, 0, 0, 0, 4, 0, 0, 0],
+                ),
+            ),
+            (
+                "indices.npy",
+                npy_bytes(
+                    "<i4",
+                    false,
+                    &[4],
+                    &[1, 0, 0, 0, 3, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0],
+                ),
+            ),
+            (
+                "data.npy",
+                npy_bytes(
+                    "<f8",
+                    false,
+                    &[4],
+                    &[1f64, 2.5, 1., -1.]
+                        .iter()
+                        .flat_map(|x| x.to_le_bytes())
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+        ];
+        let write_npz =
+            |file_name: &str, arrays: &[(&str, Vec<u8>)], method| -> Result<(), Box<dyn Error>> {
+                let mut writer = zip::ZipWriter::new(File::create(file_name)?);
+                for (name, bytes) in arrays {
+                    writer.start_file(
+                        *name,
+                        zip::write::FileOptions::default().compression_method(method),
+                    )?;
+                    writer.write_all(bytes)?;
+                }
+                writer.finish()?;
+                Ok(())
+            };
+        write_npz(
+            &file("features_csr.npz"),
+            &csr,
+            zip::CompressionMethod::Deflated,
+        )?;
+        write_npz(
+            &file("features_dense.npz"),
+            &[("arr_0.npy", npy.clone())],
+            zip::CompressionMethod::Stored,
+        )?;
+        std::fs::write(file("features_formats.npy"), &npy)?;
+        std::fs::write(file("features_formats.txt"), dense)?;
+        // the labels are ignored, the explicit zero is dropped
+        std::fs::write(
+            file("features_formats.svm"),
+            "1 1:1 3:2.5\n0\n2 0:-1 3:1 2:0\n0\n",
+        )?;
+        std::fs::write(
+            file("features_formats.coo"),
+            "# nodes dim\n4 4\n0 3 2.5\n0 1 1\n2 0 -1\n2 3 1\n",
+        )?;
+
+        for path in [
+            &file("features_formats.txt"),
+            &file("features_formats.svm"),
+            &file("features_formats.coo"),
+            &file("features_formats.npy"),
+            &file("features_dense.npz"),
+            &file("features_csr.npz"),
+        ] {
+            let node_features = NodeFeatures::from_file(path, FeaturesFormat::from_path(path))?;
+            assert_eq!(node_features.features, expected.features, "{}", path);
+            assert_eq!(node_features.values, expected.values, "{}", path);
+            assert_eq!(node_features.start_addrs, expected.start_addrs, "{}", path);
+        }
+
+        // the index list has no values
+        std::fs::write(file("features_formats.idx"), "3 1\n\n0 3\n\n")?;
+        let node_features =
+            NodeFeatures::from_file(&file("features_formats.idx"), FeaturesFormat::IndexList)?;
+        assert_eq!(node_features.features, expected.features);
+        assert_eq!(node_features.values[2], vec![1., 1.]);
+
+        // without the header, the empty nodes at the end are lost
+        std::fs::write(file("features_formats.coo"), "0 1 1\n2 0 -1\n")?;
+        assert_eq!(
+            NodeFeatures::from_file(&file("features_formats.coo"), FeaturesFormat::Coo)?.len(),
+            3
+        );
+
+        for (format, contents) in [
+            (FeaturesFormat::Libsvm, "1 1:1 1:2\n"),
+            (FeaturesFormat::Libsvm, "1 1\n"),
+            (FeaturesFormat::IndexList, "1 x\n"),
+            (FeaturesFormat::Coo, "2 2\n2 0 1\n"),
+            (FeaturesFormat::Coo, "0 1\n0 1 1 1\n"),
+        ] {
+            std::fs::write(file("features_bad"), contents)?;
+            assert!(
+                matches!(
+                    NodeFeatures::from_file(&file("features_bad"), format),
+                    Err(GcnAggError::InputFormat { line: Some(_), .. })
+                ),
+                "{:?} {}",
+                format,
+                contents
+            );
+        }
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_dense_and_random() {
+        let rows = [vec![0., 1.5, 0.], vec![-2., 0., 3.]];
+        let node_features = NodeFeatures::from_dense(&rows);
+        assert_eq!(node_features.features, vec![vec![1], vec![0, 2]]);
+        assert_eq!(node_features.values, vec![vec![1.5], vec![-2., 3.]]);
+        assert_eq!(node_features.start_addrs, vec![0, 8, 24]);
+
+        // bf16 values with 16 bits indices
+        let encoding = EncodingSettings {
+            element_type: ElementType::Bf16,
+            index_bytes: 2,
+            sparse_encoding: SparseEncoding::Csr,
+        };
+        let encoded = node_features.with_encoding(encoding, 3).unwrap();
+        assert_eq!(encoded.start_addrs, vec![0, 4, 12]);
+        assert_eq!(encoded.get_node_bytes(1), 8);
+        assert_eq!(encoded.permute(&[1, 0]).start_addrs, vec![0, 8, 12]);
+        let wide = || NodeFeatures::from_rows(vec![vec![70000]], vec![vec![1.]]);
+        assert!(matches!(
+            wide().with_encoding(encoding, 70001),
+            Err(GcnAggError::Config(_))
+        ));
+
+        // 1 byte of the bitmap of 3 columns and the fp32 values
+        let bitmap = EncodingSettings {
+            sparse_encoding: SparseEncoding::Bitmap,
+            ..Default::default()
+        };
+        let encoded = NodeFeatures::from_dense(&rows)
+            .with_encoding(bitmap, 3)
+            .unwrap();
+        assert_eq!(encoded.start_addrs, vec![0, 5, 14]);
+        // the index is not stored
+        assert!(wide().with_encoding(bitmap, 70001).is_ok());
+        // the 1 byte run and the fp32 value of each nonzero
+        let run_length = EncodingSettings {
+            sparse_encoding: SparseEncoding::RunLength,
+            ..Default::default()
+        };
+        let encoded = NodeFeatures::from_dense(&rows)
+            .with_encoding(run_length, 3)
+            .unwrap();
+        assert_eq!(encoded.start_addrs, vec![0, 5, 15]);
+        // a run of 255 zeros fits in 1 byte, 256 zeros need an extra entry
+        assert_eq!(run_length_entries(&[255, 256]), 2);
+        assert_eq!(run_length_entries(&[256]), 2);
+        assert_eq!(run_length_entries(&[0, 600]), 4);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let node_features = NodeFeatures::random(100, 20, 0.25, &mut rng);
+        assert_eq!(node_features.len(), 100);
+        assert!((node_features.get_density(20) - 0.25).abs() < 0.05);
+        assert!(NodeFeatures::random(10, 20, 0., &mut rng).get_nnz() == 0);
+    }
+
+    #[test]
+    fn test_synthetic() -> Result<(), GcnAggError> {
+        let node_features =
+            NodeFeatures::from_uri("gen:features?dist=uniform&min=0.1&max=0.5&seed=1", 200, 50)?;
+        assert_eq!(node_features.len(), 200);
+        assert!(node_features.features.iter().flatten().all(|&x| x < 50));
+        let density = node_features.get_density(50);
+        assert!((density - 0.3).abs() < 0.05);
+        // the density differs between the nodes
+        let nnz = node_features.features.iter().map(|x| x.len());
+        assert!(nnz.clone().max().unwrap() - nnz.min().unwrap() > 10);
+
+        let node_features = NodeFeatures::from_uri("gen:features?density=1&dim=8", 3, 50)?;
+        assert_eq!(node_features.get_nnz(), 3 * 8);
+        let node_features =
+            NodeFeatures::from_uri("gen:features?dist=normal&mean=0.2&std=0&seed=2", 100, 50)?;
+        assert!((node_features.get_density(50) - 0.2).abs() < 0.05);
+
+        assert!(NodeFeatures::from_uri("gen:features?dist=normal&mean=0.2", 3, 8).is_err());
+        assert!(NodeFeatures::from_uri("gen:features?dist=zipf", 3, 8).is_err());
+        assert!(NodeFeatures::from_uri("gen:rmat?density=1", 3, 8).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_layers() -> Result<(), Box<dyn Error>> {
+        let dir = test_dir("load_layers")?;
+        let graph_path = format!("{}/graph.txt", dir.display());
+        let features_path = format!("{}/features.txt", dir.display());
+        std::fs::write(&graph_path, "f 6\n1 2\n0 2\n0 1\nend\n")?;
+        std::fs::write(&features_path, "1 0 0 2 0 0\n0 0.5 0 0 0 0\n0 0 1 1 1 1\n")?;
+        let common = format!(
+            "graph_path = {:?}\nfeatures_paths = [{:?}]\n[accelerator_settings]\ngcn_hidden_size = [16, 8]\n",
+            graph_path, features_path
+        );
+        let load = |name: &str, extra: &str| -> Result<Vec<NodeFeatures>, Box<dyn Error>> {
+            let config_name = format!("{}/{}.toml", dir.display(), name);
+            std::fs::write(&config_name, format!("{}{}", common, extra))?;
+            let settings = Settings::new(vec!["configs/default.toml".into(), config_name])?;
+            let graph = Graph::new(&settings.graph_path)?;
+            Ok(NodeFeatures::load_layers(&settings, &graph)?)
+        };
+
+        let layers = load(
+            "density",
+            "[features_settings]\nsource = \"Density\"\ndensities = [1.0, 0.0]\nseed = 3\n",
+        )?;
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[0].values[0], vec![1., 2.]);
+        assert_eq!(layers[1].get_nnz(), 3 * 16);
+        assert_eq!(layers[2].get_nnz(), 0);
+
+        let layers = load(
+            "functional",
+            "[features_settings]\nsource = \"Functional\"\ndensities = []\nseed = 0\n\
+             [accelerator_settings.functional_settings]\nweights_paths = []\noutput_dim = 4\nseed = 1\ntolerance = 1e-4\n",
+        )?;
+        assert_eq!(layers.len(), 3);
+        // the relu output is sparse, but not empty
+        for layer in &layers[1..] {
+            assert_eq!(layer.len(), 3);
+            assert!(layer.values.iter().flatten().all(|&x| x > 0.));
+        }
+        assert!(layers[1].get_nnz() > 0 && layers[1].get_nnz() < 3 * 16);
+
+        // the functional source needs the functional settings
+        assert!(load(
+            "no_functional",
+            "[features_settings]\nsource = \"Functional\"\ndensities = []\nseed = 0\n"
+        )
+        .is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bad_node_features() -> Result<(), Box<dyn Error>> {
+        let file_name = "test_data/node_features_bad.txt";
+        std::fs::write(file_name, "0 1 0\n1 x 1\n")?;
+        assert!(matches!(
+            NodeFeatures::new(file_name),
+            Err(GcnAggError::InputFormat { line: Some(2), .. })
+        ));
+        std::fs::remove_file(file_name)?;
+        Ok(())
+    }
+
+    /// an empty directory for the files of one test
+    fn test_dir(name: &str) -> Result<std::path::PathBuf, Box<dyn Error>> {
+        let dir = std::env::temp_dir().join(format!("gcn_agg_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
 }
@@ -0,0 +1,304 @@
+//! # Description
+//! - read the numpy `.npy` arrays and the `.npz` archives, only what the node features need
+//! - a npy file is the magic `\x93NUMPY`, the version, the header length, the header dict and the raw data
+//! - the numbers are the little endian bool, int, uint and float(f2, f4, f8), they are all read as f64
+//! - the strings(`S` and `U`) are only read for the format tag of the scipy sparse matrix
+//!
+
+use std::{collections::HashMap, fs::File, io::Read};
+
+use crate::error::GcnAggError;
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// # Description
+/// - the elements of a npy array in the C order
+#[derive(Debug, Clone, PartialEq)]
+pub enum NpyData {
+    Numbers(Vec<f64>),
+    Strings(Vec<String>),
+}
+
+/// # Description
+/// - a npy array, `shape` is empty for a scalar
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray {
+    pub shape: Vec<usize>,
+    pub data: NpyData,
+}
+
+impl NpyArray {
+    /// # Description
+    /// - the numbers of the array
+    /// # Errors
+    /// - `InputFormat` if the array is strings
+    pub fn numbers(&self, path: &str) -> Result<&[f64], GcnAggError> {
+        match &self.data {
+            NpyData::Numbers(x) => Ok(x),
+            NpyData::Strings(_) => Err(GcnAggError::input_format(
+                path,
+                None,
+                "expect a number array, found strings",
+            )),
+        }
+    }
+}
+
+/// # Description
+/// - read the `.npy` file `path`
+pub fn read_npy(path: &str) -> Result<NpyArray, GcnAggError> {
+    let bytes = std::fs::read(path).map_err(|e| GcnAggError::io(path, e))?;
+    parse_npy(path, &bytes)
+}
+
+/// # Description
+/// - read all arrays in the `.npz` file `path`, the key is the name without `.npy`
+pub fn read_npz(path: &str) -> Result<HashMap<String, NpyArray>, GcnAggError> {
+    let file = File::open(path).map_err(|e| GcnAggError::io(path, e))?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| GcnAggError::input_format(path, None, e))?;
+    let mut arrays = HashMap::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| GcnAggError::input_format(path, None, e))?;
+        let name = entry.name().trim_end_matches(".npy").to_string();
+        let mut bytes = vec![];
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| GcnAggError::io(path, e))?;
+        let array = parse_npy(&format!("{}:{}", path, entry.name()), &bytes)?;
+        arrays.insert(name, array);
+    }
+    Ok(arrays)
+}
+
+/// # Description
+/// - parse the bytes of a npy file, `path` is only used by the errors
+pub fn parse_npy(path: &str, bytes: &[u8]) -> Result<NpyArray, GcnAggError> {
+    let error = |message: String| GcnAggError::input_format(path, None, message);
+    if bytes.len() < 10 || &bytes[..6] != NPY_MAGIC {
+        return Err(error("not a npy file".into()));
+    }
+    let (header_len, header_start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        version => return Err(error(format!("unsupported npy version {}", version))),
+    };
+    let header = bytes
+        .get(header_start..header_start + header_len)
+        .and_then(|x| std::str::from_utf8(x).ok())
+        .ok_or_else(|| error("bad npy header".into()))?;
+    let descr = header_value(header, "descr")
+        .and_then(|x| x.strip_prefix('\''))
+        .and_then(|x| x.split('\'').next())
+        .ok_or_else(|| error(format!("no descr in the header {}", header)))?;
+    let fortran_order = header_value(header, "fortran_order")
+        .map(|x| x.starts_with("True"))
+        .ok_or_else(|| error(format!("no fortran_order in the header {}", header)))?;
+    let shape: Vec<usize> = header_value(header, "shape")
+        .and_then(|x| x.strip_prefix('('))
+        .and_then(|x| x.split(')').next())
+        .ok_or_else(|| error(format!("no shape in the header {}", header)))?
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse().map_err(|_| error(format!("bad shape {}", x))))
+        .collect::<Result<_, _>>()?;
+
+    let (byte_order, kind, size) = match descr.as_bytes() {
+        [order, kind, size @ ..] => (
+            *order,
+            *kind,
+            std::str::from_utf8(size)
+                .ok()
+                .and_then(|x| x.parse::<usize>().ok())
+                .ok_or_else(|| error(format!("bad descr {}", descr)))?,
+        ),
+        _ => return Err(error(format!("bad descr {}", descr))),
+    };
+    let item_size = match kind {
+        b'U' => size * 4,
+        _ => size,
+    };
+    if byte_order == b'>' && item_size > 1 && kind != b'S' {
+        return Err(error(format!("big endian {} is not supported", descr)));
+    }
+    let len: usize = shape.iter().product();
+    let data = &bytes[header_start + header_len..];
+    if data.len() != len * item_size {
+        return Err(error(format!(
+            "{} elements of {} need {} bytes, found {}",
+            len,
+            descr,
+            len * item_size,
+            data.len()
+        )));
+    }
+
+    let items = data.chunks_exact(item_size.max(1));
+    let data = match (kind, size) {
+        (b'S', _) => NpyData::Strings(
+            items
+                .map(|x| {
+                    String::from_utf8_lossy(x)
+                        .trim_end_matches('\0')
+                        .to_string()
+                })
+                .collect(),
+        ),
+        (b'U', _) => NpyData::Strings(
+            items
+                .map(|x| {
+                    x.chunks_exact(4)
+                        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                        .take_while(|&c| c != 0)
+                        .filter_map(char::from_u32)
+                        .collect()
+                })
+                .collect(),
+        ),
+        (b'b', 1) | (b'u', 1) => NpyData::Numbers(items.map(|x| x[0] as f64).collect()),
+        (b'i', 1) => NpyData::Numbers(items.map(|x| x[0] as i8 as f64).collect()),
+        (b'i' | b'u' | b'f', 2 | 4 | 8) => {
+            NpyData::Numbers(items.map(|x| parse_number(kind, x)).collect())
+        }
+        _ => return Err(error(format!("unsupported descr {}", descr))),
+    };
+    let data = match (fortran_order, shape.as_slice()) {
+        (true, &[rows, cols]) => transpose(data, rows, cols),
+        (true, x) if x.len() > 2 => {
+            return Err(error("the fortran order of more than 2 dims".into()))
+        }
+        _ => data,
+    };
+    Ok(NpyArray { shape, data })
+}
+
+/// the text after `'key':` in the header dict
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let pattern = format!("'{}':", key);
+    header
+        .find(&pattern)
+        .map(|x| header[x + pattern.len()..].trim_start())
+}
+
+/// one little endian number of 2, 4 or 8 bytes
+fn parse_number(kind: u8, bytes: &[u8]) -> f64 {
+    match (kind, bytes.len()) {
+        (b'i', 2) => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        (b'u', 2) => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        (b'f', 2) => f16_to_f64(u16::from_le_bytes([bytes[0], bytes[1]])),
+        (b'i', 4) => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (b'u', 4) => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (b'f', 4) => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (b'i', 8) => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (b'u', 8) => u64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        (b'f', 8) => f64::from_le_bytes(bytes.try_into().unwrap()),
+        _ => unreachable!("checked by parse_npy"),
+    }
+}
+
+/// the half precision float
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits >> 15 == 1 { -1. } else { 1. };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f64;
+    sign * match exponent {
+        0 => fraction * 2f64.powi(-24),
+        0x1f if fraction == 0. => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (1. + fraction / 1024.) * 2f64.powi(exponent - 15),
+    }
+}
+
+/// the fortran order `rows x cols` matrix in the C order
+fn transpose(data: NpyData, rows: usize, cols: usize) -> NpyData {
+    fn transpose<T: Clone>(data: Vec<T>, rows: usize, cols: usize) -> Vec<T> {
+        (0..rows)
+            .flat_map(|i| (0..cols).map(move |j| (i, j)))
+            .map(|(i, j)| data[j * rows + i].clone())
+            .collect()
+    }
+    match data {
+        NpyData::Numbers(x) => NpyData::Numbers(transpose(x, rows, cols)),
+        NpyData::Strings(x) => NpyData::Strings(transpose(x, rows, cols)),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// the npy file of version 1
+    pub(crate) fn npy_bytes(
+        descr: &str,
+        fortran_order: bool,
+        shape: &[usize],
+        data: &[u8],
+    ) -> Vec<u8> {
+        let shape = match shape {
+            [x] => format!("({},)", x),
+            x => format!(
+                "({})",
+                x.iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}",
+            descr,
+            if fortran_order { "True" } else { "False" },
+            shape
+        );
+        while (10 + header.len() + 1) % 64 != 0 {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_npy() -> Result<(), GcnAggError> {
+        let data: Vec<u8> = [1f32, 0., 2.5, -1., 0., 3.]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let array = parse_npy("c", &npy_bytes("<f4", false, &[2, 3], &data))?;
+        assert_eq!(array.shape, vec![2, 3]);
+        assert_eq!(array.numbers("c")?, &[1., 0., 2.5, -1., 0., 3.]);
+
+        // the same bytes in the fortran order are the columns
+        let array = parse_npy("f", &npy_bytes("<f4", true, &[3, 2], &data))?;
+        assert_eq!(array.numbers("f")?, &[1., -1., 0., 0., 2.5, 3.]);
+
+        let data: Vec<u8> = [3i64, -2].iter().flat_map(|x| x.to_le_bytes()).collect();
+        let array = parse_npy("i", &npy_bytes("<i8", false, &[2], &data))?;
+        assert_eq!(array.numbers("i")?, &[3., -2.]);
+        // 1.5 and -2 in half precision
+        let array = parse_npy(
+            "h",
+            &npy_bytes("<f2", false, &[2], &[0x00, 0x3e, 0x00, 0xc0]),
+        )?;
+        assert_eq!(array.numbers("h")?, &[1.5, -2.]);
+
+        let array = parse_npy("s", &npy_bytes("<U3", false, &[], b"c\0\0\0s\0\0\0r\0\0\0"))?;
+        assert_eq!(array.data, NpyData::Strings(vec!["csr".into()]));
+        assert!(array.numbers("s").is_err());
+
+        assert!(parse_npy("short", &npy_bytes("<f4", false, &[2, 3], &data)).is_err());
+        assert!(parse_npy("big", &npy_bytes(">f4", false, &[1], &[0; 4])).is_err());
+        assert!(parse_npy("magic", b"not a npy file").is_err());
+        Ok(())
+    }
+}
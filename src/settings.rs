@@ -6,37 +6,408 @@ use glob::glob;
 use itertools::Itertools;
 
 use serde::{Deserialize, Serialize};
-use std::{error::Error, string::String};
+use std::{path::Path, string::String};
+
+use crate::error::GcnAggError;
 
 /// # Description
 /// - struct for recording the settings of gcn accelerator.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub description: String,
+    /// the graph file, or a synthetic graph like `gen:rmat?scale=20&ef=16&seed=1`, see `graph::generators`
     pub graph_path: String,
+    /// the format of `graph_path`, guessed from the extension if `None`, see `GraphFormat::from_path`
+    pub graph_format: Option<GraphFormat>,
+    /// the input feature size, required by the graph formats without the `f feature_size` line
+    pub feature_size: Option<usize>,
+    /// cache the parsed graph and node features as binary files in this dir, no cache if `None`
+    pub cache_dir: Option<String>,
     pub features_paths: Vec<String>,
+    pub features_settings: FeaturesSettings,
+    pub preprocess: PreprocessSettings,
+    pub reorder: ReorderSettings,
     pub accelerator_settings: AcceleratorSettings,
 }
+
+/// # Description
+/// - the format of the graph file, every format builds the same graph: node `dst` aggregates node `src` for each edge
+/// - `Native`: the `f feature_size` line, then the input nodes of each node, ends with `end`
+/// - `EdgeList`: one `src dst` edge each line, `#` starts a comment(the SNAP format), the node ids start from 0
+/// - `MatrixMarket`: the coordinate `.mtx` file, the entry `row col` is the edge from `row` to `col`, the ids start from 1
+/// - `Metis`: the METIS graph file, line `i` lists the neighbors of node `i`, the ids start from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GraphFormat {
+    Native,
+    EdgeList,
+    MatrixMarket,
+    Metis,
+}
+
+impl GraphFormat {
+    /// # Description
+    /// - guess the format from the extension of `path`
+    /// - `.mtx` is `MatrixMarket`, `.metis` is `Metis`, `.edges`, `.el` and `.edgelist` are `EdgeList`, the others are `Native`
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_lowercase());
+        match extension.as_deref() {
+            Some("mtx") => GraphFormat::MatrixMarket,
+            Some("metis") => GraphFormat::Metis,
+            Some("edges" | "el" | "edgelist") => GraphFormat::EdgeList,
+            _ => GraphFormat::Native,
+        }
+    }
+}
+
+/// # Description
+/// - the format of the node features files, each node is a row and the column indices start from 0
+/// - `Dense`: one line per node, every element is written out, e.g. `0 1.5 0 1`
+/// - `Libsvm`: one line per node, `index:value` of each nonzero, the leading label without `:` is ignored
+/// - `IndexList`: one line per node, the indices of the nonzeros, the values are 1
+/// - `Coo`: one `node index value` triplet each line, `#` starts a comment,
+///   the optional first line `nodes dim` keeps the empty nodes at the end
+/// - `Npy`: the 2d numpy array, each row is a node
+/// - `Npz`: the numpy archive of one 2d array, or the scipy csr matrix of `scipy.sparse.save_npz`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeaturesFormat {
+    Dense,
+    Libsvm,
+    IndexList,
+    Coo,
+    Npy,
+    Npz,
+}
+
+impl FeaturesFormat {
+    /// # Description
+    /// - guess the format from the extension of `path`
+    /// - `.svm` and `.libsvm` are `Libsvm`, `.idx` is `IndexList`, `.coo` is `Coo`, `.npy` is `Npy`, `.npz` is `Npz`,
+    ///   the others are `Dense`
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_lowercase());
+        match extension.as_deref() {
+            Some("svm" | "libsvm") => FeaturesFormat::Libsvm,
+            Some("idx") => FeaturesFormat::IndexList,
+            Some("coo") => FeaturesFormat::Coo,
+            Some("npy") => FeaturesFormat::Npy,
+            Some("npz") => FeaturesFormat::Npz,
+            _ => FeaturesFormat::Dense,
+        }
+    }
+}
+
+/// # Description
+/// - where the node features of the layers after the first one come from
+/// - `Files`: one file for each layer in `features_paths`
+/// - `Functional`: the nonzeros of the relu output of the functional simulation, needs `functional_settings`
+/// - `Density`: random features, each element is nonzero with the density of its layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeaturesSource {
+    Files,
+    Functional,
+    Density,
+}
+
+/// # Description
+/// - struct for recording how the node features of each layer are built.
+/// - only the first file in `features_paths` is read when `source` is not `Files`
+/// - `densities`: the density of the input features of layer 1, 2, ..., only used by `Density`
+/// - `seed`: the seed of the random features, only used by `Density`
+/// - `format`: the format of all files in `features_paths`, guessed from the extension of each file if `None`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeaturesSettings {
+    pub source: FeaturesSource,
+    pub densities: Vec<f64>,
+    pub seed: u64,
+    pub format: Option<FeaturesFormat>,
+}
+
+impl FeaturesSettings {
+    /// # Description
+    /// - the format of the features file `path`, `format` if set, otherwise guessed from the extension
+    pub fn get_format(&self, path: &str) -> FeaturesFormat {
+        self.format
+            .unwrap_or_else(|| FeaturesFormat::from_path(path))
+    }
+}
+/// # Description
+/// - the normalization of the aggregation, `d` is the degree after the preprocessing
+/// - `None`: `A·X`, the adjacency is used verbatim
+/// - `Symmetric`: `D^-1/2·A·D^-1/2·X`, the input node `j` of node `i` is scaled by `1/sqrt(d_in(i)·d_out(j))`
+/// - `Row`: `D^-1·A·X`, the mean of the input nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Normalization {
+    None,
+    Symmetric,
+    Row,
+}
+
+/// # Description
+/// - struct for recording how the graph is changed after loading, see `graph::preprocess`
+/// - `self_loops`: every node aggregates itself, `A+I`
+/// - `symmetrize`: add the reverse of each edge, for the directed inputs
+/// - `dedup`: merge the duplicate edges, including the added ones, otherwise the graph is simulated as a multigraph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreprocessSettings {
+    pub self_loops: bool,
+    pub symmetrize: bool,
+    pub dedup: bool,
+    pub normalization: Normalization,
+}
+
+/// # Description
+/// - the pass to renumber the nodes before the simulation, see `graph::reorder`
+/// - `Degree`: the nodes with more input nodes first
+/// - `Rcm`: reverse Cuthill–McKee, reduce the bandwidth of the adjacency matrix
+/// - `Bfs`: the breadth first order
+/// - `Rabbit`: the nodes of one community are consecutive, like the rabbit order
+/// - `Random`: a random shuffle from the seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReorderMethod {
+    None,
+    Degree,
+    Rcm,
+    Bfs,
+    Rabbit,
+    Random,
+}
+
+/// # Description
+/// - struct for recording how the nodes are renumbered, `seed` is only used by `Random`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReorderSettings {
+    pub method: ReorderMethod,
+    pub seed: u64,
+}
+
+/// # Description
+/// - the dataflow of the aggregation
+/// - `Mixed` will choose `Sparse` or `Dense` for each layer or each output window, see `MixedSettings`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RunningMode {
     Sparse,
     Dense,
     Mixed,
 }
 
+/// # Description
+/// - the granularity of the decision in mixed mode
+/// - `Layer`: the whole layer use the same dataflow
+/// - `Window`: each output window decide its own dataflow, the previous layer writes back each node in the
+///   dataflows of the windows that read it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MixedGranularity {
+    Layer,
+    Window,
+}
+
+/// # Description
+/// - the type of each feature value in the memory and the buffers
+/// - the functional simulation still computes in f32, only the sizes are changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElementType {
+    Int8,
+    Fp16,
+    Bf16,
+    #[default]
+    Fp32,
+}
+
+impl ElementType {
+    /// # Description
+    /// - the bytes of one value
+    pub fn bytes(self) -> usize {
+        match self {
+            ElementType::Int8 => 1,
+            ElementType::Fp16 | ElementType::Bf16 => 2,
+            ElementType::Fp32 => 4,
+        }
+    }
+}
+
+/// # Description
+/// - how a sparse node is stored, the sparsifier produces the output features in it
+/// - `Csr`: the column index(`index_bytes`) and the value of each nonzero
+/// - `Bitmap`: one bit for each of the `dim` columns, then the values of the nonzeros
+/// - `RunLength`: the number of zeros before each nonzero in 1 byte and the value, a run longer than 255 adds
+///   an entry of 255 zeros and a zero value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SparseEncoding {
+    #[default]
+    Csr,
+    Bitmap,
+    RunLength,
+}
+
+/// # Description
+/// - struct for recording how the node features are encoded, see `NodeFeatures::with_encoding`
+/// - a dense node is `dim` values, a sparse node is encoded by `sparse_encoding`
+/// - `index_bytes`: the bytes of the column index of `Csr`, 2 or 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodingSettings {
+    pub element_type: ElementType,
+    pub index_bytes: usize,
+    pub sparse_encoding: SparseEncoding,
+}
+
+impl Default for EncodingSettings {
+    fn default() -> Self {
+        EncodingSettings {
+            element_type: ElementType::Fp32,
+            index_bytes: 4,
+            sparse_encoding: SparseEncoding::Csr,
+        }
+    }
+}
+
+impl EncodingSettings {
+    /// # Description
+    /// - the bytes of one dense element
+    pub fn element_bytes(&self) -> usize {
+        self.element_type.bytes()
+    }
+
+    /// # Description
+    /// - the bytes of one sparse element, the index and the value
+    pub fn sparse_element_bytes(&self) -> usize {
+        self.index_bytes + self.element_bytes()
+    }
+
+    /// # Description
+    /// - the bytes of a sparse node of `dim` columns, `indices` are the sorted columns of the nonzeros
+    pub fn sparse_node_bytes(&self, indices: &[usize], dim: usize) -> usize {
+        match self.sparse_encoding {
+            SparseEncoding::Csr => indices.len() * self.sparse_element_bytes(),
+            SparseEncoding::Bitmap => dim.div_ceil(8) + indices.len() * self.element_bytes(),
+            SparseEncoding::RunLength => run_length_entries(indices) * (1 + self.element_bytes()),
+        }
+    }
+
+    /// # Description
+    /// - the largest column index that can be encoded
+    pub fn max_index(&self) -> usize {
+        match self.index_bytes {
+            2 => u16::MAX as usize,
+            _ => u32::MAX as usize,
+        }
+    }
+}
+
+/// # Description
+/// - the entries of the run-length encoding of the sorted `indices`, see `SparseEncoding::RunLength`
+pub fn run_length_entries(indices: &[usize]) -> usize {
+    let mut next = 0;
+    indices
+        .iter()
+        .map(|&i| {
+            let run = i - next;
+            next = i + 1;
+            1 + run / 256
+        })
+        .sum()
+}
+
+/// # Description
+/// - struct for recording how the tensors are placed in the memory, see `accelerator::MemoryLayout`
+/// - `alignment`: the start of each region is aligned to this many bytes, a multiple of 64
+/// - `row_padding`: start each node at a 64 bytes boundary, so a node never shares a request with another one
+/// - `channels`: spread the addresses over this many channels, each `interleave_bytes` goes to the next channel,
+///   channel `i` owns the `i`th slice of the address space; 1 keeps the addresses linear
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutSettings {
+    pub alignment: u64,
+    pub row_padding: bool,
+    pub channels: u64,
+    pub interleave_bytes: u64,
+}
+
 /// # Description
 /// - struct for recording the settings of gcn accelerator.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AcceleratorSettings {
     pub input_buffer_size: usize,
     pub agg_buffer_size: usize,
+    /// the bytes of the weights on chip, the weights of a layer larger than it are streamed in tiles of the half
+    pub weight_buffer_size: usize,
     // pub output_buffer_size: usize,
     pub gcn_hidden_size: Vec<usize>,
     pub aggregator_settings: AggregatorSettings,
     pub mlp_settings: MlpSettings,
     pub sparsifier_settings: SparsifierSettings,
     pub running_mode: RunningMode,
+    pub mixed_settings: MixedSettings,
+    pub encoding_settings: EncodingSettings,
+    pub layout_settings: LayoutSettings,
+    pub memory_settings: MemorySettings,
     pub mem_config_name: String,
+    /// skip the cycles that all components are just counting down, the result is the same
+    pub fast_forward: bool,
+    /// write the spans of the pipeline to this file as chrome trace-event json, no trace if `None`
+    pub trace_path: Option<String>,
+    pub deadlock_settings: DeadlockSettings,
+    /// compute the real values and check them with the reference, no functional simulation if `None`
+    pub functional_settings: Option<FunctionalSettings>,
+}
+
+/// # Description
+/// - the memory model used by the memory interface
+/// - `Ramulator`: the dram simulator, configured by `mem_config_name`
+/// - `FixedLatency`: fixed latency and fixed bandwidth
+/// - `Ideal`: zero latency and unlimited bandwidth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryBackendType {
+    Ramulator,
+    FixedLatency,
+    Ideal,
+}
+
+/// # Description
+/// - struct for recording the settings of the memory backend.
+/// - `latency` and `bandwidth`(requests of 64 bytes per cycle) are only used by `FixedLatency`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemorySettings {
+    pub backend: MemoryBackendType,
+    pub latency: u64,
+    pub bandwidth: usize,
+}
+
+/// # Description
+/// - struct for recording the settings of the deadlock detection.
+/// - a deadlock is reported when no handler is triggered for `threshold` cycles, 0 disables the detection
+/// - the snapshot of all components is written to `snapshot_path` when a deadlock is found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadlockSettings {
+    pub threshold: u64,
+    pub snapshot_path: Option<String>,
+}
+
+/// # Description
+/// - struct for recording the settings of the functional simulation.
+/// - `weights_paths`: the weight file of each layer, each line is a row of the weight, random weights if empty
+/// - `output_dim`: the output dimension of the final layer, e.g. the number of classes
+/// - `seed`: the seed of the random weights
+/// - `tolerance`: the max error allowed, relative to the reference value(or absolute when the value is less than 1)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionalSettings {
+    pub weights_paths: Vec<String>,
+    pub output_dim: usize,
+    pub seed: u64,
+    pub tolerance: f32,
+}
+
+/// # Description
+/// - struct for recording the settings of the mixed running mode.
+/// - the dense dataflow is used when the measured feature density is not less than `dense_threshold`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MixedSettings {
+    pub dense_threshold: f64,
+    pub granularity: MixedGranularity,
 }
 
 /// # Description
@@ -72,8 +443,8 @@ impl Settings {
     /// # Arguments
     /// - `config_path`: the vec of paths of the config file with surfix `.toml`.
     /// # Return
-    /// - `Result<Settings, ConfigError>`: the settings of gcn accelerator.
-    pub fn new(config_path: Vec<String>) -> Result<Self, Box<dyn Error>> {
+    /// - `Result<Settings, GcnAggError>`: the settings of gcn accelerator.
+    pub fn new(config_path: Vec<String>) -> Result<Self, GcnAggError> {
         let input_files = config_path.iter().map(|x| File::with_name(x)).collect_vec();
         let default_files: Vec<_> = glob("configs/user_configs/*.toml")?
             .map_ok(File::from)
@@ -85,11 +456,93 @@ impl Settings {
             .build()?
             .try_deserialize()?;
 
-        match result.features_paths.len() - result.accelerator_settings.gcn_hidden_size.len() {
-            1 => Ok(result),
-            _ => Err(
-                "the number of features paths is not equal to the number of gcn hidden size".into(),
-            ),
+        result.check_graph()?;
+        result.check_features()?;
+        result.check_encoding()?;
+        Ok(result)
+    }
+
+    /// # Description
+    /// - replace the graph of the configs by `graph_path`, its format is guessed from the extension
+    /// # Errors
+    /// - `Config` if the graph needs the feature_size but it's not set
+    pub fn set_graph_path(&mut self, graph_path: &str) -> Result<(), GcnAggError> {
+        self.graph_path = graph_path.to_string();
+        self.graph_format = None;
+        self.check_graph()
+    }
+
+    /// # Description
+    /// - the format of `graph_path`, `graph_format` if set, otherwise guessed from the extension
+    pub fn get_graph_format(&self) -> GraphFormat {
+        self.graph_format
+            .unwrap_or_else(|| GraphFormat::from_path(&self.graph_path))
+    }
+
+    /// # Description
+    /// - check the feature size is provided when the graph file does not have it
+    fn check_graph(&self) -> Result<(), GcnAggError> {
+        let has_feature_size =
+            !self.graph_path.starts_with("gen:") && self.get_graph_format() == GraphFormat::Native;
+        match has_feature_size || self.feature_size.is_some() {
+            true => Ok(()),
+            false => Err(GcnAggError::Config(format!(
+                "the graph {} needs the feature_size",
+                self.graph_path
+            ))),
+        }
+    }
+
+    /// # Description
+    /// - check the index of the sparse elements is 16 or 32 bits
+    fn check_encoding(&self) -> Result<(), GcnAggError> {
+        match self.accelerator_settings.encoding_settings.index_bytes {
+            2 | 4 => Ok(()),
+            x => Err(GcnAggError::Config(format!(
+                "the index_bytes should be 2 or 4, but it's {}",
+                x
+            ))),
+        }
+    }
+
+    /// # Description
+    /// - check the features settings can build the node features of every layer
+    fn check_features(&self) -> Result<(), GcnAggError> {
+        let num_hidden = self.accelerator_settings.gcn_hidden_size.len();
+        let FeaturesSettings {
+            source, densities, ..
+        } = &self.features_settings;
+        match source {
+            FeaturesSource::Files if self.features_paths.len() != num_hidden + 1 => {
+                Err(GcnAggError::Config(
+                    "the number of features paths is not equal to the number of gcn hidden size"
+                        .into(),
+                ))
+            }
+            _ if self.features_paths.is_empty() => Err(GcnAggError::Config(
+                "the features of the first layer is not provided".into(),
+            )),
+            FeaturesSource::Functional
+                if self.accelerator_settings.functional_settings.is_none() =>
+            {
+                Err(GcnAggError::Config(
+                    "the functional features source needs the functional_settings".into(),
+                ))
+            }
+            FeaturesSource::Density if densities.len() != num_hidden => {
+                Err(GcnAggError::Config(format!(
+                    "there are {} densities, but {} gcn hidden sizes",
+                    densities.len(),
+                    num_hidden
+                )))
+            }
+            FeaturesSource::Density if densities.iter().any(|x| !(0. ..=1.).contains(x)) => {
+                Err(GcnAggError::Config(format!(
+                    "the densities should be in [0, 1]: {:?}",
+                    densities
+                )))
+            }
+            _ => Ok(()),
         }
     }
 }
@@ -106,4 +559,17 @@ mod tests {
         println!("{}", json);
         Ok(())
     }
+
+    #[test]
+    fn test_graph_format() {
+        use super::GraphFormat;
+        assert_eq!(GraphFormat::from_path("a/b.mtx"), GraphFormat::MatrixMarket);
+        assert_eq!(GraphFormat::from_path("b.METIS"), GraphFormat::Metis);
+        assert_eq!(GraphFormat::from_path("b.edges"), GraphFormat::EdgeList);
+        assert_eq!(
+            GraphFormat::from_path("graphs/test.graph"),
+            GraphFormat::Native
+        );
+        assert_eq!(GraphFormat::from_path("graph"), GraphFormat::Native);
+    }
 }
@@ -1,11 +1,11 @@
 mod common;
 use chrono::Local;
-use gcn_agg::{
-    accelerator::System, gcn_result::GcnAggResult, graph::Graph, node_features::NodeFeatures,
-    settings::Settings,
-};
+#[cfg(feature = "ramulator")]
+use gcn_agg::gcn_result::GcnAggResult;
+use gcn_agg::{accelerator::System, graph::Graph, node_features::NodeFeatures, settings::Settings};
 use itertools::Itertools;
 
+#[cfg(feature = "ramulator")]
 #[test]
 fn test_system() -> Result<(), Box<dyn std::error::Error>> {
     std::fs::create_dir_all("output")?;
@@ -37,7 +37,7 @@ fn test_system() -> Result<(), Box<dyn std::error::Error>> {
         &node_features,
         settings.accelerator_settings,
         &mem_stat_path,
-    );
+    )?;
 
     // run the system
     let mut stat = system.run()?;
@@ -59,6 +59,7 @@ fn test_system() -> Result<(), Box<dyn std::error::Error>> {
     std::fs::write(output_path, serde_json::to_string_pretty(&results)?)?;
     Ok(())
 }
+#[cfg(feature = "ramulator")]
 #[test]
 fn test_system_dense() -> Result<(), Box<dyn std::error::Error>> {
     std::fs::create_dir_all("output")?;
@@ -93,7 +94,7 @@ fn test_system_dense() -> Result<(), Box<dyn std::error::Error>> {
         &node_features,
         settings.accelerator_settings,
         &mem_stat_path,
-    );
+    )?;
 
     // run the system
     let mut stat = system.run()?;
@@ -115,3 +116,60 @@ fn test_system_dense() -> Result<(), Box<dyn std::error::Error>> {
     std::fs::write(output_path, serde_json::to_string_pretty(&results)?)?;
     Ok(())
 }
+#[test]
+fn test_system_mixed() -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all("output")?;
+    let current_time: String = Local::now().format("%Y-%m-%d-%H-%M-%S%.6f").to_string();
+
+    simple_logger::init_with_level(log::Level::Info).unwrap_or(());
+
+    let settings = Settings::new(vec![
+        "configs/default.toml".into(),
+        "configs/optional_configs/mixed.toml".into(),
+        "configs/optional_configs/fixed_latency_memory.toml".into(),
+    ])
+    .unwrap();
+
+    let graph = Graph::new(settings.graph_path.as_str())?;
+    let node_features: Vec<_> = settings
+        .features_paths
+        .iter()
+        .map(|x| NodeFeatures::new(x.as_str()))
+        .try_collect()?;
+    let mem_stat_path = format!("output/{}_mixed_mem_stat.txt", current_time);
+    let mut system = System::new(
+        &graph,
+        &node_features,
+        settings.accelerator_settings,
+        &mem_stat_path,
+    )?;
+
+    let stat = system.run()?;
+    assert!(system.finished());
+    assert!(stat.cycle > 0);
+    Ok(())
+}
+#[test]
+fn test_system_ideal_memory() -> Result<(), Box<dyn std::error::Error>> {
+    simple_logger::init_with_level(log::Level::Info).unwrap_or(());
+
+    let settings = Settings::new(vec![
+        "configs/default.toml".into(),
+        "configs/optional_configs/ideal_memory.toml".into(),
+    ])
+    .unwrap();
+
+    let graph = Graph::new(settings.graph_path.as_str())?;
+    let node_features: Vec<_> = settings
+        .features_paths
+        .iter()
+        .map(|x| NodeFeatures::new(x.as_str()))
+        .try_collect()?;
+    // the ideal memory do not write any stats file
+    let mut system = System::new(&graph, &node_features, settings.accelerator_settings, "")?;
+
+    let stat = system.run()?;
+    assert!(system.finished());
+    assert!(stat.cycle > 0);
+    Ok(())
+}